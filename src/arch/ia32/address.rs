@@ -1,3 +1,4 @@
+#[cfg(target_arch = "x86_64")]
 use crate::arch::ia32e::interrupts::frame::InterruptStackFrame;
 use core::fmt;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
@@ -34,11 +35,13 @@ impl VirtualAddress {
     }
 
     /// Convert a x86-interrupt into an ia32e virtual address.
+    #[cfg(target_arch = "x86_64")]
     pub fn from_handler(src: extern "x86-interrupt" fn(InterruptStackFrame)) -> Self {
         Self(src as *const () as u64)
     }
 
     /// Convert a x86-interrupt into an ia32e virtual address.
+    #[cfg(target_arch = "x86_64")]
     pub fn from_handler_with_err(src: extern "x86-interrupt" fn(InterruptStackFrame, u64)) -> Self {
         Self(src as *const () as u64)
     }
@@ -99,7 +102,7 @@ impl fmt::Display for VirtualAddress {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PhysicalAddress(u64);
 
 impl PhysicalAddress {
@@ -135,6 +138,12 @@ impl PhysicalAddress {
     }
 }
 
+impl From<PhysicalAddress> for u64 {
+    fn from(address: PhysicalAddress) -> u64 {
+        address.0
+    }
+}
+
 impl Add for PhysicalAddress {
     type Output = Self;
 
@@ -169,6 +178,12 @@ impl fmt::Debug for PhysicalAddress {
     }
 }
 
+impl fmt::Display for PhysicalAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#X}", self.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,61 @@
+//! Multiboot entry used to boot the 32 bits kernel, the `bootloader` crate
+//! only supports IA-32e kernels.
+//!
+//! The header is recognized by QEMU's `-kernel` option and by GRUB, both
+//! leave the processor in protected mode with paging disabled, without any
+//! stack, the magic value in EAX and the boot information address in EBX
+//! (cf. Multiboot specification 0.6.96, 3.2).
+use core::arch::global_asm;
+
+/// Value passed by a multiboot compliant loader in EAX.
+pub const BOOTLOADER_MAGIC: u32 = 0x2BAD_B002;
+
+global_asm!(
+    ".section .multiboot, \"a\"",
+    ".balign 4",
+    // Header magic value.
+    ".long 0x1BADB002",
+    // No flags: an ELF kernel does not need the address fields.
+    ".long 0",
+    // Checksum, magic and flags must sum to zero.
+    ".long -0x1BADB002",
+    "",
+    ".section .bss.boot_stack, \"aw\", @nobits",
+    ".balign 16",
+    "boot_stack_bottom:",
+    // 64 KiB stack used until the kernel sets up its own.
+    ".skip 65536",
+    "boot_stack_top:",
+    "",
+    ".section .text.multiboot_start, \"ax\"",
+    ".global multiboot_start",
+    "multiboot_start:",
+    "mov esp, offset boot_stack_top",
+    "push ebx",
+    "push eax",
+    "call flint_multiboot_entry",
+    "2:",
+    "hlt",
+    "jmp 2b",
+);
+
+/// Define the kernel's entry function when booted by a multiboot loader,
+/// the function must have the `fn() -> !` signature.
+///
+/// The generated entry panics if the kernel was not started by a multiboot
+/// compliant loader.
+#[macro_export]
+macro_rules! multiboot_entry {
+    ($path:path) => {
+        #[export_name = "flint_multiboot_entry"]
+        pub extern "C" fn __impl_multiboot_entry(magic: u32, _info: u32) -> ! {
+            let f: fn() -> ! = $path;
+            assert_eq!(
+                magic,
+                $crate::arch::ia32::boot::BOOTLOADER_MAGIC,
+                "Kernel was not loaded by a multiboot bootloader"
+            );
+            f()
+        }
+    };
+}
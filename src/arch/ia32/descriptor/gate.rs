@@ -45,6 +45,25 @@ impl From<GateSize> for bool {
 #[repr(C, packed)]
 pub struct Gate(u64);
 
+impl Gate {
+    /// Create a new non present [`Gate`].
+    pub const fn const_default() -> Self {
+        Gate(0)
+    }
+
+    /// Whether the gate's present bit is set.
+    pub fn is_present(&self) -> bool {
+        let internal = self.0;
+        internal.get_bit(47)
+    }
+}
+
+impl Default for Gate {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
 impl fmt::Display for Gate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let internal = self.0;
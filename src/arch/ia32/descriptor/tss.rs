@@ -5,6 +5,7 @@ use super::Granularity;
 use crate::arch::ia32::PrivilegeLevel;
 use crate::utils::bitfield::*;
 use configuration::Configuration;
+use core::fmt;
 use permissions::Permissions;
 
 mod configuration;
@@ -13,7 +14,7 @@ mod permissions;
 /// A task state segment descriptor structure that can be used directly by the
 /// processor to describe a task state segment.
 #[must_use]
-#[derive(Default, Copy, Clone)]
+#[derive(Copy, Clone)]
 #[repr(C, packed)]
 pub struct TssDescriptor {
     /// Bits 0 to 15 of the segment limit.
@@ -31,6 +32,18 @@ pub struct TssDescriptor {
 }
 
 impl TssDescriptor {
+    /// Create a new non present [`TssDescriptor`].
+    pub const fn const_default() -> Self {
+        TssDescriptor {
+            limit_15_0: 0,
+            base_15_0: 0,
+            base_23_16: 0,
+            permissions: Permissions::const_default(),
+            configuration: Configuration::const_default(),
+            base_31_24: 0,
+        }
+    }
+
     /// Creates a new [`TssDescriptor`] from a base address and a segment limit.
     ///
     /// # Arguments
@@ -115,6 +128,44 @@ impl TssDescriptor {
             ..self
         }
     }
+
+    /// Get the whole reassembled base address from a [`TssDescriptor`]
+    /// fields.
+    pub fn get_address(&self) -> u32 {
+        u32::from(self.base_31_24) << 24
+            | u32::from(self.base_23_16) << 16
+            | u32::from(self.base_15_0)
+    }
+
+    /// Get the whole reassembled segment limit from a [`TssDescriptor`]
+    /// fields.
+    pub fn get_limit(&self) -> u32 {
+        u32::from(self.configuration.get_limit()) << 16 | u32::from(self.limit_15_0)
+    }
+
+    /// Format the configuration and permissions of a [`TssDescriptor`],
+    /// without its base address and limit.
+    pub(crate) fn fmt_attributes(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.configuration, self.permissions)
+    }
+}
+
+impl Default for TssDescriptor {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+impl fmt::Display for TssDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Base address: {}\nLimit: {}",
+            self.get_address(),
+            self.get_limit()
+        )?;
+        self.fmt_attributes(f)
+    }
 }
 
 #[cfg(test)]
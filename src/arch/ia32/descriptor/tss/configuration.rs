@@ -11,7 +11,7 @@ use core::fmt;
 /// - Segment limit bits 16 to 19
 /// - Available bit (AVL)
 /// - Granularity bit (G)
-#[derive(Default, Copy, Clone)]
+#[derive(Copy, Clone)]
 pub struct Configuration(u8);
 
 /// The set of all field offsets for the [`Configuration`] structure.
@@ -24,7 +24,18 @@ mod offset {
     pub const G: usize = 7;
 }
 
+impl Default for Configuration {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
 impl Configuration {
+    /// Create a new [`Configuration`] with every field cleared.
+    pub const fn const_default() -> Self {
+        Configuration(0)
+    }
+
     /// Get the bits (19:16) of the limit value stored in the configuration
     /// field.
     pub fn get_limit(&self) -> u8 {
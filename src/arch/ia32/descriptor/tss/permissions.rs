@@ -37,14 +37,20 @@ mod offset {
 
 impl Default for Permissions {
     fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+impl Permissions {
+    /// Create a new non present [`Permissions`] for an available task state
+    /// segment.
+    pub const fn const_default() -> Self {
         // We set up tss specific bits as specified by the Intel manual.
         // Those bits will allow the processor to identify the kind of
         // descriptor.
         Permissions(0b00001001)
     }
-}
 
-impl Permissions {
     /// Change a [`Permissions`]'s busy bit.
     ///
     /// # Arguments
@@ -1,5 +1,6 @@
 use core::arch::asm;
 
+pub mod frame;
 pub mod idt;
 pub mod pic;
 pub mod pit;
@@ -25,6 +26,39 @@ pub unsafe fn enable() {
     asm!("sti");
 }
 
+/// Whether maskable external interrupts are enabled, reading the interrupt
+/// enable flag (IF) of the flags register.
+pub fn are_enabled() -> bool {
+    let flags: usize;
+    unsafe {
+        asm!("pushf", "pop {}", out(reg) flags, options(nomem, preserves_flags));
+    }
+    flags & (1 << 9) != 0
+}
+
+/// Run a function with maskable external interrupts disabled, restoring the
+/// previous interrupt state afterwards.
+///
+/// # Arguments
+///
+/// * `f` - The function to run.
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let enabled = are_enabled();
+    unsafe {
+        disable();
+    }
+    let result = f();
+    if enabled {
+        unsafe {
+            enable();
+        }
+    }
+    result
+}
+
 pub fn setup() {
     unsafe {
         idt::setup_idt();
@@ -0,0 +1,29 @@
+use core::fmt;
+
+/// The frame pushed by the processor on an interrupt or exception in
+/// protected mode without privilege level change (cf. Intel volume III,
+/// 6.12.1).
+///
+/// Fields are pointer sized so the structure also has a sensible layout when
+/// the crate is built for IA-32e, where this module is only compiled and
+/// never used by the processor.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct InterruptStackFrame {
+    /// Next or faulting instruction (EIP)
+    pub eip: usize,
+    /// Code segment selector (CS)
+    pub code_segment: usize,
+    /// CPU flags (EFLAGS)
+    pub eflags: usize,
+}
+
+impl fmt::Display for InterruptStackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Interrupt Stack Frame:\nEIP: {:#X}\nCS: {:X}\nEFLAGS: {:X}\n",
+            self.eip, self.code_segment, self.eflags
+        )
+    }
+}
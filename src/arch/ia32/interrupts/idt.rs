@@ -1,27 +1,40 @@
-use crate::arch::ia32::descriptor::gate::Gate;
+use crate::arch::ia32::descriptor::gate::{interrupt::InterruptGate, Gate, GateSize};
+use crate::arch::ia32::interrupts::frame::InterruptStackFrame;
+use crate::arch::ia32::interrupts::pic::{self, *};
+use crate::arch::ia32::interrupts::pit::{self, *};
+use crate::arch::ia32::selector::{SegmentSelector, TableIndicator};
+use crate::arch::ia32::PrivilegeLevel;
+use crate::drivers::ps2::{keyboard, mouse};
+use crate::serial;
+use crate::sync::{IrqSafeSpinLock, RwLock};
+use crate::utils::bitfield::*;
 use core::arch::asm;
 use core::ptr::addr_of;
 use core::{fmt, mem};
-use log::trace;
+use log::{info, trace};
 
 const IDT_LEN: usize = 256;
 
 pub struct InterruptDescriptorTable(pub [Gate; IDT_LEN]);
 
 impl InterruptDescriptorTable {
-    pub fn load(&'static self) {
+    /// Load the table in the processor's IDTR.
+    ///
+    /// # Safety
+    ///
+    /// The table must neither move nor be dropped while loaded, which is
+    /// guaranteed for a table stored in a static.
+    pub unsafe fn load(&self) {
         trace!("Loading interrupt descriptor table...");
 
         let idtr = InterruptDescriptorTableRegister::new(
-            (IDT_LEN * mem::size_of::<Gate>())
+            (IDT_LEN * mem::size_of::<Gate>() - 1)
                 .try_into()
                 .expect("Idt length does not fit in a u16, cannot set IDTR"),
             self,
         );
 
-        unsafe {
-            asm!("lidt [{}]", in(reg) addr_of!(idtr));
-        }
+        asm!("lidt [{}]", in(reg) addr_of!(idtr));
     }
 }
 
@@ -34,6 +47,7 @@ impl fmt::Display for InterruptDescriptorTable {
     }
 }
 
+#[repr(C, packed)]
 struct InterruptDescriptorTableRegister {
     size: u16,
     offset: *const InterruptDescriptorTable,
@@ -48,7 +62,240 @@ impl InterruptDescriptorTableRegister {
     }
 }
 
+/// Kernel code segment index in the 32 bits GDT.
+const GDT_KERNEL_CODE: u16 = 1;
+
+/// An interrupt handler for vectors without error code.
+pub type HandlerFunc = extern "x86-interrupt" fn(InterruptStackFrame);
+/// An interrupt handler for vectors pushing an error code.
+pub type HandlerFuncWithErrCode = extern "x86-interrupt" fn(InterruptStackFrame, usize);
+
+static IDT: IrqSafeSpinLock<InterruptDescriptorTable> =
+    IrqSafeSpinLock::new(InterruptDescriptorTable([Gate::const_default(); IDT_LEN]));
+
+/// Build a present 32 bits interrupt gate pointing to a handler in the kernel
+/// code segment.
+fn interrupt_gate(handler: usize) -> Gate {
+    InterruptGate::new(
+        handler as u32,
+        SegmentSelector::new(GDT_KERNEL_CODE, TableIndicator::GDT, PrivilegeLevel::Kernel),
+    )
+    .size(GateSize::Gate32Bits)
+    .privilege_level(PrivilegeLevel::Kernel)
+    .into()
+}
+
+impl InterruptDescriptorTable {
+    /// Install a handler for a vector without error code.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The vector to install the handler on.
+    /// * `handler` - The handler function.
+    pub fn set_handler(&mut self, index: usize, handler: HandlerFunc) {
+        self.0[index] = interrupt_gate(handler as usize);
+    }
+
+    /// Install a handler for a vector pushing an error code.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The vector to install the handler on.
+    /// * `handler` - The handler function.
+    pub fn set_handler_with_err(&mut self, index: usize, handler: HandlerFuncWithErrCode) {
+        self.0[index] = interrupt_gate(handler as usize);
+    }
+}
+
+/// A function called on a breakpoint exception (`int3`), execution then
+/// resumes after the instruction.
+pub type BreakpointCallback = fn(&InterruptStackFrame);
+
+static BREAKPOINT_CALLBACK: RwLock<Option<BreakpointCallback>> = RwLock::new(None);
+
+/// Set the function called on breakpoint exceptions, which are otherwise
+/// only logged.
+///
+/// # Arguments
+///
+/// * `callback` - The function to call, `None` removes the current one.
+pub fn set_breakpoint_callback(callback: Option<BreakpointCallback>) {
+    *BREAKPOINT_CALLBACK.write() = callback;
+}
+
+fn setup_predefined(idt: &mut InterruptDescriptorTable) {
+    idt.set_handler(0, div_by_zero);
+    idt.set_handler(1, debug_exception);
+    idt.set_handler(2, nmi);
+    idt.set_handler(3, breakpoint);
+    idt.set_handler(4, overflow);
+    idt.set_handler(5, bound_range);
+    idt.set_handler(6, invalid_op);
+    idt.set_handler(7, device_na);
+    idt.set_handler_with_err(8, double_fault);
+    idt.set_handler(9, coproc_overrun);
+    idt.set_handler_with_err(10, invalid_tss);
+    idt.set_handler_with_err(11, segment_not_present);
+    idt.set_handler_with_err(12, stack_fault);
+    idt.set_handler_with_err(13, general_fault);
+    idt.set_handler_with_err(14, page_fault);
+    idt.set_handler(16, x87_fpe);
+    idt.set_handler_with_err(17, alignment_check);
+    idt.set_handler(18, machine_check);
+    idt.set_handler(19, simd_fpe);
+    idt.set_handler(20, virt_exception);
+    idt.set_handler_with_err(21, control_protection);
+
+    idt.set_handler(PIC_OFFSET + PIT_IRQ, pit);
+    idt.set_handler(PIC_OFFSET + KEYBOARD_IRQ, keyboard);
+    idt.set_handler(PIC_OFFSET + COM1_IRQ, serial_primary);
+    idt.set_handler(PIC_OFFSET + COM2_IRQ, serial_secondary);
+    idt.set_handler(PIC_OFFSET + MOUSE_IRQ, mouse);
+}
+
 pub fn setup_idt() {
-    trace!("Setting up idt...");
-    todo!("Implement idt setup");
+    unsafe {
+        trace!("Setting up idt...");
+        pic::setup(
+            0b11111111_u8
+                .set_bit(KEYBOARD_IRQ, false)
+                .set_bit(PIT_IRQ, false)
+                .set_bit(COM1_IRQ, false)
+                .set_bit(COM2_IRQ, false)
+                .set_bit(CASCADE_IRQ, false),
+            0b11111111_u8.set_bit(MOUSE_IRQ - 8, false),
+        );
+        pit::setup();
+        let mut idt = IDT.lock();
+        setup_predefined(&mut idt);
+        trace!("Loading idt...");
+        idt.load();
+    }
+}
+
+extern "x86-interrupt" fn div_by_zero(_frame: InterruptStackFrame) {
+    panic!("Division by zero!");
+}
+
+extern "x86-interrupt" fn debug_exception(_frame: InterruptStackFrame) {
+    panic!("Debug exception");
+}
+
+extern "x86-interrupt" fn nmi(_frame: InterruptStackFrame) {
+    panic!("Non-Maskable Interrupt");
+}
+
+extern "x86-interrupt" fn breakpoint(frame: InterruptStackFrame) {
+    info!("Breakpoint\n{}", frame);
+    // The callback is copied out of the lock, it may replace itself.
+    let callback = *BREAKPOINT_CALLBACK.read();
+    if let Some(callback) = callback {
+        callback(&frame);
+    }
+}
+
+extern "x86-interrupt" fn overflow(_frame: InterruptStackFrame) {
+    panic!("Overflow occured");
+}
+
+extern "x86-interrupt" fn bound_range(_frame: InterruptStackFrame) {
+    panic!("Bound range exceeded");
+}
+
+extern "x86-interrupt" fn invalid_op(_frame: InterruptStackFrame) {
+    panic!("Invalid opcode");
+}
+
+extern "x86-interrupt" fn device_na(_frame: InterruptStackFrame) {
+    panic!("Device not available");
+}
+
+extern "x86-interrupt" fn double_fault(frame: InterruptStackFrame, _err: usize) {
+    panic!("Double fault!\n{}", frame);
+}
+
+extern "x86-interrupt" fn coproc_overrun(_frame: InterruptStackFrame) {
+    panic!("Coprocessor segment overrun");
+}
+
+extern "x86-interrupt" fn invalid_tss(_frame: InterruptStackFrame, _err: usize) {
+    panic!("Invalid TSS");
+}
+
+extern "x86-interrupt" fn segment_not_present(_frame: InterruptStackFrame, _err: usize) {
+    panic!("Segment not present");
+}
+
+extern "x86-interrupt" fn stack_fault(_frame: InterruptStackFrame, _err: usize) {
+    panic!("Stack segment fault");
+}
+
+extern "x86-interrupt" fn general_fault(_frame: InterruptStackFrame, _err: usize) {
+    panic!("General protection fault");
+}
+
+extern "x86-interrupt" fn page_fault(frame: InterruptStackFrame, err: usize) {
+    panic!("Page fault (error code {:#X})\n{}", err, frame);
+}
+
+extern "x86-interrupt" fn x87_fpe(_frame: InterruptStackFrame) {
+    panic!("Floating point exception");
+}
+
+extern "x86-interrupt" fn alignment_check(_frame: InterruptStackFrame, _err: usize) {
+    panic!("Unaligned memory data reference");
+}
+
+extern "x86-interrupt" fn machine_check(_frame: InterruptStackFrame) {
+    panic!("Machine check exception");
+}
+
+extern "x86-interrupt" fn simd_fpe(_frame: InterruptStackFrame) {
+    panic!("Floating point exception");
+}
+
+extern "x86-interrupt" fn virt_exception(_frame: InterruptStackFrame) {
+    panic!("Virtualization exception");
+}
+
+extern "x86-interrupt" fn control_protection(_frame: InterruptStackFrame, _err: usize) {
+    panic!("Control protection exception");
+}
+
+extern "x86-interrupt" fn keyboard(_frame: InterruptStackFrame) {
+    keyboard::handle_interrupt();
+    ack_eoi(KEYBOARD_IRQ as u8);
+}
+
+extern "x86-interrupt" fn mouse(_frame: InterruptStackFrame) {
+    mouse::handle_interrupt();
+    ack_eoi(MOUSE_IRQ as u8);
+}
+
+extern "x86-interrupt" fn pit(_frame: InterruptStackFrame) {
+    TICK_COUNTER.increment();
+    ack_eoi(PIT_IRQ as u8);
+}
+
+extern "x86-interrupt" fn serial_primary(_frame: InterruptStackFrame) {
+    serial::handle_interrupt(COM1_IRQ);
+    ack_eoi(COM1_IRQ as u8);
+}
+
+extern "x86-interrupt" fn serial_secondary(_frame: InterruptStackFrame) {
+    serial::handle_interrupt(COM2_IRQ);
+    ack_eoi(COM2_IRQ as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn set_handler_present() {
+        let mut idt = InterruptDescriptorTable([Gate::const_default(); IDT_LEN]);
+        assert!(!idt.0[3].is_present());
+        idt.set_handler(3, breakpoint);
+        assert!(idt.0[3].is_present());
+    }
 }
@@ -1,6 +1,7 @@
+use crate::utils::bitfield::*;
 use log::trace;
 use pic8259a::{AddressInterval, PIC8259a, TriggerMode, MASTER_PIC_COM, SLAVE_PIC_COM};
-pub use pic8259a::{KEYBOARD_IRQ, PIT_IRQ};
+pub use pic8259a::{CASCADE_IRQ, COM1_IRQ, COM2_IRQ, KEYBOARD_IRQ, MOUSE_IRQ, PIT_IRQ};
 
 mod pic8259a;
 
@@ -51,3 +52,31 @@ pub fn ack_eoi(irq: u8) {
         MASTER_PIC.ack_eoi();
     }
 }
+
+/// Mask or unmask a single IRQ line, leaving the other lines untouched.
+///
+/// # Arguments
+///
+/// * `irq` - IRQ index, between 0 and 15.
+/// * `masked` - Whether the IRQ should be disabled.
+pub fn set_mask(irq: u8, masked: bool) {
+    let (pic, line) = if irq >= 8 {
+        (&SLAVE_PIC, irq - 8)
+    } else {
+        (&MASTER_PIC, irq)
+    };
+    unsafe {
+        let mask = pic.read_ocw1().set_bit(line.into(), masked);
+        pic.send_ocw1(mask);
+    }
+}
+
+/// Mask every IRQ line of both PICs, used once another interrupt controller
+/// takes over.
+pub fn disable() {
+    trace!("Masking 8259a PICs...");
+    unsafe {
+        MASTER_PIC.send_ocw1(0xFF);
+        SLAVE_PIC.send_ocw1(0xFF);
+    }
+}
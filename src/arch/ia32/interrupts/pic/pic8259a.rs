@@ -1,6 +1,6 @@
 //! A module for the 8259 PIC.
 use crate::arch::io::port::Port;
-use crate::arch::io::register::WriteRegister;
+use crate::arch::io::register::{ReadRegister, WriteRegister};
 use crate::utils::bitfield::*;
 
 /// Master and Slave PICs ports on IBM PC
@@ -10,6 +10,11 @@ pub const SLAVE_PIC_COM: u16 = 0xA0;
 // PIC IRQs mapping
 pub const PIT_IRQ: usize = 0;
 pub const KEYBOARD_IRQ: usize = 1;
+/// The master line the slave PIC is connected to.
+pub const CASCADE_IRQ: usize = 2;
+pub const COM2_IRQ: usize = 3;
+pub const COM1_IRQ: usize = 4;
+pub const MOUSE_IRQ: usize = 12;
 
 /// An enum defining how interrupts are triggered.
 #[derive(PartialEq, Eq)]
@@ -239,6 +244,17 @@ impl PIC8259a {
         self.data_port.write(mask);
     }
 
+    /// Read the interrupt mask register set by the last Operational Command
+    /// Word 1.
+    ///
+    /// # Safety
+    ///
+    /// The PIC must have been initialised, the data port returns the
+    /// interrupt mask only outside of the initialisation sequence.
+    pub unsafe fn read_ocw1(&self) -> u8 {
+        self.data_port.read()
+    }
+
     /// Acknowledge End Of Interrupt on PIC.
     ///
     /// # Safety
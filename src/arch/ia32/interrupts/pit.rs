@@ -1,23 +1,86 @@
-use pit8254::{setup_rate_generator, Channel};
+use crate::arch::ia32::interrupts::without_interrupts;
+use crate::arch::ia32::read_timestamp_counter;
+use crate::sync::IrqSafeSpinLock;
+use pit8254::{setup_rate_generator, Channel, INTERNAL_FREQUENCY};
 
 mod pit8254;
 
-/// PIT Channel 0 interrupt frequency.
+/// Default PIT Channel 0 interrupt frequency.
 pub const DESIRED_FREQUENCY: u16 = 100; // 100 Hz
 
+/// Lowest frequency whose divisor fits in the 16 bits counter.
+pub const MIN_FREQUENCY: u16 = 19;
+
+const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+
 /// Setup the 8254 PIT with a Rate Generator of [`DESIRED_FREQUENCY`] on IRQ0.
 pub fn setup() {
-    unsafe {
-        setup_rate_generator(Channel::Channel0, DESIRED_FREQUENCY);
-    }
+    set_frequency(DESIRED_FREQUENCY);
 }
 
-/// A struct representing a tick counter.
-pub struct TickCounter {
+/// Reprogram the 8254 PIT's Channel 0 and update [`TICK_COUNTER`]'s period.
+///
+/// # Arguments
+///
+/// * `frequency` - The desired interrupt frequency, in Hz.
+///
+/// # Panics
+///
+/// The frequency must be at least [`MIN_FREQUENCY`].
+pub fn set_frequency(frequency: u16) {
+    assert!(
+        frequency >= MIN_FREQUENCY,
+        "PIT frequency must be at least {} Hz",
+        MIN_FREQUENCY
+    );
+    without_interrupts(|| {
+        let divisor = unsafe { setup_rate_generator(Channel::Channel0, frequency) };
+        TICK_COUNTER.set_period(
+            frequency,
+            u64::from(divisor) * NANOSECONDS_PER_SECOND / u64::from(INTERNAL_FREQUENCY),
+        );
+    });
+}
+
+/// Clock state updated on every tick.
+struct Clock {
     /// Elasped ticks.
-    ticks: u32,
+    ticks: u64,
+    /// Elasped nanoseconds at the last tick.
+    nanoseconds: u64,
+    /// Duration of a tick, in nanoseconds.
+    period: u64,
     /// Counter expected frequency.
     frequency: u16,
+    /// Time stamp counter value at the last tick, 0 before the first one.
+    timestamp: u64,
+    /// Time stamp counter increments measured over the last tick, 0 until
+    /// calibrated.
+    timestamps_per_tick: u64,
+}
+
+impl Clock {
+    /// Interpolate the nanoseconds elapsed since the last tick using the
+    /// time stamp counter. The result never reaches the tick period so the
+    /// clock stays monotonic.
+    fn interpolate(&self) -> u64 {
+        if self.timestamps_per_tick == 0 || self.period == 0 {
+            return 0;
+        }
+        let delta = read_timestamp_counter().saturating_sub(self.timestamp);
+        let nanoseconds =
+            u128::from(delta) * u128::from(self.period) / u128::from(self.timestamps_per_tick);
+        nanoseconds.min(u128::from(self.period - 1)) as u64
+    }
+}
+
+/// A struct representing a tick counter, it can be shared between an
+/// interrupt handler and normal code.
+///
+/// Elapsed time is accumulated at every tick with the programmed period and
+/// refined between ticks using the time stamp counter.
+pub struct TickCounter {
+    clock: IrqSafeSpinLock<Clock>,
 }
 
 impl TickCounter {
@@ -28,26 +91,109 @@ impl TickCounter {
     /// * `frequency` - The frequency at which *increment* will be called.
     pub const fn new(frequency: u16) -> Self {
         Self {
-            ticks: 0,
-            frequency,
+            clock: IrqSafeSpinLock::new(Clock {
+                ticks: 0,
+                nanoseconds: 0,
+                period: NANOSECONDS_PER_SECOND / frequency as u64,
+                frequency,
+                timestamp: 0,
+                timestamps_per_tick: 0,
+            }),
         }
     }
 
     /// Increments the counter.
-    pub fn increment(&mut self) {
-        self.ticks += 1;
+    pub fn increment(&self) {
+        let now = read_timestamp_counter();
+        let mut clock = self.clock.lock();
+        if clock.timestamp != 0 {
+            clock.timestamps_per_tick = now.saturating_sub(clock.timestamp);
+        }
+        clock.timestamp = now;
+        clock.ticks += 1;
+        clock.nanoseconds += clock.period;
+    }
+
+    /// Change the period between two calls to *increment*, the time elapsed
+    /// since the last tick is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency` - The new frequency, in Hz.
+    /// * `period` - The new exact period, in nanoseconds.
+    pub fn set_period(&self, frequency: u16, period: u64) {
+        let mut clock = self.clock.lock();
+        clock.nanoseconds += clock.interpolate();
+        clock.period = period;
+        clock.frequency = frequency;
+        // The next tick comes a full period after the change.
+        clock.timestamp = read_timestamp_counter();
+        clock.timestamps_per_tick = 0;
     }
 
     /// Returns the elasped ticks.
-    pub fn elasped_ticks(&self) -> u32 {
-        self.ticks
+    pub fn elasped_ticks(&self) -> u64 {
+        self.clock.lock().ticks
     }
 
     /// Returns the elasped seconds.
-    pub fn elasped_seconds(&self) -> u32 {
-        self.ticks / (self.frequency as u32)
+    pub fn elasped_seconds(&self) -> u64 {
+        self.elasped_nanoseconds() / NANOSECONDS_PER_SECOND
+    }
+
+    /// Returns the elasped nanoseconds, interpolated between ticks.
+    pub fn elasped_nanoseconds(&self) -> u64 {
+        let clock = self.clock.lock();
+        clock.nanoseconds + clock.interpolate()
+    }
+
+    /// Returns the counter expected frequency, in Hz.
+    pub fn frequency(&self) -> u16 {
+        self.clock.lock().frequency
+    }
+
+    /// Convert nanoseconds to time stamp counter increments, if the counter
+    /// has been calibrated over a tick.
+    ///
+    /// # Arguments
+    ///
+    /// * `nanoseconds` - The duration to convert.
+    pub fn timestamps_for(&self, nanoseconds: u64) -> Option<u64> {
+        let clock = self.clock.lock();
+        if clock.timestamps_per_tick == 0 {
+            return None;
+        }
+        let timestamps = u128::from(nanoseconds) * u128::from(clock.timestamps_per_tick)
+            / u128::from(clock.period);
+        Some(timestamps.try_into().unwrap_or(u64::MAX))
     }
 }
 
 /// 8254 PIT's Channel 0 tick counter.
-pub static mut TICK_COUNTER: TickCounter = TickCounter::new(DESIRED_FREQUENCY);
+pub static TICK_COUNTER: TickCounter = TickCounter::new(DESIRED_FREQUENCY);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn ticks_accumulate_period() {
+        let counter = TickCounter::new(DESIRED_FREQUENCY);
+        counter.increment();
+        counter.increment();
+        assert_eq!(counter.elasped_ticks(), 2);
+        assert!(counter.elasped_nanoseconds() >= 2 * 10_000_000);
+        assert!(counter.elasped_nanoseconds() < 3 * 10_000_000);
+    }
+
+    #[test_case]
+    fn period_change_keeps_elapsed_time() {
+        let counter = TickCounter::new(DESIRED_FREQUENCY);
+        counter.increment();
+        let before = counter.elasped_nanoseconds();
+        counter.set_period(1000, 1_000_000);
+        assert_eq!(counter.frequency(), 1000);
+        assert!(counter.elasped_nanoseconds() >= before);
+        assert_eq!(counter.timestamps_for(1000), None);
+    }
+}
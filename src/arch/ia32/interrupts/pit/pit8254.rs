@@ -7,7 +7,8 @@ const COUNTER_1: u16 = 0x41;
 const COUNTER_2: u16 = 0x42;
 const CONTROL_REG: u16 = 0x43;
 
-const INTERNAL_FREQUENCY: u32 = 1193182;
+/// Frequency of the oscillator driving every channel, in Hz.
+pub const INTERNAL_FREQUENCY: u32 = 1193182;
 
 /// An enum representing the counter representation mode of a [`Channel`].
 #[derive(PartialEq)]
@@ -125,7 +126,12 @@ unsafe fn send_command(
 /// # Safety
 ///
 /// The selected channel and frequency must be consistent.
-pub unsafe fn setup_rate_generator(channel: Channel, frequency: u16) {
+///
+/// # Note
+///
+/// The programmed divisor is returned, the actual frequency is
+/// [`INTERNAL_FREQUENCY`] divided by it.
+pub unsafe fn setup_rate_generator(channel: Channel, frequency: u16) -> u16 {
     send_command(
         channel,
         CountMode::Binary,
@@ -141,4 +147,5 @@ pub unsafe fn setup_rate_generator(channel: Channel, frequency: u16) {
     // Set desired frequency, least significant byte first.
     out_byte(channel.address(), divisor.get_bits(0..=7) as u8);
     out_byte(channel.address(), divisor.get_bits(8..=15) as u8);
+    divisor
 }
@@ -1,5 +1,5 @@
 pub mod gdt;
 
-pub fn setup() {
+pub fn setup(_physical_memory_offset: u64) {
     gdt::setup_gdt();
 }
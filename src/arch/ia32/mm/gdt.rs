@@ -1,8 +1,12 @@
-use crate::arch::ia32::descriptor::segment::SegmentDescriptor;
+use crate::arch::ia32::descriptor::segment::{
+    DefaultOperationSize, DescriptorType, Granularity, SegmentDescriptor, SegmentType,
+};
+use crate::arch::ia32::selector::{SegmentSelector, TableIndicator};
+use crate::arch::ia32::PrivilegeLevel;
+use crate::sync::Once;
 use core::arch::asm;
-use core::ptr::addr_of;
 use core::{fmt, mem};
-use log::trace;
+use log::{debug, trace};
 
 const GDT_LEN: usize = 5;
 
@@ -13,14 +17,14 @@ impl GlobalDescriptorTable {
         trace!("Loading global descriptor table...");
 
         let gdtr = GlobalDescriptorTableRegister::new(
-            (GDT_LEN * mem::size_of::<SegmentDescriptor>())
+            (GDT_LEN * mem::size_of::<SegmentDescriptor>() - 1)
                 .try_into()
                 .expect("Gdt length does not fit in a u16, cannot set GDTR"),
             self,
         );
 
         unsafe {
-            asm!("lgdt [{}]", in(reg) addr_of!(gdtr));
+            gdtr.load();
         }
     }
 }
@@ -35,25 +39,133 @@ impl fmt::Display for GlobalDescriptorTable {
 }
 
 /// Global descriptor table register (Intel III 2.4.1).
+///
+/// The table type is generic so the IA-32e table, which holds wider system
+/// descriptors, can share the same register layout.
 #[repr(C, packed)]
-struct GlobalDescriptorTableRegister {
+pub(crate) struct GlobalDescriptorTableRegister<T> {
     /// Table limit specifying the number of bytes in the table.
     size: u16,
     /// Linear base address. Should be 32 bits in protected mode and 64 bits
     /// in IA-32e mode.
-    offset: *const GlobalDescriptorTable,
+    offset: *const T,
 }
 
-impl GlobalDescriptorTableRegister {
-    pub fn new(size: u16, address: *const GlobalDescriptorTable) -> Self {
+impl<T> GlobalDescriptorTableRegister<T> {
+    pub fn new(size: u16, address: *const T) -> Self {
         GlobalDescriptorTableRegister {
             size,
             offset: address,
         }
     }
+
+    /// Load the register's value into the processor's GDTR.
+    ///
+    /// # Safety
+    ///
+    /// The referenced table must stay alive and valid as long as it is in
+    /// use by the processor.
+    pub unsafe fn load(&self) {
+        asm!("lgdt [{}]", in(reg) self as *const Self);
+    }
+}
+
+/// Kernel code segment index.
+const KERNEL_CODE: u16 = 1;
+/// Kernel data segment index.
+const KERNEL_DATA: u16 = 2;
+
+static GDT: Once<GlobalDescriptorTable> = Once::new();
+
+/// Build a flat 4 GiB protected mode segment descriptor.
+fn flat_segment(segment_type: SegmentType, level: PrivilegeLevel) -> SegmentDescriptor {
+    SegmentDescriptor::new(0, 0xFFFFF)
+        .segment_type(segment_type)
+        .descriptor_type(DescriptorType::CodeOrData)
+        .privilege_level(level)
+        .default_operation_size(DefaultOperationSize::Segment32Bits)
+        .granularity(Granularity::FourKByte)
+}
+
+/// Reload every segment register so the processor uses the kernel segments
+/// of the newly loaded table.
+///
+/// # Safety
+///
+/// Both selectors must reference valid descriptors of the loaded GDT.
+#[cfg(target_arch = "x86")]
+unsafe fn reload_segments(code: SegmentSelector, data: SegmentSelector) {
+    // CS cannot be written directly, a far return pops it along with the
+    // instruction pointer.
+    asm!(
+        "push {code}",
+        "lea {tmp}, [2f]",
+        "push {tmp}",
+        "retf",
+        "2:",
+        "mov ds, {data:x}",
+        "mov es, {data:x}",
+        "mov fs, {data:x}",
+        "mov gs, {data:x}",
+        "mov ss, {data:x}",
+        code = in(reg) u32::from(code),
+        data = in(reg) u32::from(data),
+        tmp = out(reg) _,
+    );
 }
 
 pub fn setup_gdt() {
     trace!("Setting up 32bits gdt...");
-    todo!("Implement 32 bit GDT");
+    let gdt = GDT.call_once(|| {
+        GlobalDescriptorTable([
+            // Null segment
+            SegmentDescriptor::default(),
+            // Kernel code
+            flat_segment(
+                SegmentType::Code {
+                    accessed: false,
+                    read: true,
+                    conforming: false,
+                },
+                PrivilegeLevel::Kernel,
+            ),
+            // Kernel data
+            flat_segment(
+                SegmentType::Data {
+                    accessed: false,
+                    write: true,
+                    expand_down: false,
+                },
+                PrivilegeLevel::Kernel,
+            ),
+            // User code
+            flat_segment(
+                SegmentType::Code {
+                    accessed: false,
+                    read: true,
+                    conforming: false,
+                },
+                PrivilegeLevel::Userland,
+            ),
+            // User data
+            flat_segment(
+                SegmentType::Data {
+                    accessed: false,
+                    write: true,
+                    expand_down: false,
+                },
+                PrivilegeLevel::Userland,
+            ),
+        ])
+    });
+    debug!("GDT:\n{}", gdt);
+    gdt.load();
+
+    let code = SegmentSelector::new(KERNEL_CODE, TableIndicator::GDT, PrivilegeLevel::Kernel);
+    let data = SegmentSelector::new(KERNEL_DATA, TableIndicator::GDT, PrivilegeLevel::Kernel);
+    trace!("Reloading segment registers ({}, {})...", code, data);
+    #[cfg(target_arch = "x86")]
+    unsafe {
+        reload_segments(code, data);
+    }
 }
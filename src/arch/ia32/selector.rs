@@ -59,7 +59,23 @@ impl From<SegmentSelector> for u32 {
     }
 }
 
+impl From<u16> for SegmentSelector {
+    fn from(value: u16) -> Self {
+        SegmentSelector(value)
+    }
+}
+
 impl SegmentSelector {
+    /// Get the index of the descriptor within its table.
+    pub fn index(&self) -> u16 {
+        self.0 >> 3
+    }
+
+    /// Get the table holding the descriptor.
+    pub fn table_indicator(&self) -> TableIndicator {
+        self.0.get_bit(2).into()
+    }
+
     pub fn new(index: u16, ti: TableIndicator, rpl: PrivilegeLevel) -> Self {
         SegmentSelector(index << 3 | ((ti as u16) << 2) | (rpl as u16))
     }
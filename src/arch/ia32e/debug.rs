@@ -0,0 +1,463 @@
+//! A module programming the debug registers DR0-DR7, to stop on the
+//! execution of an instruction or on an access to some data.
+//!
+//! Breakpoints are reported through the #DB exception, which calls the
+//! handler registered along with the breakpoint that fired.
+//!
+//! ```ignore
+//! // Catch the exact instruction overwriting the first IDT gate.
+//! debug::set_breakpoint(idt_address, Condition::Write, Length::QuadWord, |slot, frame| {
+//!     panic!("IDT overwritten (breakpoint {}):\n{}", slot, frame);
+//! })?;
+//! ```
+//!
+//! # Note
+//!
+//! The debug registers are per processor, breakpoints are only set on the
+//! current one.
+use crate::arch::ia32e::interrupts::trap::TrapFrame;
+use crate::sync::IrqSafeSpinLock;
+use crate::utils::bitfield::*;
+use core::arch::asm;
+use core::fmt;
+
+/// Number of breakpoint address registers, DR0 to DR3.
+pub const BREAKPOINT_COUNT: usize = 4;
+
+/// Offset of the resume flag in RFLAGS, when set instruction breakpoints are
+/// ignored for one instruction.
+const RESUME_FLAG: usize = 16;
+
+mod offset {
+    /// Offset of the local enable bit of DR0 in DR7, each breakpoint takes 2
+    /// bits.
+    pub const LOCAL_ENABLE: usize = 0;
+    /// Offset of the local exact breakpoint enable bit in DR7.
+    pub const LOCAL_EXACT: usize = 8;
+    /// Offset of the global exact breakpoint enable bit in DR7.
+    pub const GLOBAL_EXACT: usize = 9;
+    /// Offset of the condition of DR0 in DR7, each breakpoint takes 4 bits:
+    /// 2 for the condition then 2 for the length.
+    pub const CONDITION: usize = 16;
+    /// Offset of the debug register access detected bit in DR6.
+    pub const ACCESS_DETECTED: usize = 13;
+    /// Offset of the single step bit in DR6.
+    pub const SINGLE_STEP: usize = 14;
+    /// Offset of the task switch bit in DR6.
+    pub const TASK_SWITCH: usize = 15;
+}
+
+/// Value of DR6 once every status bit is cleared, reserved bits read as 1.
+const DR6_CLEARED: u64 = 0xFFFF_0FF0;
+/// Value of DR7 with every breakpoint disabled, bit 10 is reserved to 1.
+const DR7_DISABLED: u64 = 1 << 10;
+
+/// The access triggering a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Condition {
+    /// The instruction at the address is executed.
+    Execute = 0b00,
+    /// The data at the address is written.
+    Write = 0b01,
+    /// The data at the address is read or written, not on instruction
+    /// fetches.
+    ReadWrite = 0b11,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Condition::Execute => "execute",
+                Condition::Write => "write",
+                Condition::ReadWrite => "read/write",
+            }
+        )
+    }
+}
+
+/// The size of the data watched by a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Length {
+    Byte = 0b00,
+    Word = 0b01,
+    QuadWord = 0b10,
+    DoubleWord = 0b11,
+}
+
+impl Length {
+    /// Get the length in bytes.
+    pub const fn size(self) -> u64 {
+        match self {
+            Length::Byte => 1,
+            Length::Word => 2,
+            Length::DoubleWord => 4,
+            Length::QuadWord => 8,
+        }
+    }
+}
+
+/// An error preventing a breakpoint from being set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointError {
+    /// The four debug registers are in use.
+    NoFreeRegister,
+    /// The address is not aligned on the length.
+    Unaligned,
+    /// Instruction breakpoints must have a [`Length::Byte`] length.
+    InvalidLength,
+}
+
+impl fmt::Display for BreakpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakpointError::NoFreeRegister => write!(f, "every debug register is in use"),
+            BreakpointError::Unaligned => write!(f, "address not aligned on the length"),
+            BreakpointError::InvalidLength => {
+                write!(f, "instruction breakpoints must be one byte long")
+            }
+        }
+    }
+}
+
+/// The debug control register, enabling breakpoints and holding their
+/// conditions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugControl(pub u64);
+
+impl DebugControl {
+    /// Get the control value with every breakpoint disabled.
+    pub const fn const_default() -> Self {
+        DebugControl(DR7_DISABLED)
+    }
+
+    /// Enable or disable a breakpoint, exact breakpoints are enabled along
+    /// with any of them as recommended by the manual.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The breakpoint, from 0 to 3.
+    /// * `enabled` - Whether the breakpoint is enabled.
+    pub fn enabled(self, index: usize, enabled: bool) -> Self {
+        let value = self.0.set_bit(offset::LOCAL_ENABLE + 2 * index, enabled);
+        let any = (0..BREAKPOINT_COUNT).any(|i| value.get_bit(offset::LOCAL_ENABLE + 2 * i));
+        DebugControl(
+            value
+                .set_bit(offset::LOCAL_EXACT, any)
+                .set_bit(offset::GLOBAL_EXACT, any),
+        )
+    }
+
+    /// Set the condition and length of a breakpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The breakpoint, from 0 to 3.
+    /// * `condition` - The access triggering the breakpoint.
+    /// * `length` - The size of the watched data.
+    pub fn condition(self, index: usize, condition: Condition, length: Length) -> Self {
+        let start = offset::CONDITION + 4 * index;
+        DebugControl(
+            self.0
+                .set_bits(start..start + 2, condition as u64)
+                .set_bits(start + 2..start + 4, length as u64),
+        )
+    }
+
+    /// Whether a breakpoint is enabled.
+    pub fn is_enabled(self, index: usize) -> bool {
+        self.0.get_bit(offset::LOCAL_ENABLE + 2 * index)
+    }
+}
+
+impl Default for DebugControl {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+/// The debug status register, reporting the cause of a debug exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugStatus(pub u64);
+
+impl DebugStatus {
+    /// Whether a breakpoint condition was met, which is also reported for
+    /// disabled breakpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The breakpoint, from 0 to 3.
+    pub fn triggered(self, index: usize) -> bool {
+        self.0.get_bit(index)
+    }
+
+    /// Iterate over the breakpoints whose condition was met.
+    pub fn triggered_breakpoints(self) -> impl Iterator<Item = usize> {
+        (0..BREAKPOINT_COUNT).filter(move |&index| self.triggered(index))
+    }
+
+    /// Whether the exception comes from single stepping (RFLAGS.TF).
+    pub fn single_step(self) -> bool {
+        self.0.get_bit(offset::SINGLE_STEP)
+    }
+
+    /// Whether the exception comes from a task switch to a task with its
+    /// debug trap flag set.
+    pub fn task_switch(self) -> bool {
+        self.0.get_bit(offset::TASK_SWITCH)
+    }
+
+    /// Whether the exception comes from an access to a debug register while
+    /// general detect is enabled.
+    pub fn access_detected(self) -> bool {
+        self.0.get_bit(offset::ACCESS_DETECTED)
+    }
+}
+
+impl fmt::Display for DebugStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Breakpoints:")?;
+        for index in self.triggered_breakpoints() {
+            write!(f, " {}", index)?;
+        }
+        write!(
+            f,
+            " Single step: {} Task switch: {} Access detected: {}",
+            self.single_step(),
+            self.task_switch(),
+            self.access_detected()
+        )
+    }
+}
+
+/// Read a breakpoint address register.
+///
+/// # Arguments
+///
+/// * `index` - The register, from 0 to 3.
+pub fn address(index: usize) -> u64 {
+    let value: u64;
+    unsafe {
+        match index {
+            0 => asm!("mov {}, dr0", out(reg) value, options(nomem, nostack, preserves_flags)),
+            1 => asm!("mov {}, dr1", out(reg) value, options(nomem, nostack, preserves_flags)),
+            2 => asm!("mov {}, dr2", out(reg) value, options(nomem, nostack, preserves_flags)),
+            3 => asm!("mov {}, dr3", out(reg) value, options(nomem, nostack, preserves_flags)),
+            _ => panic!("Invalid debug address register DR{}", index),
+        }
+    }
+    value
+}
+
+/// Write a breakpoint address register.
+///
+/// # Safety
+///
+/// The breakpoint must not be enabled without a handler, the #DB exception
+/// would panic.
+unsafe fn set_address(index: usize, value: u64) {
+    match index {
+        0 => asm!("mov dr0, {}", in(reg) value, options(nomem, nostack, preserves_flags)),
+        1 => asm!("mov dr1, {}", in(reg) value, options(nomem, nostack, preserves_flags)),
+        2 => asm!("mov dr2, {}", in(reg) value, options(nomem, nostack, preserves_flags)),
+        3 => asm!("mov dr3, {}", in(reg) value, options(nomem, nostack, preserves_flags)),
+        _ => panic!("Invalid debug address register DR{}", index),
+    }
+}
+
+/// Read the debug status register (DR6).
+pub fn status() -> DebugStatus {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, dr6", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    DebugStatus(value)
+}
+
+/// Clear the debug status register, which the processor never does.
+pub fn clear_status() {
+    unsafe {
+        asm!("mov dr6, {}", in(reg) DR6_CLEARED, options(nomem, nostack, preserves_flags));
+    }
+}
+
+/// Read the debug control register (DR7).
+pub fn control() -> DebugControl {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, dr7", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    DebugControl(value)
+}
+
+/// Write the debug control register.
+///
+/// # Safety
+///
+/// Enabled breakpoints must have a handler, the #DB exception would panic.
+unsafe fn set_control(value: DebugControl) {
+    asm!("mov dr7, {}", in(reg) value.0, options(nomem, nostack, preserves_flags));
+}
+
+/// A function called when a breakpoint fires, with the breakpoint index and
+/// the interrupted context.
+///
+/// Write breakpoints fire once the instruction completed, and instruction
+/// breakpoints before it is executed.
+pub type BreakpointHandler = fn(usize, &mut TrapFrame);
+
+/// A breakpoint set in a debug register.
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    condition: Condition,
+    handler: BreakpointHandler,
+}
+
+static BREAKPOINTS: IrqSafeSpinLock<[Option<Breakpoint>; BREAKPOINT_COUNT]> =
+    IrqSafeSpinLock::new([None; BREAKPOINT_COUNT]);
+
+/// Set a breakpoint in a free debug register.
+///
+/// Returns the index of the breakpoint.
+///
+/// # Arguments
+///
+/// * `address` - The watched address, aligned on the length.
+/// * `condition` - The access triggering the breakpoint.
+/// * `length` - The size of the watched data, [`Length::Byte`] for
+///   instruction breakpoints.
+/// * `handler` - The function called when the breakpoint fires.
+pub fn set_breakpoint(
+    address: u64,
+    condition: Condition,
+    length: Length,
+    handler: BreakpointHandler,
+) -> Result<usize, BreakpointError> {
+    if condition == Condition::Execute && length != Length::Byte {
+        return Err(BreakpointError::InvalidLength);
+    }
+    if (address / length.size()) * length.size() != address {
+        return Err(BreakpointError::Unaligned);
+    }
+    let mut breakpoints = BREAKPOINTS.lock();
+    let index = breakpoints
+        .iter()
+        .position(Option::is_none)
+        .ok_or(BreakpointError::NoFreeRegister)?;
+    breakpoints[index] = Some(Breakpoint { condition, handler });
+    unsafe {
+        set_address(index, address);
+        set_control(
+            control()
+                .condition(index, condition, length)
+                .enabled(index, true),
+        );
+    }
+    Ok(index)
+}
+
+/// Remove a breakpoint, freeing its debug register.
+///
+/// # Arguments
+///
+/// * `index` - The breakpoint returned by [`set_breakpoint`].
+pub fn clear_breakpoint(index: usize) {
+    let mut breakpoints = BREAKPOINTS.lock();
+    if breakpoints[index].take().is_some() {
+        unsafe {
+            set_control(control().enabled(index, false));
+            set_address(index, 0);
+        }
+    }
+}
+
+/// Call the handlers of the breakpoints that fired.
+///
+/// Returns the status without the breakpoints which were handled, for the
+/// caller to handle the other causes of the exception.
+///
+/// # Arguments
+///
+/// * `status` - The debug status read on the exception, which should then
+///   be cleared with [`clear_status`].
+/// * `frame` - The interrupted context.
+pub fn handle_breakpoints(status: DebugStatus, frame: &mut TrapFrame) -> DebugStatus {
+    let mut remaining = status;
+    for index in status.triggered_breakpoints() {
+        // The handler is called without the lock, it may clear breakpoints.
+        let Some(breakpoint) = BREAKPOINTS.lock()[index] else {
+            continue;
+        };
+        if !control().is_enabled(index) {
+            continue;
+        }
+        (breakpoint.handler)(index, frame);
+        // Instruction breakpoints are faults, the instruction would fire
+        // again when resumed.
+        if breakpoint.condition == Condition::Execute {
+            frame.rflags = frame.rflags.set_bit(RESUME_FLAG, true);
+        }
+        remaining = DebugStatus(remaining.0.set_bit(index, false));
+    }
+    remaining
+}
+
+/// The default #DB handler, dispatching breakpoints to their handlers.
+///
+/// # Panics
+///
+/// Debug exceptions not coming from a set breakpoint panic.
+pub fn debug_exception(frame: &mut TrapFrame) {
+    let status = status();
+    clear_status();
+    let remaining = handle_breakpoints(status, frame);
+    if remaining == status
+        || remaining.single_step()
+        || remaining.task_switch()
+        || remaining.access_detected()
+    {
+        panic!("Debug exception ({})\n{}", status, frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn control_encoding() {
+        let control = DebugControl::default()
+            .condition(1, Condition::Write, Length::QuadWord)
+            .enabled(1, true);
+        assert_eq!(control.0, 0x0090_0704);
+        assert!(control.is_enabled(1));
+        assert_eq!(control.enabled(1, false).0, DR7_DISABLED | 0x0090_0000);
+    }
+
+    #[test_case]
+    fn status_decoding() {
+        let status = DebugStatus(DR6_CLEARED | 0b1010 | 1 << offset::SINGLE_STEP);
+        assert!(status.triggered(1) && status.triggered(3));
+        assert!(!status.triggered(0));
+        assert!(status.single_step());
+        assert!(!status.task_switch());
+        assert_eq!(status.triggered_breakpoints().count(), 2);
+    }
+
+    #[test_case]
+    fn invalid_breakpoints() {
+        fn handler(_: usize, _: &mut TrapFrame) {}
+        assert_eq!(
+            set_breakpoint(0x1001, Condition::Write, Length::DoubleWord, handler),
+            Err(BreakpointError::Unaligned)
+        );
+        assert_eq!(
+            set_breakpoint(0x1000, Condition::Execute, Length::Word, handler),
+            Err(BreakpointError::InvalidLength)
+        );
+    }
+}
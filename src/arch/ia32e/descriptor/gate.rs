@@ -113,6 +113,28 @@ impl Gate {
         Self::new(offset.into(), segment_selector).kind(Kind::Trap)
     }
 
+    /// Change the interrupt routine address of the [`Gate`], every other
+    /// field is kept as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The address of the interrupt routine.
+    pub fn offset(self, offset: VirtualAddress) -> Self {
+        let offset = u64::from(offset);
+        Self {
+            offset_15_0: offset.get_bits(0..16).try_into().unwrap(),
+            offset_31_16: offset.get_bits(16..32).try_into().unwrap(),
+            offset_63_32: offset.get_bits(32..64).try_into().unwrap(),
+            ..self
+        }
+    }
+
+    /// Whether the presence bit of the [`Gate`] is set.
+    pub fn is_present(&self) -> bool {
+        let configuration = self.configuration;
+        configuration.is_present()
+    }
+
     /// Set or clear the presence bit of the [`Gate`].
     ///
     /// # Arguments
@@ -70,6 +70,11 @@ impl Configuration {
         Self(self.0.set_bit(PRESENT, present))
     }
 
+    /// Whether the [`Configuration`]'s present bit is set.
+    pub fn is_present(&self) -> bool {
+        self.0.get_bit(offset::PRESENT)
+    }
+
     /// Change a [`Configuration`]'s interrupt stack table value.
     ///
     /// # Arguments
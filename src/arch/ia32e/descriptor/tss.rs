@@ -4,11 +4,12 @@
 use crate::arch::ia32::descriptor::tss::TssDescriptor as IA32TssDescriptor;
 use crate::arch::ia32e::{descriptor::Granularity, PrivilegeLevel};
 use crate::utils::bitfield::*;
+use core::fmt;
 
 /// A task state segment descriptor structure that can be used directly by the
 /// processor to describe a task state segment.
 #[must_use]
-#[derive(Default, Copy, Clone)]
+#[derive(Copy, Clone)]
 #[repr(C, packed)]
 pub struct TssDescriptor {
     tss: IA32TssDescriptor,
@@ -19,6 +20,15 @@ pub struct TssDescriptor {
 }
 
 impl TssDescriptor {
+    /// Create a new non present [`TssDescriptor`].
+    pub const fn const_default() -> Self {
+        TssDescriptor {
+            tss: IA32TssDescriptor::const_default(),
+            base_63_32: 0,
+            reserved: 0,
+        }
+    }
+
     /// Creates a new [`TssDescriptor`] from a base address and a segment limit.
     ///
     /// # Arguments
@@ -99,6 +109,33 @@ impl TssDescriptor {
             ..self
         }
     }
+
+    /// Get the whole reassembled base address from a [`TssDescriptor`]
+    /// fields.
+    pub fn get_address(&self) -> u64 {
+        let tss = self.tss;
+        u64::from(self.base_63_32) << 32 | u64::from(tss.get_address())
+    }
+}
+
+impl Default for TssDescriptor {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+impl fmt::Display for TssDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tss = self.tss;
+        writeln!(
+            f,
+            "Base address: {:#X}\nLimit: {}",
+            self.get_address(),
+            tss.get_limit()
+        )?;
+        // The inner descriptor only holds the low 32 bits of the address.
+        tss.fmt_attributes(f)
+    }
 }
 
 #[cfg(test)]
@@ -1,11 +1,52 @@
-pub use crate::arch::ia32::interrupts::{disable, enable};
+use crate::arch::ia32::interrupts::pic;
+pub use crate::arch::ia32::interrupts::{are_enabled, disable, enable, without_interrupts};
+use log::{info, warn};
 
+pub mod apic;
+pub mod fatal;
 pub mod frame;
 pub mod idt;
+pub mod page_fault;
+pub mod trap;
+
+/// Signal the end of an external interrupt to the controller delivering it.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA IRQ being serviced.
+pub fn end_of_interrupt(irq: u8) {
+    if apic::is_active() {
+        apic::end_of_interrupt();
+    } else {
+        pic::ack_eoi(irq);
+    }
+}
+
+/// Mask or unmask an ISA IRQ on the controller delivering it.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA IRQ number.
+/// * `masked` - `true` disables the IRQ.
+pub fn set_irq_masked(irq: u8, masked: bool) {
+    if apic::is_active() {
+        apic::set_irq_masked(irq, masked);
+    } else {
+        pic::set_mask(irq, masked);
+    }
+}
 
 pub fn setup() {
+    idt::setup_idt();
+    if cfg!(feature = "legacy_pic") {
+        info!("Using the 8259A PICs");
+    } else {
+        match apic::setup() {
+            Ok(()) => info!("Interrupts routed through the APIC"),
+            Err(error) => warn!("{}, falling back to the 8259A PICs", error),
+        }
+    }
     unsafe {
-        idt::setup_idt();
         enable();
     }
 }
@@ -0,0 +1,205 @@
+//! A module routing external interrupts through the local APIC and the I/O
+//! APIC instead of the legacy 8259A PICs.
+use crate::arch::ia32::address::{PhysicalAddress, VirtualAddress};
+use crate::arch::ia32::interrupts::pic::{
+    self, COM1_IRQ, COM2_IRQ, KEYBOARD_IRQ, MOUSE_IRQ, PIC_OFFSET, PIT_IRQ,
+};
+use crate::arch::ia32::interrupts::without_interrupts;
+use crate::arch::ia32e::mm::paging::{flags, AddressSpace, PageFlags, PageSize, PagingError};
+use crate::arch::ia32e::registers::{self, IA32_APIC_BASE};
+use crate::mm::frame;
+use crate::sync::{IrqSafeSpinLock, Once};
+use crate::utils::bitfield::*;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use log::{debug, trace};
+
+pub use io::{DeliveryMode, IoApic, Polarity, RedirectionEntry, TriggerMode};
+pub use local::LocalApic;
+
+pub mod io;
+pub mod local;
+
+/// Physical address of the first I/O APIC on PC compatible machines.
+pub const IO_APIC_PHYSICAL_ADDRESS: u64 = 0xFEC0_0000;
+/// Virtual address the local APIC registers are mapped at.
+pub const LOCAL_APIC_ADDRESS: u64 = 0x5555_5555_0000;
+/// Virtual address the I/O APIC registers are mapped at.
+pub const IO_APIC_ADDRESS: u64 = LOCAL_APIC_ADDRESS + 0x1000;
+/// Vector raised by the local APIC for spurious interrupts, its lower four
+/// bits must be set on older processors.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// The set of all bit offsets in the `IA32_APIC_BASE` register.
+mod offset {
+    /// Offset of the APIC global enable bit.
+    pub const GLOBAL_ENABLE: usize = 11;
+    /// Offset of the APIC support bit in the EDX register of CPUID leaf 1.
+    pub const CPUID_APIC: usize = 9;
+
+    /// Bounds of the local APIC base frame number.
+    pub mod base {
+        /// Lower bit offset.
+        pub const LOWER: usize = 12;
+        /// Upper bit offset.
+        pub const UPPER: usize = 51;
+    }
+}
+
+/// Set of errors that may occur while setting up the APIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApicError {
+    /// The processor has no local APIC.
+    Unsupported,
+    /// The registers could not be mapped.
+    Mapping(PagingError),
+}
+
+impl From<PagingError> for ApicError {
+    fn from(error: PagingError) -> Self {
+        ApicError::Mapping(error)
+    }
+}
+
+impl fmt::Display for ApicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApicError::Unsupported => write!(f, "No local APIC available"),
+            ApicError::Mapping(error) => write!(f, "Cannot map APIC registers: {}", error),
+        }
+    }
+}
+
+static LOCAL_APIC: Once<LocalApic> = Once::new();
+static IO_APIC: Once<IrqSafeSpinLock<IoApic>> = Once::new();
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the processor has an on-chip local APIC.
+pub fn is_supported() -> bool {
+    registers::cpuid(1, 0).edx.get_bit(offset::CPUID_APIC)
+}
+
+/// Whether external interrupts are delivered through the APIC.
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// Get the I/O APIC input pin an ISA IRQ is wired to.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA IRQ number.
+///
+/// # Note
+///
+/// The ACPI tables are not parsed, the interrupt source override found on
+/// every PC compatible chipset, connecting the PIT to pin 2, is assumed.
+pub fn global_system_interrupt(irq: u8) -> u8 {
+    if irq == PIT_IRQ as u8 {
+        2
+    } else {
+        irq
+    }
+}
+
+/// Map an APIC register page, uncached.
+fn map_registers(page: u64, frame: u64) -> Result<(), PagingError> {
+    // The register frames are outside of RAM and the pages are reserved for
+    // them.
+    unsafe {
+        AddressSpace::active().map(
+            VirtualAddress::new(page),
+            PhysicalAddress::new(frame),
+            PageSize::Size4KiB,
+            PageFlags(flags::WRITABLE | flags::CACHE_DISABLE | flags::NO_EXECUTE),
+            &mut *frame::allocator(),
+        )
+    }
+}
+
+/// Enable the local APIC, mask every 8259A line and route the PIT, keyboard
+/// and serial IRQs through the I/O APIC on the vectors used by the PICs.
+///
+/// # Note
+///
+/// Memory management must be set up. On error the 8259A PICs are left
+/// untouched so they can be kept as a fallback.
+pub fn setup() -> Result<(), ApicError> {
+    trace!("Setting up APIC...");
+    if !is_supported() {
+        return Err(ApicError::Unsupported);
+    }
+
+    let base = unsafe { registers::read_msr(IA32_APIC_BASE) };
+    let physical = base.get_bits(offset::base::LOWER..=offset::base::UPPER) << offset::base::LOWER;
+    map_registers(LOCAL_APIC_ADDRESS, physical)?;
+    map_registers(IO_APIC_ADDRESS, IO_APIC_PHYSICAL_ADDRESS)?;
+
+    without_interrupts(|| {
+        unsafe {
+            registers::write_msr(IA32_APIC_BASE, base.set_bit(offset::GLOBAL_ENABLE, true));
+        }
+        pic::disable();
+
+        let local = LOCAL_APIC.call_once(|| unsafe { LocalApic::new(LOCAL_APIC_ADDRESS) });
+        local.enable(SPURIOUS_VECTOR);
+
+        let mut io = IO_APIC
+            .call_once(|| IrqSafeSpinLock::new(unsafe { IoApic::new(IO_APIC_ADDRESS) }))
+            .lock();
+        for pin in 0..=io.max_redirection_entry() {
+            io.set_redirection(pin, RedirectionEntry::default().masked(true));
+        }
+        for irq in [PIT_IRQ, KEYBOARD_IRQ, COM1_IRQ, COM2_IRQ, MOUSE_IRQ] {
+            io.set_redirection(
+                global_system_interrupt(irq as u8),
+                RedirectionEntry::default()
+                    .vector((PIC_OFFSET + irq) as u8)
+                    .destination(local.id()),
+            );
+        }
+        ACTIVE.store(true, Ordering::Release);
+    });
+
+    debug!(
+        "Local APIC {} (version {:#X}) at {:#X}",
+        LOCAL_APIC.get().unwrap().id(),
+        LOCAL_APIC.get().unwrap().version(),
+        physical
+    );
+    Ok(())
+}
+
+/// Signal the end of the interrupt being serviced to the local APIC.
+pub fn end_of_interrupt() {
+    if let Some(local) = LOCAL_APIC.get() {
+        local.end_of_interrupt();
+    }
+}
+
+/// Mask or unmask an ISA IRQ in the I/O APIC redirection table.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA IRQ number.
+/// * `masked` - `true` disables the IRQ.
+pub fn set_irq_masked(irq: u8, masked: bool) {
+    if let Some(io) = IO_APIC.get() {
+        let mut io = io.lock();
+        let pin = global_system_interrupt(irq);
+        let entry = io.redirection(pin).masked(masked);
+        io.set_redirection(pin, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn pit_is_overridden() {
+        assert_eq!(global_system_interrupt(PIT_IRQ as u8), 2);
+        assert_eq!(global_system_interrupt(KEYBOARD_IRQ as u8), 1);
+        assert_eq!(global_system_interrupt(COM1_IRQ as u8), 4);
+    }
+}
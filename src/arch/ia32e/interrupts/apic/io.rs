@@ -0,0 +1,325 @@
+//! A module containing the [`IoApic`] structure and its
+//! [`RedirectionEntry`] routing external interrupts to local APICs.
+use crate::utils::bitfield::*;
+use core::fmt;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Offsets of the memory mapped registers from the I/O APIC base address.
+mod mmio {
+    /// I/O register select, holds the index of the register to access.
+    pub const REGISTER_SELECT: u64 = 0x00;
+    /// I/O window, holds the value of the selected register.
+    pub const WINDOW: u64 = 0x10;
+}
+
+/// Indexes of the I/O APIC registers, accessed through the I/O window.
+mod register {
+    /// I/O APIC identification register.
+    pub const ID: u8 = 0x00;
+    /// I/O APIC version register.
+    pub const VERSION: u8 = 0x01;
+    /// First register of the redirection table, every entry spans two
+    /// registers.
+    pub const REDIRECTION_TABLE: u8 = 0x10;
+}
+
+/// The set of all field offsets for the [`RedirectionEntry`] structure.
+mod offset {
+    /// Bounds of the delivery mode bits.
+    pub mod delivery_mode {
+        /// Lower bit offset.
+        pub const LOWER: usize = 8;
+        /// Upper bit offset.
+        pub const UPPER: usize = 10;
+    }
+    /// Offset of the destination mode bit.
+    pub const LOGICAL_DESTINATION: usize = 11;
+    /// Offset of the delivery status bit.
+    pub const DELIVERY_PENDING: usize = 12;
+    /// Offset of the interrupt input pin polarity bit.
+    pub const ACTIVE_LOW: usize = 13;
+    /// Offset of the trigger mode bit.
+    pub const LEVEL_TRIGGERED: usize = 15;
+    /// Offset of the interrupt mask bit.
+    pub const MASKED: usize = 16;
+    /// Bounds of the destination field bits.
+    pub mod destination {
+        /// Lower bit offset.
+        pub const LOWER: usize = 56;
+        /// Upper bit offset.
+        pub const UPPER: usize = 63;
+    }
+}
+
+/// How the interrupt is delivered to the destination processors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DeliveryMode {
+    /// Deliver the vector to every destination processor.
+    Fixed = 0b000,
+    /// Deliver the vector to the lowest priority destination processor.
+    LowestPriority = 0b001,
+    /// System management interrupt, the vector is ignored.
+    Smi = 0b010,
+    /// Non maskable interrupt, the vector is ignored.
+    Nmi = 0b100,
+    /// INIT interrupt, the vector is ignored.
+    Init = 0b101,
+    /// Deliver as if the interrupt came from an 8259A compatible controller.
+    ExtInt = 0b111,
+}
+
+/// The interrupt input pin signal polarity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    /// The pin is asserted high, ISA interrupts use this polarity.
+    ActiveHigh,
+    /// The pin is asserted low, PCI interrupts use this polarity.
+    ActiveLow,
+}
+
+/// The interrupt input pin trigger mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// Edge triggered, ISA interrupts use this mode.
+    Edge,
+    /// Level triggered, PCI interrupts use this mode.
+    Level,
+}
+
+/// An I/O APIC redirection table entry (cf. 82093AA datasheet, 3.2.4).
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectionEntry(u64);
+
+impl RedirectionEntry {
+    /// Create a new fixed, edge triggered and active high entry delivering
+    /// vector 0 to the physical APIC 0.
+    pub const fn const_default() -> Self {
+        RedirectionEntry(0)
+    }
+
+    /// Change a [`RedirectionEntry`]'s vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - The IDT vector raised on the destination processors.
+    pub fn vector(self, vector: u8) -> Self {
+        Self(self.0.set_bits(..8, vector.into()))
+    }
+
+    /// Change a [`RedirectionEntry`]'s delivery mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The desired [`DeliveryMode`].
+    pub fn delivery_mode(self, mode: DeliveryMode) -> Self {
+        use offset::delivery_mode::{LOWER, UPPER};
+        Self(self.0.set_bits(LOWER..=UPPER, mode as u64))
+    }
+
+    /// Change a [`RedirectionEntry`]'s destination mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `logical` - `true` for a logical destination, `false` for a
+    ///   physical APIC ID.
+    pub fn logical_destination(self, logical: bool) -> Self {
+        Self(self.0.set_bit(offset::LOGICAL_DESTINATION, logical))
+    }
+
+    /// Change a [`RedirectionEntry`]'s pin polarity.
+    ///
+    /// # Arguments
+    ///
+    /// * `polarity` - The desired [`Polarity`].
+    pub fn polarity(self, polarity: Polarity) -> Self {
+        Self(
+            self.0
+                .set_bit(offset::ACTIVE_LOW, polarity == Polarity::ActiveLow),
+        )
+    }
+
+    /// Change a [`RedirectionEntry`]'s trigger mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The desired [`TriggerMode`].
+    pub fn trigger_mode(self, mode: TriggerMode) -> Self {
+        Self(
+            self.0
+                .set_bit(offset::LEVEL_TRIGGERED, mode == TriggerMode::Level),
+        )
+    }
+
+    /// Change a [`RedirectionEntry`]'s mask bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `masked` - The desired bit value, `true` disables the interrupt.
+    pub fn masked(self, masked: bool) -> Self {
+        Self(self.0.set_bit(offset::MASKED, masked))
+    }
+
+    /// Change a [`RedirectionEntry`]'s destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The APIC ID, or set of processors in logical mode.
+    pub fn destination(self, destination: u8) -> Self {
+        use offset::destination::{LOWER, UPPER};
+        Self(self.0.set_bits(LOWER..=UPPER, destination.into()))
+    }
+
+    /// Get the vector of the entry.
+    pub fn get_vector(&self) -> u8 {
+        self.0.get_bits(..8).try_into().unwrap()
+    }
+
+    /// Get the destination of the entry.
+    pub fn get_destination(&self) -> u8 {
+        use offset::destination::{LOWER, UPPER};
+        self.0.get_bits(LOWER..=UPPER).try_into().unwrap()
+    }
+
+    /// Whether the interrupt is masked.
+    pub fn is_masked(&self) -> bool {
+        self.0.get_bit(offset::MASKED)
+    }
+
+    /// Whether an interrupt is waiting to be delivered.
+    pub fn is_pending(&self) -> bool {
+        self.0.get_bit(offset::DELIVERY_PENDING)
+    }
+}
+
+impl fmt::Display for RedirectionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use offset::delivery_mode::{LOWER, UPPER};
+        write!(
+            f,
+            "Vector: {}\nDelivery mode: {:#b}\nLogical: {}\nActive low: {}\nLevel: {}\nMasked: {}\nDestination: {}",
+            self.get_vector(),
+            self.0.get_bits(LOWER..=UPPER),
+            self.0.get_bit(offset::LOGICAL_DESTINATION),
+            self.0.get_bit(offset::ACTIVE_LOW),
+            self.0.get_bit(offset::LEVEL_TRIGGERED),
+            self.is_masked(),
+            self.get_destination()
+        )
+    }
+}
+
+/// An I/O APIC, accessed through its memory mapped register window.
+///
+/// # Note
+///
+/// Every access selects a register before using the window, methods take
+/// `&mut self` so accesses cannot interleave.
+pub struct IoApic {
+    /// Virtual address of the registers.
+    base: u64,
+}
+
+impl IoApic {
+    /// Create an [`IoApic`] from the virtual address of its registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The virtual address the register page is mapped at.
+    ///
+    /// # Safety
+    ///
+    /// The I/O APIC register page must be mapped, uncached, at `base`.
+    pub const unsafe fn new(base: u64) -> Self {
+        IoApic { base }
+    }
+
+    unsafe fn read(&mut self, register: u8) -> u32 {
+        write_volatile(
+            (self.base + mmio::REGISTER_SELECT) as *mut u32,
+            register.into(),
+        );
+        read_volatile((self.base + mmio::WINDOW) as *const u32)
+    }
+
+    unsafe fn write(&mut self, register: u8, value: u32) {
+        write_volatile(
+            (self.base + mmio::REGISTER_SELECT) as *mut u32,
+            register.into(),
+        );
+        write_volatile((self.base + mmio::WINDOW) as *mut u32, value);
+    }
+
+    /// Get the I/O APIC identification.
+    pub fn id(&mut self) -> u8 {
+        unsafe { self.read(register::ID) }
+            .get_bits(24..28)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Get the index of the last redirection table entry.
+    pub fn max_redirection_entry(&mut self) -> u8 {
+        unsafe { self.read(register::VERSION) }
+            .get_bits(16..24)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Read a redirection table entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The entry index, which is the input pin number.
+    pub fn redirection(&mut self, index: u8) -> RedirectionEntry {
+        let register = register::REDIRECTION_TABLE + index * 2;
+        unsafe {
+            let low = self.read(register);
+            let high = self.read(register + 1);
+            RedirectionEntry(u64::from(high) << 32 | u64::from(low))
+        }
+    }
+
+    /// Write a redirection table entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The entry index, which is the input pin number.
+    /// * `entry` - The new entry.
+    pub fn set_redirection(&mut self, index: u8, entry: RedirectionEntry) {
+        let register = register::REDIRECTION_TABLE + index * 2;
+        unsafe {
+            // Mask the entry while it is half written.
+            self.write(register, entry.masked(true).0 as u32);
+            self.write(register + 1, (entry.0 >> 32) as u32);
+            self.write(register, entry.0 as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn default_is_fixed_unmasked() {
+        let entry = RedirectionEntry::default();
+        assert_eq!(entry.0, 0);
+        assert!(!entry.is_masked());
+    }
+
+    #[test_case]
+    fn builder_bits() {
+        let entry = RedirectionEntry::default()
+            .vector(0x21)
+            .delivery_mode(DeliveryMode::ExtInt)
+            .logical_destination(true)
+            .polarity(Polarity::ActiveLow)
+            .trigger_mode(TriggerMode::Level)
+            .masked(true)
+            .destination(3);
+        assert_eq!(entry.0, 0x0300_0000_0001_AF21);
+        assert_eq!(entry.get_vector(), 0x21);
+        assert_eq!(entry.get_destination(), 3);
+    }
+}
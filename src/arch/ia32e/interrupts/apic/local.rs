@@ -0,0 +1,140 @@
+//! A module containing the [`LocalApic`] structure giving access to the
+//! memory mapped registers of the processor's local APIC.
+use crate::utils::bitfield::*;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Offsets of the local APIC registers from its base address (cf. Intel
+/// volume III, 10.4.1).
+mod register {
+    /// Local APIC ID register.
+    pub const ID: u64 = 0x20;
+    /// Local APIC version register.
+    pub const VERSION: u64 = 0x30;
+    /// Task priority register (TPR).
+    pub const TASK_PRIORITY: u64 = 0x80;
+    /// End of interrupt register.
+    pub const EOI: u64 = 0xB0;
+    /// Spurious interrupt vector register.
+    pub const SPURIOUS: u64 = 0xF0;
+    /// Error status register (ESR).
+    pub const ERROR_STATUS: u64 = 0x280;
+    /// Local vector table timer entry.
+    pub const LVT_TIMER: u64 = 0x320;
+    /// Local vector table error entry.
+    pub const LVT_ERROR: u64 = 0x370;
+}
+
+/// The set of all field offsets within the local APIC registers.
+mod offset {
+    /// Offset of the APIC software enable bit in the spurious interrupt
+    /// vector register.
+    pub const SOFTWARE_ENABLE: usize = 8;
+    /// Offset of the mask bit in a local vector table entry.
+    pub const LVT_MASKED: usize = 16;
+
+    /// Bounds of the APIC ID bits within the ID register.
+    pub mod id {
+        /// Lower bit offset.
+        pub const LOWER: usize = 24;
+        /// Upper bit offset.
+        pub const UPPER: usize = 31;
+    }
+
+    /// Bounds of the max LVT entry bits within the version register.
+    pub mod max_lvt_entry {
+        /// Lower bit offset.
+        pub const LOWER: usize = 16;
+        /// Upper bit offset.
+        pub const UPPER: usize = 23;
+    }
+}
+
+/// The local APIC of the current processor, accessed through its memory
+/// mapped registers.
+pub struct LocalApic {
+    /// Virtual address of the registers.
+    base: u64,
+}
+
+impl LocalApic {
+    /// Create a [`LocalApic`] from the virtual address of its registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The virtual address the register page is mapped at.
+    ///
+    /// # Safety
+    ///
+    /// The local APIC register page must be mapped, uncached, at `base`.
+    pub const unsafe fn new(base: u64) -> Self {
+        LocalApic { base }
+    }
+
+    unsafe fn read(&self, register: u64) -> u32 {
+        read_volatile((self.base + register) as *const u32)
+    }
+
+    unsafe fn write(&self, register: u64, value: u32) {
+        write_volatile((self.base + register) as *mut u32, value);
+    }
+
+    /// Get the APIC ID of the current processor.
+    pub fn id(&self) -> u8 {
+        use offset::id::{LOWER, UPPER};
+        unsafe { self.read(register::ID) }
+            .get_bits(LOWER..=UPPER)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Get the version of the local APIC.
+    pub fn version(&self) -> u8 {
+        unsafe { self.read(register::VERSION) }
+            .get_bits(..8)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Get the index of the last local vector table entry.
+    pub fn max_lvt_entry(&self) -> u8 {
+        use offset::max_lvt_entry::{LOWER, UPPER};
+        unsafe { self.read(register::VERSION) }
+            .get_bits(LOWER..=UPPER)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Software enable the local APIC, accepting every interrupt priority
+    /// with the timer and error interrupts masked.
+    ///
+    /// # Arguments
+    ///
+    /// * `spurious_vector` - The vector used for spurious interrupts.
+    pub fn enable(&self, spurious_vector: u8) {
+        unsafe {
+            self.write(register::TASK_PRIORITY, 0);
+            self.write(register::LVT_TIMER, 0_u32.set_bit(offset::LVT_MASKED, true));
+            self.write(register::LVT_ERROR, 0_u32.set_bit(offset::LVT_MASKED, true));
+            self.write(
+                register::SPURIOUS,
+                u32::from(spurious_vector).set_bit(offset::SOFTWARE_ENABLE, true),
+            );
+        }
+    }
+
+    /// Signal the end of the interrupt being serviced.
+    pub fn end_of_interrupt(&self) {
+        unsafe {
+            self.write(register::EOI, 0);
+        }
+    }
+
+    /// Get the errors detected since the last call.
+    pub fn error_status(&self) -> u32 {
+        unsafe {
+            // The register is updated by a write.
+            self.write(register::ERROR_STATUS, 0);
+            self.read(register::ERROR_STATUS)
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! A module reporting fatal exceptions with the whole processor state: the
+//! general purpose registers saved by the trampolines, the control registers,
+//! the segment selectors and the decoded error code.
+use super::page_fault::PageFaultErrorCode;
+use super::trap::TrapFrame;
+use crate::arch::ia32e::registers::{self, IA32_EFER};
+use crate::arch::ia32e::selector::SegmentSelector;
+use crate::debug::backtrace;
+use crate::utils::bitfield::*;
+use core::arch::asm;
+use core::fmt;
+
+/// The set of all field offsets for the [`SelectorErrorCode`] structure.
+mod offset {
+    /// Offset of the external event bit (EXT).
+    pub const EXTERNAL: usize = 0;
+    /// Offset of the descriptor location bit (IDT), when set the index
+    /// refers to a gate of the IDT.
+    pub const IDT: usize = 1;
+}
+
+/// The error code of exceptions related to a segment selector or an IDT
+/// vector: #TS, #NP, #SS and #GP (cf. Intel volume III, 6.13).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SelectorErrorCode(u64);
+
+impl From<u64> for SelectorErrorCode {
+    fn from(value: u64) -> Self {
+        SelectorErrorCode(value)
+    }
+}
+
+impl SelectorErrorCode {
+    /// Whether the exception occurred while delivering an event external to
+    /// the program, such as an interrupt.
+    pub fn external(&self) -> bool {
+        self.0.get_bit(offset::EXTERNAL)
+    }
+
+    /// Get the IDT vector involved in the exception, if the error code
+    /// refers to the IDT.
+    pub fn vector(&self) -> Option<u16> {
+        self.0.get_bit(offset::IDT).then(|| self.selector().index())
+    }
+
+    /// Get the selector involved in the exception, its privilege level bits
+    /// are not part of the error code and read as 0.
+    pub fn selector(&self) -> SegmentSelector {
+        SegmentSelector::from(self.0.get_bits(0..16) as u16 & !0b11)
+    }
+
+    /// Whether the error code refers to no selector, as for a #GP raised by
+    /// a general protection violation.
+    pub fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for SelectorErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_null() {
+            return write!(f, "Error code: 0 (no selector)");
+        }
+        write!(f, "Error code: {:#X} (", self.0)?;
+        match self.vector() {
+            Some(vector) => write!(f, "IDT vector {}", vector)?,
+            None => {
+                let selector = self.selector();
+                write!(
+                    f,
+                    "{} index {}",
+                    selector.table_indicator(),
+                    selector.index()
+                )?;
+            }
+        }
+        if self.external() {
+            write!(f, ", external event")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// The control registers and EFER, as set when an exception occurred.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ControlRegisters {
+    pub cr0: u64,
+    pub cr2: u64,
+    pub cr3: u64,
+    pub cr4: u64,
+    pub efer: u64,
+}
+
+impl ControlRegisters {
+    /// Read the control registers of the processor.
+    pub fn read() -> Self {
+        ControlRegisters {
+            cr0: registers::cr0(),
+            cr2: registers::cr2(),
+            cr3: registers::cr3(),
+            cr4: registers::cr4(),
+            // EFER is available on every IA-32e capable processor.
+            efer: unsafe { registers::read_msr(IA32_EFER) },
+        }
+    }
+}
+
+impl fmt::Display for ControlRegisters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "CR0: {:#018X} CR2: {:#018X} CR3: {:#018X}",
+            self.cr0, self.cr2, self.cr3
+        )?;
+        write!(f, "CR4: {:#018X} EFER: {:#018X}", self.cr4, self.efer)
+    }
+}
+
+/// The data segment selectors, which are not saved in the [`TrapFrame`] as
+/// exception handlers do not change them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DataSelectors {
+    pub ds: u16,
+    pub es: u16,
+    pub fs: u16,
+    pub gs: u16,
+}
+
+impl DataSelectors {
+    /// Read the data segment selectors of the processor.
+    pub fn read() -> Self {
+        let (ds, es, fs, gs): (u16, u16, u16, u16);
+        unsafe {
+            asm!(
+                "mov {:x}, ds",
+                "mov {:x}, es",
+                "mov {:x}, fs",
+                "mov {:x}, gs",
+                out(reg) ds,
+                out(reg) es,
+                out(reg) fs,
+                out(reg) gs,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        DataSelectors { ds, es, fs, gs }
+    }
+}
+
+impl fmt::Display for DataSelectors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DS: {:#X} ES: {:#X} FS: {:#X} GS: {:#X}",
+            self.ds, self.es, self.fs, self.gs
+        )
+    }
+}
+
+/// The whole processor state when an exception occurred.
+pub struct RegisterDump<'a> {
+    pub frame: &'a TrapFrame,
+    pub control: ControlRegisters,
+    pub selectors: DataSelectors,
+}
+
+impl<'a> RegisterDump<'a> {
+    /// Capture the state of the processor, which must be called from the
+    /// exception handler before any of the registers is changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The registers saved on the exception.
+    pub fn capture(frame: &'a TrapFrame) -> Self {
+        RegisterDump {
+            frame,
+            control: ControlRegisters::read(),
+            selectors: DataSelectors::read(),
+        }
+    }
+}
+
+impl fmt::Display for RegisterDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.frame)?;
+        writeln!(f, "{}", self.control)?;
+        write!(f, "{}", self.selectors)
+    }
+}
+
+/// Get the name of an exception vector.
+///
+/// # Arguments
+///
+/// * `vector` - The exception vector.
+pub fn exception_name(vector: u64) -> &'static str {
+    match vector {
+        0 => "Division by zero",
+        1 => "Debug exception",
+        2 => "Non-Maskable Interrupt",
+        3 => "Breakpoint",
+        4 => "Overflow",
+        5 => "Bound range exceeded",
+        6 => "Invalid opcode",
+        7 => "Device not available",
+        8 => "Double fault",
+        9 => "Coprocessor segment overrun",
+        10 => "Invalid TSS",
+        11 => "Segment not present",
+        12 => "Stack segment fault",
+        13 => "General protection fault",
+        14 => "Page fault",
+        16 => "x87 floating point exception",
+        17 => "Unaligned memory data reference",
+        18 => "Machine check exception",
+        19 => "SIMD floating point exception",
+        20 => "Virtualization exception",
+        21 => "Control protection exception",
+        _ => "Reserved exception",
+    }
+}
+
+/// Decoded error code of an exception.
+struct ErrorCode {
+    vector: u64,
+    value: u64,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.vector {
+            10..=13 => write!(f, "{}", SelectorErrorCode::from(self.value)),
+            14 => write!(f, "{}", PageFaultErrorCode::from(self.value)),
+            8 | 17 | 21 => write!(f, "Error code: {:#X}", self.value),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Panic with the name of the exception, its decoded error code and a dump
+/// of the registers.
+///
+/// # Arguments
+///
+/// * `message` - A description of the failure, the exception name is used
+///   when `None`.
+/// * `frame` - The registers saved on the exception.
+pub fn panic_with_dump(message: Option<fmt::Arguments>, frame: &TrapFrame) -> ! {
+    let dump = RegisterDump::capture(frame);
+    backtrace::set_panic_origin(frame);
+    let error_code = ErrorCode {
+        vector: frame.vector,
+        value: frame.error_code,
+    };
+    match message {
+        Some(message) => panic!("{}\n{}\n{}", message, error_code, dump),
+        None => panic!(
+            "{} (vector {})\n{}\n{}",
+            exception_name(frame.vector),
+            frame.vector,
+            error_code,
+            dump
+        ),
+    }
+}
+
+/// The default handler of exceptions, which are all fatal.
+pub fn fatal_exception(frame: &mut TrapFrame) {
+    panic_with_dump(None, frame);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::ia32e::selector::TableIndicator;
+    use core::fmt::Write;
+
+    /// A fixed size buffer to format into.
+    struct Buffer {
+        data: [u8; 512],
+        length: usize,
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.length + s.len();
+            self.data
+                .get_mut(self.length..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.length = end;
+            Ok(())
+        }
+    }
+
+    #[test_case]
+    fn page_fault_error_code_printed_once() {
+        let mut buffer = Buffer {
+            data: [0; 512],
+            length: 0,
+        };
+        let code = ErrorCode {
+            vector: 14,
+            value: 0b11,
+        };
+        write!(buffer, "{}", code).unwrap();
+        let text = core::str::from_utf8(&buffer.data[..buffer.length]).unwrap();
+        assert_eq!(text.matches("Error code").count(), 1);
+    }
+
+    #[test_case]
+    fn selector_error_code() {
+        // GDT entry 5.
+        let code = SelectorErrorCode::from(5 << 3);
+        assert_eq!(code.vector(), None);
+        assert_eq!(code.selector().index(), 5);
+        assert!(matches!(
+            code.selector().table_indicator(),
+            TableIndicator::GDT
+        ));
+        // LDT entry 2, external event.
+        let code = SelectorErrorCode::from(2 << 3 | 0b101);
+        assert!(code.external());
+        assert!(matches!(
+            code.selector().table_indicator(),
+            TableIndicator::LDT
+        ));
+        // IDT vector 13.
+        let code = SelectorErrorCode::from(13 << 3 | 0b10);
+        assert_eq!(code.vector(), Some(13));
+        assert!(!code.external());
+    }
+}
@@ -4,24 +4,174 @@ use crate::arch::ia32::interrupts::pic::*;
 use crate::arch::ia32::interrupts::pit;
 use crate::arch::ia32::interrupts::pit::*;
 use crate::arch::ia32e::{
+    debug,
     descriptor::gate::Gate,
     interrupts::frame::InterruptStackFrame,
+    interrupts::page_fault::{self, PageFaultErrorCode, Resolution},
+    interrupts::{
+        apic, end_of_interrupt, fatal,
+        trap::{self, TrapFrame},
+    },
+    registers,
     selector::{SegmentSelector, TableIndicator},
+    task::{DOUBLE_FAULT_IST_INDEX, MACHINE_CHECK_IST_INDEX, NMI_IST_INDEX},
     PrivilegeLevel,
 };
-use crate::arch::in_byte;
+use crate::drivers::ps2::{keyboard, mouse};
+use crate::serial;
 use crate::utils::bitfield::*;
 
+use crate::sync::{IrqSafeSpinLock, IrqSafeSpinLockGuard, RwLock};
 use core::arch::asm;
+use core::marker::PhantomData;
 use core::ptr::addr_of;
 use core::{fmt, mem};
-use log::trace;
+use log::{info, trace};
 
 const IDT_LEN: usize = 256;
 const GDT_KERNEL_CODE: u16 = 1;
 
+/// First vector available for external interrupts, lower ones are reserved
+/// for processor exceptions.
+pub const FIRST_IRQ_VECTOR: usize = 32;
+
+/// An interrupt handler for vectors without error code.
+pub type HandlerFunc = extern "x86-interrupt" fn(InterruptStackFrame);
+/// An interrupt handler for vectors pushing an error code.
+pub type HandlerFuncWithErrCode = extern "x86-interrupt" fn(InterruptStackFrame, u64);
+/// An interrupt handler for aborts without error code.
+pub type DivergingHandlerFunc = extern "x86-interrupt" fn(InterruptStackFrame) -> !;
+/// An interrupt handler for aborts pushing an error code.
+pub type DivergingHandlerFuncWithErrCode = extern "x86-interrupt" fn(InterruptStackFrame, u64) -> !;
+
+/// A function type that can be installed in an [`InterruptDescriptorTable`].
+pub trait Handler: Copy {
+    /// Get the address of the handler.
+    fn address(self) -> VirtualAddress;
+}
+
+impl Handler for HandlerFunc {
+    fn address(self) -> VirtualAddress {
+        VirtualAddress::from_handler(self)
+    }
+}
+
+impl Handler for HandlerFuncWithErrCode {
+    fn address(self) -> VirtualAddress {
+        VirtualAddress::from_handler_with_err(self)
+    }
+}
+
+impl Handler for DivergingHandlerFunc {
+    fn address(self) -> VirtualAddress {
+        VirtualAddress::new(self as *const () as u64)
+    }
+}
+
+impl Handler for DivergingHandlerFuncWithErrCode {
+    fn address(self) -> VirtualAddress {
+        VirtualAddress::new(self as *const () as u64)
+    }
+}
+
+/// An interrupt vector along the type of handler it expects.
+pub struct Vector<F: Handler> {
+    index: usize,
+    handler: PhantomData<F>,
+}
+
+impl<F: Handler> Clone for Vector<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: Handler> Copy for Vector<F> {}
+
+impl<F: Handler> Vector<F> {
+    const fn new(index: usize) -> Self {
+        Self {
+            index,
+            handler: PhantomData,
+        }
+    }
+
+    /// Get the index of the vector within the IDT.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Vector<HandlerFunc> {
+    /// Get the vector of an external interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The vector index, between 32 and 255.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the index is reserved for exceptions or
+    /// out of the IDT.
+    pub fn irq(index: usize) -> Self {
+        assert!(
+            (FIRST_IRQ_VECTOR..IDT_LEN).contains(&index),
+            "IRQ vectors must be between 32 and 255"
+        );
+        Self::new(index)
+    }
+}
+
+/// The processor exception vectors (cf. Intel volume III, 6.3.1).
+pub mod vector {
+    use super::*;
+
+    /// Divide error (#DE).
+    pub const DIVIDE_ERROR: Vector<HandlerFunc> = Vector::new(0);
+    /// Debug exception (#DB).
+    pub const DEBUG: Vector<HandlerFunc> = Vector::new(1);
+    /// Non-maskable interrupt.
+    pub const NMI: Vector<HandlerFunc> = Vector::new(2);
+    /// Breakpoint (#BP).
+    pub const BREAKPOINT: Vector<HandlerFunc> = Vector::new(3);
+    /// Overflow (#OF).
+    pub const OVERFLOW: Vector<HandlerFunc> = Vector::new(4);
+    /// Bound range exceeded (#BR).
+    pub const BOUND_RANGE: Vector<HandlerFunc> = Vector::new(5);
+    /// Invalid opcode (#UD).
+    pub const INVALID_OPCODE: Vector<HandlerFunc> = Vector::new(6);
+    /// Device not available (#NM).
+    pub const DEVICE_NOT_AVAILABLE: Vector<HandlerFunc> = Vector::new(7);
+    /// Double fault (#DF).
+    pub const DOUBLE_FAULT: Vector<DivergingHandlerFuncWithErrCode> = Vector::new(8);
+    /// Coprocessor segment overrun.
+    pub const COPROCESSOR_SEGMENT_OVERRUN: Vector<HandlerFunc> = Vector::new(9);
+    /// Invalid TSS (#TS).
+    pub const INVALID_TSS: Vector<HandlerFuncWithErrCode> = Vector::new(10);
+    /// Segment not present (#NP).
+    pub const SEGMENT_NOT_PRESENT: Vector<HandlerFuncWithErrCode> = Vector::new(11);
+    /// Stack segment fault (#SS).
+    pub const STACK_SEGMENT_FAULT: Vector<HandlerFuncWithErrCode> = Vector::new(12);
+    /// General protection (#GP).
+    pub const GENERAL_PROTECTION: Vector<HandlerFuncWithErrCode> = Vector::new(13);
+    /// Page fault (#PF).
+    pub const PAGE_FAULT: Vector<HandlerFuncWithErrCode> = Vector::new(14);
+    /// x87 FPU floating point error (#MF).
+    pub const X87_FLOATING_POINT: Vector<HandlerFunc> = Vector::new(16);
+    /// Alignment check (#AC).
+    pub const ALIGNMENT_CHECK: Vector<HandlerFuncWithErrCode> = Vector::new(17);
+    /// Machine check (#MC).
+    pub const MACHINE_CHECK: Vector<DivergingHandlerFunc> = Vector::new(18);
+    /// SIMD floating point exception (#XM).
+    pub const SIMD_FLOATING_POINT: Vector<HandlerFunc> = Vector::new(19);
+    /// Virtualization exception (#VE).
+    pub const VIRTUALIZATION: Vector<HandlerFunc> = Vector::new(20);
+    /// Control protection exception (#CP).
+    pub const CONTROL_PROTECTION: Vector<HandlerFuncWithErrCode> = Vector::new(21);
+}
+
 pub struct InterruptDescriptorTable {
-    pub entries: [Gate; IDT_LEN],
+    entries: [Gate; IDT_LEN],
 }
 
 impl InterruptDescriptorTable {
@@ -30,6 +180,43 @@ impl InterruptDescriptorTable {
             entries: [Gate::const_default(); IDT_LEN],
         }
     }
+
+    /// Install a handler on a vector. An already present gate only gets its
+    /// routine address replaced, keeping its type, privilege level and
+    /// interrupt stack table.
+    ///
+    /// # Arguments
+    ///
+    /// * `vector` - The vector to install the handler on.
+    /// * `handler` - The handler matching the vector's type.
+    pub fn set_handler<F: Handler>(&mut self, vector: Vector<F>, handler: F) -> &mut Gate {
+        unsafe { self.set_raw_handler(vector.index, handler.address()) }
+    }
+
+    /// Install a routine on a vector from its address, as done by
+    /// [`InterruptDescriptorTable::set_handler`].
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The vector index.
+    /// * `address` - The address of the routine.
+    ///
+    /// # Safety
+    ///
+    /// The routine must follow the interrupt calling convention of the
+    /// vector, handling its error code and returning with `iretq`.
+    pub unsafe fn set_raw_handler(&mut self, index: usize, address: VirtualAddress) -> &mut Gate {
+        let entry = &mut self.entries[index];
+        *entry = if entry.is_present() {
+            entry.offset(address)
+        } else {
+            Gate::interrupt(
+                address,
+                SegmentSelector::new(GDT_KERNEL_CODE, TableIndicator::GDT, PrivilegeLevel::Kernel),
+            )
+        };
+        entry
+    }
 }
 
 impl Default for InterruptDescriptorTable {
@@ -39,7 +226,13 @@ impl Default for InterruptDescriptorTable {
 }
 
 impl InterruptDescriptorTable {
-    pub fn load(&'static self) {
+    /// Load the table in the processor's IDTR.
+    ///
+    /// # Safety
+    ///
+    /// The table must neither move nor be dropped while loaded, which is
+    /// guaranteed for a table stored in a static.
+    pub unsafe fn load(&self) {
         trace!("Loading interrupt descriptor table...");
 
         let idtr = InterruptDescriptorTableRegister::new(
@@ -49,9 +242,7 @@ impl InterruptDescriptorTable {
             self,
         );
 
-        unsafe {
-            asm!("lidt [{}]", in(reg) addr_of!(idtr));
-        }
+        asm!("lidt [{}]", in(reg) addr_of!(idtr));
     }
 }
 
@@ -79,88 +270,82 @@ impl InterruptDescriptorTableRegister {
     }
 }
 
-static mut IDT: InterruptDescriptorTable = InterruptDescriptorTable::const_default();
-
-unsafe fn setup_predefined() {
-    let kernel_segment =
-        SegmentSelector::new(GDT_KERNEL_CODE, TableIndicator::GDT, PrivilegeLevel::Kernel);
-
-    // Divide error
-    IDT.entries[0] = Gate::interrupt(VirtualAddress::from_handler(div_by_zero), kernel_segment);
-    // Debug exception
-    IDT.entries[1] = Gate::interrupt(
-        VirtualAddress::from_handler(debug_exception),
-        kernel_segment,
-    );
-    // NMI interrupt
-    IDT.entries[2] = Gate::interrupt(VirtualAddress::from_handler(nmi), kernel_segment);
-    // Breakpoint
-    IDT.entries[3] = Gate::interrupt(VirtualAddress::from_handler(breakpoint), kernel_segment);
-    // Overflow
-    IDT.entries[4] = Gate::interrupt(VirtualAddress::from_handler(overflow), kernel_segment);
-    // Bound range exceeded
-    IDT.entries[5] = Gate::interrupt(VirtualAddress::from_handler(bound_range), kernel_segment);
-    // Invalid OP code
-    IDT.entries[6] = Gate::interrupt(VirtualAddress::from_handler(invalid_op), kernel_segment);
-    // Device not available
-    IDT.entries[7] = Gate::interrupt(VirtualAddress::from_handler(device_na), kernel_segment);
-    // Double fault
-    IDT.entries[8] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(double_fault),
-        kernel_segment,
-    );
-    // Coprocessor segment overrun
-    IDT.entries[9] = Gate::interrupt(VirtualAddress::from_handler(coproc_overrun), kernel_segment);
-    // Invalid TSS
-    IDT.entries[10] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(invalid_tss),
-        kernel_segment,
-    );
-    // Segment not present
-    IDT.entries[11] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(segment_not_present),
-        kernel_segment,
-    );
-    // Stack segment fault
-    IDT.entries[12] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(stack_fault),
-        kernel_segment,
-    );
-    // General protection
-    IDT.entries[13] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(general_fault),
-        kernel_segment,
-    );
-    // Page fault
-    IDT.entries[14] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(page_fault),
-        kernel_segment,
-    );
-    // x87 fpu floating point error
-    IDT.entries[16] = Gate::interrupt(VirtualAddress::from_handler(x87_fpe), kernel_segment);
-    // Alignment check
-    IDT.entries[17] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(alignment_check),
-        kernel_segment,
-    );
-    // Machine check
-    IDT.entries[18] = Gate::interrupt(VirtualAddress::from_handler(machine_check), kernel_segment);
-    // SIMD floating point Exception
-    IDT.entries[19] = Gate::interrupt(VirtualAddress::from_handler(simd_fpe), kernel_segment);
-    // Virtualization exception
-    IDT.entries[20] = Gate::interrupt(VirtualAddress::from_handler(virt_exception), kernel_segment);
-    // Control protection exception
-    IDT.entries[21] = Gate::interrupt(
-        VirtualAddress::from_handler_with_err(control_protection),
-        kernel_segment,
-    );
+static IDT: IrqSafeSpinLock<InterruptDescriptorTable> =
+    IrqSafeSpinLock::new(InterruptDescriptorTable::const_default());
+
+/// Lock the kernel IDT, e.g. to display it.
+///
+/// # Note
+///
+/// Interrupts are masked until the guard is dropped.
+pub fn table() -> IrqSafeSpinLockGuard<'static, InterruptDescriptorTable> {
+    IDT.lock()
+}
+
+/// Install a handler on a vector of the kernel IDT, it can be called before
+/// or after the IDT has been loaded.
+///
+/// # Arguments
+///
+/// * `vector` - The vector to install the handler on.
+/// * `handler` - The handler matching the vector's type.
+pub fn register<F: Handler>(vector: Vector<F>, handler: F) {
+    // The gate is not written atomically, the lock masks interrupts so it
+    // cannot be used while being modified.
+    IDT.lock().set_handler(vector, handler);
+}
+
+/// Install a routine on a vector of the kernel IDT from its address.
+///
+/// # Arguments
+///
+/// * `index` - The vector index.
+/// * `address` - The address of the routine.
+///
+/// # Safety
+///
+/// See [`InterruptDescriptorTable::set_raw_handler`].
+pub unsafe fn register_raw(index: usize, address: VirtualAddress) {
+    IDT.lock().set_raw_handler(index, address);
+}
+
+/// A function called on a breakpoint exception (`int3`), execution then
+/// resumes after the instruction.
+pub type BreakpointCallback = fn(&InterruptStackFrame);
+
+static BREAKPOINT_CALLBACK: RwLock<Option<BreakpointCallback>> = RwLock::new(None);
+
+/// Set the function called on breakpoint exceptions, which are otherwise
+/// only logged.
+///
+/// # Arguments
+///
+/// * `callback` - The function to call, `None` removes the current one.
+pub fn set_breakpoint_callback(callback: Option<BreakpointCallback>) {
+    *BREAKPOINT_CALLBACK.write() = callback;
+}
+
+fn setup_predefined(idt: &mut InterruptDescriptorTable) {
+    // Exceptions go through trampolines saving every register, for their
+    // handlers to report or change the whole interrupted context.
+    for index in (0..FIRST_IRQ_VECTOR).filter(|&index| trap::has_trampoline(index)) {
+        trap::install(idt, index);
+    }
+    for (index, stack) in [
+        (vector::NMI.index(), NMI_IST_INDEX),
+        (vector::DOUBLE_FAULT.index(), DOUBLE_FAULT_IST_INDEX),
+        (vector::MACHINE_CHECK.index(), MACHINE_CHECK_IST_INDEX),
+    ] {
+        let gate = &mut idt.entries[index];
+        *gate = gate.interrupt_stack_table(stack);
+    }
 
-    // PIT interrupt
-    IDT.entries[PIC_OFFSET + PIT_IRQ] =
-        Gate::interrupt(VirtualAddress::from_handler(pit), kernel_segment);
-    // Keyboard interrupt
-    IDT.entries[PIC_OFFSET + KEYBOARD_IRQ] =
-        Gate::interrupt(VirtualAddress::from_handler(keyboard), kernel_segment);
+    idt.set_handler(Vector::irq(PIC_OFFSET + PIT_IRQ), pit);
+    idt.set_handler(Vector::irq(PIC_OFFSET + KEYBOARD_IRQ), keyboard);
+    idt.set_handler(Vector::irq(PIC_OFFSET + COM1_IRQ), serial_primary);
+    idt.set_handler(Vector::irq(PIC_OFFSET + COM2_IRQ), serial_secondary);
+    idt.set_handler(Vector::irq(PIC_OFFSET + MOUSE_IRQ), mouse);
+    idt.set_handler(Vector::irq(apic::SPURIOUS_VECTOR.into()), spurious);
 }
 
 pub fn setup_idt() {
@@ -169,113 +354,113 @@ pub fn setup_idt() {
         pic::setup(
             0b11111111_u8
                 .set_bit(KEYBOARD_IRQ, false)
-                .set_bit(PIT_IRQ, false),
-            0b11111111,
+                .set_bit(PIT_IRQ, false)
+                .set_bit(COM1_IRQ, false)
+                .set_bit(COM2_IRQ, false)
+                .set_bit(CASCADE_IRQ, false),
+            0b11111111_u8.set_bit(MOUSE_IRQ - 8, false),
         );
         pit::setup();
-        setup_predefined();
+        let mut idt = IDT.lock();
+        setup_predefined(&mut idt);
+        trap::register(vector::DEBUG.index(), Some(debug::debug_exception));
+        trap::register(vector::BREAKPOINT.index(), Some(breakpoint));
+        trap::register(vector::DOUBLE_FAULT.index(), Some(double_fault));
+        trap::register(vector::PAGE_FAULT.index(), Some(page_fault));
         trace!("Loading idt...");
-        IDT.load();
+        idt.load();
     }
 }
 
-extern "x86-interrupt" fn div_by_zero(_frame: InterruptStackFrame) {
-    panic!("Division by zero!");
-}
-
-extern "x86-interrupt" fn debug_exception(_frame: InterruptStackFrame) {
-    panic!("Debug exception");
-}
-
-extern "x86-interrupt" fn nmi(_frame: InterruptStackFrame) {
-    panic!("Non-Maskable Interrupt");
-}
-
-extern "x86-interrupt" fn breakpoint(_frame: InterruptStackFrame) {
-    panic!("Breakpoint!");
-}
-
-extern "x86-interrupt" fn overflow(_frame: InterruptStackFrame) {
-    panic!("Overflow occured");
-}
-
-extern "x86-interrupt" fn bound_range(_frame: InterruptStackFrame) {
-    panic!("Bound range exceeded");
-}
-
-extern "x86-interrupt" fn invalid_op(_frame: InterruptStackFrame) {
-    panic!("Invalid opcode");
-}
-
-extern "x86-interrupt" fn device_na(_frame: InterruptStackFrame) {
-    panic!("Device not available");
-}
-
-extern "x86-interrupt" fn double_fault(frame: InterruptStackFrame, _err: u64) {
-    panic!("Double fault!\n{}", frame);
-}
-
-extern "x86-interrupt" fn coproc_overrun(_frame: InterruptStackFrame) {
-    panic!("Coprocessor segment overrun");
-}
-
-extern "x86-interrupt" fn invalid_tss(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Invalid TSS");
-}
-
-extern "x86-interrupt" fn segment_not_present(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Segment not present");
+fn double_fault(frame: &mut TrapFrame) {
+    let fault_address = registers::cr2();
+    // A page fault right below the interrupted stack pointer means the
+    // processor could not even push the fault's frame: the stack overflowed
+    // into its guard page.
+    if frame.stack_pointer.wrapping_sub(fault_address) < 4096 {
+        fatal::panic_with_dump(
+            Some(format_args!(
+                "Double fault: kernel stack overflow (access at {:#X})",
+                fault_address
+            )),
+            frame,
+        );
+    }
+    fatal::panic_with_dump(None, frame);
 }
 
-extern "x86-interrupt" fn stack_fault(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Stack segment fault");
+fn page_fault(frame: &mut TrapFrame) {
+    let address = unsafe { VirtualAddress::unchecked_new(registers::cr2()) };
+    let error_code = PageFaultErrorCode::from(frame.error_code);
+    let stack_frame = frame.interrupt_stack_frame();
+    if page_fault::resolve(address, error_code, &stack_frame) == Resolution::Resolved {
+        return;
+    }
+    fatal::panic_with_dump(
+        Some(format_args!(
+            "Page fault on {} access at {}",
+            error_code.access(),
+            address
+        )),
+        frame,
+    );
 }
 
-extern "x86-interrupt" fn general_fault(_frame: InterruptStackFrame, _err: u64) {
-    panic!("General protection fault");
+fn breakpoint(frame: &mut TrapFrame) {
+    let frame = frame.interrupt_stack_frame();
+    info!("Breakpoint\n{}", frame);
+    // The callback is copied out of the lock, it may replace itself.
+    let callback = *BREAKPOINT_CALLBACK.read();
+    if let Some(callback) = callback {
+        callback(&frame);
+    }
 }
 
-extern "x86-interrupt" fn page_fault(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Page fault");
+extern "x86-interrupt" fn keyboard(_frame: InterruptStackFrame) {
+    keyboard::handle_interrupt();
+    end_of_interrupt(KEYBOARD_IRQ as u8);
 }
 
-extern "x86-interrupt" fn x87_fpe(_frame: InterruptStackFrame) {
-    panic!("Floating point exception");
+extern "x86-interrupt" fn mouse(_frame: InterruptStackFrame) {
+    mouse::handle_interrupt();
+    end_of_interrupt(MOUSE_IRQ as u8);
 }
 
-extern "x86-interrupt" fn alignment_check(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Unaligned memory data reference");
+extern "x86-interrupt" fn pit(_frame: InterruptStackFrame) {
+    TICK_COUNTER.increment();
+    end_of_interrupt(PIT_IRQ as u8);
 }
 
-extern "x86-interrupt" fn machine_check(_frame: InterruptStackFrame) {
-    panic!("Machine check exception");
+extern "x86-interrupt" fn serial_primary(_frame: InterruptStackFrame) {
+    serial::handle_interrupt(COM1_IRQ);
+    end_of_interrupt(COM1_IRQ as u8);
 }
 
-extern "x86-interrupt" fn simd_fpe(_frame: InterruptStackFrame) {
-    panic!("Floating point exception");
+extern "x86-interrupt" fn serial_secondary(_frame: InterruptStackFrame) {
+    serial::handle_interrupt(COM2_IRQ);
+    end_of_interrupt(COM2_IRQ as u8);
 }
 
-extern "x86-interrupt" fn virt_exception(_frame: InterruptStackFrame) {
-    panic!("Virtualization exception");
+extern "x86-interrupt" fn spurious(_frame: InterruptStackFrame) {
+    // Spurious interrupts are not in service, no EOI is expected.
 }
 
-extern "x86-interrupt" fn control_protection(_frame: InterruptStackFrame, _err: u64) {
-    panic!("Control protection exception");
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-extern "x86-interrupt" fn keyboard(_frame: InterruptStackFrame) {
-    unsafe {
-        let scancode: u8 = in_byte(0x60_u16);
-        if scancode.get_bit(7) {
-            println!("Key pressed!");
-        }
-        ack_eoi(KEYBOARD_IRQ as u8);
+    #[test_case]
+    fn irq_vector_index() {
+        assert_eq!(Vector::irq(FIRST_IRQ_VECTOR).index(), 32);
+        assert_eq!(Vector::irq(255).index(), 255);
     }
-}
 
-extern "x86-interrupt" fn pit(_frame: InterruptStackFrame) {
-    unsafe {
-        TICK_COUNTER.increment();
-        ack_eoi(PIT_IRQ as u8);
+    #[test_case]
+    fn set_handler_present() {
+        let mut idt = InterruptDescriptorTable::const_default();
+        let vector = Vector::irq(PIC_OFFSET + PIT_IRQ);
+        assert!(!idt.entries[vector.index()].is_present());
+        idt.set_handler(vector, pit);
+        assert!(idt.entries[vector.index()].is_present());
     }
 }
@@ -0,0 +1,219 @@
+//! A module containing the page fault error code decoding and the registry of
+//! fault resolvers consulted before a page fault is considered fatal.
+use crate::arch::ia32::address::VirtualAddress;
+use crate::arch::ia32e::interrupts::frame::InterruptStackFrame;
+use crate::sync::RwLock;
+use crate::utils::bitfield::*;
+use core::fmt;
+
+/// Maximum number of fault resolvers that can be registered.
+const MAX_RESOLVERS: usize = 8;
+
+/// The set of all field offsets for the [`PageFaultErrorCode`] structure.
+mod offset {
+    /// Offset of the present bit (P).
+    pub const PRESENT: usize = 0;
+    /// Offset of the write bit (W/R).
+    pub const WRITE: usize = 1;
+    /// Offset of the user mode bit (U/S).
+    pub const USER: usize = 2;
+    /// Offset of the reserved bit violation bit (RSVD).
+    pub const RESERVED_BIT: usize = 3;
+    /// Offset of the instruction fetch bit (I/D).
+    pub const INSTRUCTION_FETCH: usize = 4;
+    /// Offset of the protection key bit (PK).
+    pub const PROTECTION_KEY: usize = 5;
+    /// Offset of the shadow stack bit (SS).
+    pub const SHADOW_STACK: usize = 6;
+}
+
+/// The kind of access that caused a page fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Data read.
+    Read,
+    /// Data write.
+    Write,
+    /// Instruction fetch.
+    InstructionFetch,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Access::Read => "read",
+                Access::Write => "write",
+                Access::InstructionFetch => "instruction fetch",
+            }
+        )
+    }
+}
+
+/// The error code pushed by the processor on a page fault (cf. Intel volume
+/// III, 4.7).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PageFaultErrorCode(u64);
+
+impl From<u64> for PageFaultErrorCode {
+    fn from(value: u64) -> Self {
+        PageFaultErrorCode(value)
+    }
+}
+
+impl PageFaultErrorCode {
+    /// Whether the fault was caused by a page-level protection violation,
+    /// `false` meaning the page was not present.
+    pub fn present(&self) -> bool {
+        self.0.get_bit(offset::PRESENT)
+    }
+
+    /// Whether the access causing the fault was a write.
+    pub fn write(&self) -> bool {
+        self.0.get_bit(offset::WRITE)
+    }
+
+    /// Whether the access causing the fault was made in user mode.
+    pub fn user(&self) -> bool {
+        self.0.get_bit(offset::USER)
+    }
+
+    /// Whether a reserved bit was set in a paging structure entry.
+    pub fn reserved_bit(&self) -> bool {
+        self.0.get_bit(offset::RESERVED_BIT)
+    }
+
+    /// Whether the access causing the fault was an instruction fetch.
+    pub fn instruction_fetch(&self) -> bool {
+        self.0.get_bit(offset::INSTRUCTION_FETCH)
+    }
+
+    /// Whether the fault was caused by a protection key violation.
+    pub fn protection_key(&self) -> bool {
+        self.0.get_bit(offset::PROTECTION_KEY)
+    }
+
+    /// Whether the fault was caused by a shadow stack access.
+    pub fn shadow_stack(&self) -> bool {
+        self.0.get_bit(offset::SHADOW_STACK)
+    }
+
+    /// Get the kind of access that caused the fault.
+    pub fn access(&self) -> Access {
+        if self.instruction_fetch() {
+            Access::InstructionFetch
+        } else if self.write() {
+            Access::Write
+        } else {
+            Access::Read
+        }
+    }
+}
+
+impl fmt::Display for PageFaultErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Error code: {:#X}\nPresent: {}\nWrite: {}\nUser: {}\nReserved bit: {}\nInstruction fetch: {}\nProtection key: {}\nShadow stack: {}",
+            self.0,
+            self.present(),
+            self.write(),
+            self.user(),
+            self.reserved_bit(),
+            self.instruction_fetch(),
+            self.protection_key(),
+            self.shadow_stack()
+        )
+    }
+}
+
+/// The outcome of a [`FaultResolver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// The fault has been fixed, the faulting instruction can be restarted.
+    Resolved,
+    /// The resolver does not handle this fault.
+    Unhandled,
+}
+
+/// A function given the chance to fix a page fault, used to implement demand
+/// paging, guard pages or copy-on-write.
+pub type FaultResolver = fn(VirtualAddress, PageFaultErrorCode, &InterruptStackFrame) -> Resolution;
+
+/// Every registered fault resolver, in registration order.
+static RESOLVERS: RwLock<[Option<FaultResolver>; MAX_RESOLVERS]> =
+    RwLock::new([None; MAX_RESOLVERS]);
+
+/// Register a fault resolver, it will be consulted on every page fault after
+/// the previously registered ones.
+///
+/// # Arguments
+///
+/// * `resolver` - The resolver to register.
+pub fn register_resolver(resolver: FaultResolver) -> Result<(), &'static str> {
+    let mut resolvers = RESOLVERS.write();
+    let slot = resolvers
+        .iter_mut()
+        .find(|slot| slot.is_none())
+        .ok_or("Too many page fault resolvers registered.")?;
+    *slot = Some(resolver);
+    Ok(())
+}
+
+/// Ask every registered resolver to fix a page fault until one succeeds.
+///
+/// # Arguments
+///
+/// * `address` - The faulting address read from CR2.
+/// * `error_code` - The decoded error code.
+/// * `frame` - The interrupt stack frame of the fault.
+pub fn resolve(
+    address: VirtualAddress,
+    error_code: PageFaultErrorCode,
+    frame: &InterruptStackFrame,
+) -> Resolution {
+    let resolvers = RESOLVERS.read();
+    resolvers
+        .iter()
+        .flatten()
+        .map(|resolver| resolver(address, error_code, frame))
+        .find(|resolution| *resolution == Resolution::Resolved)
+        .unwrap_or(Resolution::Unhandled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn not_present_read() {
+        let code = PageFaultErrorCode::from(0);
+        assert!(!code.present());
+        assert_eq!(code.access(), Access::Read);
+    }
+
+    #[test_case]
+    fn user_write_violation() {
+        let code = PageFaultErrorCode::from(0b111);
+        assert!(code.present());
+        assert!(code.user());
+        assert_eq!(code.access(), Access::Write);
+    }
+
+    #[test_case]
+    fn instruction_fetch() {
+        let code = PageFaultErrorCode::from(0b10001);
+        assert_eq!(code.access(), Access::InstructionFetch);
+    }
+
+    #[test_case]
+    fn extended_bits() {
+        let code = PageFaultErrorCode::from(0b1101000);
+        assert!(code.reserved_bit());
+        assert!(code.protection_key());
+        assert!(code.shadow_stack());
+        assert!(!code.instruction_fetch());
+    }
+}
@@ -0,0 +1,321 @@
+//! A module containing assembly trampolines saving every general purpose
+//! register in a [`TrapFrame`] before calling a registered Rust handler.
+//!
+//! Unlike `x86-interrupt` handlers, trap handlers can inspect and modify the
+//! whole interrupted context, which debuggers rely on.
+use super::fatal;
+use super::frame::InterruptStackFrame;
+use super::idt::InterruptDescriptorTable;
+use crate::arch::ia32::address::VirtualAddress;
+use crate::arch::ia32e::descriptor::gate::Gate;
+use crate::sync::RwLock;
+use core::arch::global_asm;
+use core::fmt;
+
+/// Number of vectors reserved for processor exceptions.
+const EXCEPTION_COUNT: usize = 32;
+
+/// The interrupted context, as saved by the trampolines and the processor.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct TrapFrame {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+    /// The vector of the exception.
+    pub vector: u64,
+    /// The error code pushed by the processor, 0 for vectors without one.
+    pub error_code: u64,
+    /// Next or faulting instruction (RIP).
+    pub rip: u64,
+    /// Code segment selector (CS).
+    pub code_segment: u64,
+    /// CPU flags (RFLAGS).
+    pub rflags: u64,
+    /// Stack pointer (RSP).
+    pub stack_pointer: u64,
+    /// Stack segment selector (SS).
+    pub stack_segment: u64,
+}
+
+impl TrapFrame {
+    /// Get the part of the frame pushed by the processor.
+    pub fn interrupt_stack_frame(&self) -> InterruptStackFrame {
+        InterruptStackFrame {
+            rip: VirtualAddress::new(self.rip),
+            code_segment: self.code_segment,
+            rflags: self.rflags,
+            stack_pointer: VirtualAddress::new(self.stack_pointer),
+            stack_segment: self.stack_segment,
+        }
+    }
+}
+
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "RAX: {:#018X} RBX: {:#018X} RCX: {:#018X}",
+            self.rax, self.rbx, self.rcx
+        )?;
+        writeln!(
+            f,
+            "RDX: {:#018X} RSI: {:#018X} RDI: {:#018X}",
+            self.rdx, self.rsi, self.rdi
+        )?;
+        writeln!(
+            f,
+            "RBP: {:#018X} RSP: {:#018X} R8:  {:#018X}",
+            self.rbp, self.stack_pointer, self.r8
+        )?;
+        writeln!(
+            f,
+            "R9:  {:#018X} R10: {:#018X} R11: {:#018X}",
+            self.r9, self.r10, self.r11
+        )?;
+        writeln!(
+            f,
+            "R12: {:#018X} R13: {:#018X} R14: {:#018X}",
+            self.r12, self.r13, self.r14
+        )?;
+        writeln!(f, "R15: {:#018X} RIP: {:#018X}", self.r15, self.rip)?;
+        write!(
+            f,
+            "RFLAGS: {:#X} CS: {:#X} SS: {:#X} Vector: {} Error code: {:#X}",
+            self.rflags, self.code_segment, self.stack_segment, self.vector, self.error_code
+        )
+    }
+}
+
+/// A handler called with the interrupted context, returning resumes it with
+/// the possibly modified registers.
+pub type TrapHandler = fn(&mut TrapFrame);
+
+/// Handlers of every exception vector going through a trampoline.
+static HANDLERS: RwLock<[Option<TrapHandler>; EXCEPTION_COUNT]> =
+    RwLock::new([None; EXCEPTION_COUNT]);
+
+/// Set the handler of an exception vector installed with [`install`].
+///
+/// # Arguments
+///
+/// * `vector` - The exception vector.
+/// * `handler` - The handler, `None` restores the default one which panics
+///   with a dump of the registers.
+pub fn register(vector: usize, handler: Option<TrapHandler>) {
+    HANDLERS.write()[vector] = handler;
+}
+
+/// Get the handler of an exception vector.
+///
+/// # Arguments
+///
+/// * `vector` - The exception vector.
+pub fn handler(vector: usize) -> Option<TrapHandler> {
+    HANDLERS.read()[vector]
+}
+
+/// Generate the trampoline of an exception vector, named `flint_trap_N`.
+/// It pushes a null error code when the processor does not, then the
+/// vector, and jumps to the common part.
+macro_rules! trampoline {
+    ($vector:literal) => {
+        concat!(
+            ".global flint_trap_",
+            $vector,
+            "\n",
+            "flint_trap_",
+            $vector,
+            ":\n",
+            "push 0\n",
+            "push ",
+            $vector,
+            "\n",
+            "jmp flint_trap_common\n",
+        )
+    };
+    ($vector:literal, error_code) => {
+        concat!(
+            ".global flint_trap_",
+            $vector,
+            "\n",
+            "flint_trap_",
+            $vector,
+            ":\n",
+            "push ",
+            $vector,
+            "\n",
+            "jmp flint_trap_common\n",
+        )
+    };
+}
+
+// The common part saves the general purpose registers in the `TrapFrame`
+// order. The processor aligns the stack on 16 bytes before pushing its frame
+// and 22 quad words are pushed, the handler is therefore called with an
+// aligned stack.
+global_asm!(
+    trampoline!(0),
+    trampoline!(1),
+    trampoline!(2),
+    trampoline!(3),
+    trampoline!(4),
+    trampoline!(5),
+    trampoline!(6),
+    trampoline!(7),
+    trampoline!(8, error_code),
+    trampoline!(9),
+    trampoline!(10, error_code),
+    trampoline!(11, error_code),
+    trampoline!(12, error_code),
+    trampoline!(13, error_code),
+    trampoline!(14, error_code),
+    trampoline!(16),
+    trampoline!(17, error_code),
+    trampoline!(18),
+    trampoline!(19),
+    trampoline!(20),
+    trampoline!(21, error_code),
+    "flint_trap_common:",
+    "push rax",
+    "push rbx",
+    "push rcx",
+    "push rdx",
+    "push rsi",
+    "push rdi",
+    "push rbp",
+    "push r8",
+    "push r9",
+    "push r10",
+    "push r11",
+    "push r12",
+    "push r13",
+    "push r14",
+    "push r15",
+    "mov rdi, rsp",
+    "cld",
+    "call flint_trap_dispatch",
+    "pop r15",
+    "pop r14",
+    "pop r13",
+    "pop r12",
+    "pop r11",
+    "pop r10",
+    "pop r9",
+    "pop r8",
+    "pop rbp",
+    "pop rdi",
+    "pop rsi",
+    "pop rdx",
+    "pop rcx",
+    "pop rbx",
+    "pop rax",
+    "add rsp, 16",
+    "iretq",
+);
+
+extern "C" {
+    fn flint_trap_0();
+    fn flint_trap_1();
+    fn flint_trap_2();
+    fn flint_trap_3();
+    fn flint_trap_4();
+    fn flint_trap_5();
+    fn flint_trap_6();
+    fn flint_trap_7();
+    fn flint_trap_8();
+    fn flint_trap_9();
+    fn flint_trap_10();
+    fn flint_trap_11();
+    fn flint_trap_12();
+    fn flint_trap_13();
+    fn flint_trap_14();
+    fn flint_trap_16();
+    fn flint_trap_17();
+    fn flint_trap_18();
+    fn flint_trap_19();
+    fn flint_trap_20();
+    fn flint_trap_21();
+}
+
+/// Get the trampoline of an exception vector, if any.
+fn trampoline(vector: usize) -> Option<unsafe extern "C" fn()> {
+    match vector {
+        0 => Some(flint_trap_0),
+        1 => Some(flint_trap_1),
+        2 => Some(flint_trap_2),
+        3 => Some(flint_trap_3),
+        4 => Some(flint_trap_4),
+        5 => Some(flint_trap_5),
+        6 => Some(flint_trap_6),
+        7 => Some(flint_trap_7),
+        8 => Some(flint_trap_8),
+        9 => Some(flint_trap_9),
+        10 => Some(flint_trap_10),
+        11 => Some(flint_trap_11),
+        12 => Some(flint_trap_12),
+        13 => Some(flint_trap_13),
+        14 => Some(flint_trap_14),
+        16 => Some(flint_trap_16),
+        17 => Some(flint_trap_17),
+        18 => Some(flint_trap_18),
+        19 => Some(flint_trap_19),
+        20 => Some(flint_trap_20),
+        21 => Some(flint_trap_21),
+        _ => None,
+    }
+}
+
+/// Whether an exception vector has a trampoline, which is the case of every
+/// exception defined by the architecture.
+pub fn has_trampoline(vector: usize) -> bool {
+    trampoline(vector).is_some()
+}
+
+/// Install the trampoline of an exception vector in an IDT.
+///
+/// Returns the gate, for the caller to set an interrupt stack.
+///
+/// # Arguments
+///
+/// * `idt` - The table to modify.
+/// * `vector` - The exception vector.
+///
+/// # Panics
+///
+/// The vector must have a trampoline, see [`has_trampoline`].
+pub fn install(idt: &mut InterruptDescriptorTable, vector: usize) -> &mut Gate {
+    let trampoline = trampoline(vector).expect("No trampoline for this vector");
+    unsafe { idt.set_raw_handler(vector, VirtualAddress::new(trampoline as *const () as u64)) }
+}
+
+#[no_mangle]
+extern "C" fn flint_trap_dispatch(frame: &mut TrapFrame) {
+    match handler(frame.vector as usize) {
+        Some(handler) => handler(frame),
+        None => fatal::fatal_exception(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn frame_layout() {
+        assert_eq!(core::mem::size_of::<TrapFrame>(), 22 * 8);
+        assert_eq!(core::mem::size_of::<TrapFrame>() % 16, 0);
+    }
+}
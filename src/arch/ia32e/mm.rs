@@ -1,5 +1,7 @@
 pub mod gdt;
+pub mod paging;
 
-pub fn setup() {
+pub fn setup(physical_memory_offset: u64) {
     gdt::setup_gdt();
+    paging::setup(physical_memory_offset);
 }
@@ -2,21 +2,76 @@ use crate::arch::ia32::descriptor::segment::{
     DefaultOperationSize, DescriptorType, Granularity, PrivilegeLevel, SegmentDescriptor,
     SegmentType,
 };
-use crate::arch::ia32::mm::gdt::GlobalDescriptorTable;
+use crate::arch::ia32::mm::gdt::GlobalDescriptorTableRegister;
+use crate::arch::ia32e::descriptor::tss::TssDescriptor;
+use crate::arch::ia32e::selector::{SegmentSelector, TableIndicator};
+use crate::arch::ia32e::task::{self, TaskStateSegment};
+use crate::sync::Once;
+use core::{fmt, mem};
 use log::{debug, trace};
 
-static mut GDT: GlobalDescriptorTable = GlobalDescriptorTable([
-    SegmentDescriptor::const_default(),
-    SegmentDescriptor::const_default(),
-    SegmentDescriptor::const_default(),
-    SegmentDescriptor::const_default(),
-    SegmentDescriptor::const_default(),
-]);
+/// Number of code and data segment descriptors in the table.
+const SEGMENT_COUNT: usize = 5;
+
+/// Index of the TSS descriptor, it spans two regular descriptor slots.
+pub const TSS_INDEX: u16 = SEGMENT_COUNT as u16;
+
+/// The IA-32e global descriptor table, the regular segments are followed by
+/// the 16 bytes wide descriptor of the kernel task state segment.
+#[repr(C, packed)]
+pub struct GlobalDescriptorTable {
+    segments: [SegmentDescriptor; SEGMENT_COUNT],
+    tss: TssDescriptor,
+}
+
+impl GlobalDescriptorTable {
+    /// Create a new table filled with null descriptors.
+    pub const fn const_default() -> Self {
+        GlobalDescriptorTable {
+            segments: [SegmentDescriptor::const_default(); SEGMENT_COUNT],
+            tss: TssDescriptor::const_default(),
+        }
+    }
+
+    pub fn load(&'static self) {
+        trace!("Loading global descriptor table...");
+
+        let gdtr = GlobalDescriptorTableRegister::new(
+            (mem::size_of::<Self>() - 1)
+                .try_into()
+                .expect("Gdt length does not fit in a u16, cannot set GDTR"),
+            self,
+        );
+
+        unsafe {
+            gdtr.load();
+        }
+    }
+}
+
+impl fmt::Display for GlobalDescriptorTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let segments = self.segments;
+        let tss = self.tss;
+        for entry in segments {
+            writeln!(f, "{}", entry)?;
+        }
+        writeln!(f, "{}", tss)
+    }
+}
+
+static GDT: Once<GlobalDescriptorTable> = Once::new();
+
+/// Get the kernel GDT, once set up.
+pub fn table() -> Option<&'static GlobalDescriptorTable> {
+    GDT.get()
+}
 
 pub fn setup_gdt() {
     trace!("Setting up 64bits gdt...");
-    unsafe {
-        GDT = GlobalDescriptorTable([
+    let tss = task::setup_tss();
+    let gdt = GDT.call_once(|| GlobalDescriptorTable {
+        segments: [
             // Null segment
             SegmentDescriptor::default(),
             // Kernel code
@@ -63,8 +118,19 @@ pub fn setup_gdt() {
                 .privilege_level(PrivilegeLevel::Userland)
                 .default_operation_size(DefaultOperationSize::Segment32Bits)
                 .granularity(Granularity::FourKByte),
-        ]);
-        debug!("GDT:\n{}", GDT);
-        GDT.load();
+        ],
+        tss: TssDescriptor::new(
+            tss as *const TaskStateSegment as u64,
+            (mem::size_of::<TaskStateSegment>() - 1) as u32,
+        ),
+    });
+    debug!("GDT:\n{}", gdt);
+    gdt.load();
+    unsafe {
+        task::load(SegmentSelector::new(
+            TSS_INDEX,
+            TableIndicator::GDT,
+            PrivilegeLevel::Kernel,
+        ));
     }
 }
@@ -0,0 +1,565 @@
+//! A module containing the four-level paging structures (PML4, PDPT, PD and
+//! PT) and an [`AddressSpace`] to map, unmap and translate virtual addresses.
+use crate::arch::ia32::address::{PhysicalAddress, VirtualAddress};
+use crate::arch::ia32e::registers;
+use crate::mm::frame::{FrameAllocator, FRAME_SIZE};
+use crate::utils::bitfield::*;
+use core::arch::asm;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+pub use entry::{flags, PageFlags, PageTableEntry};
+use log::trace;
+
+mod entry;
+
+/// Number of entries in every paging structure.
+const ENTRY_COUNT: usize = 512;
+
+/// Offset of the virtual address space where the whole physical memory is
+/// mapped by the bootloader.
+static PHYSICAL_MEMORY_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+/// A paging structure level.
+pub trait TableLevel {
+    /// Offset of the virtual address bits indexing a table of this level.
+    const INDEX_SHIFT: usize;
+}
+
+/// A paging structure level whose entries may reference another table.
+pub trait HierarchicalLevel: TableLevel {
+    /// The level of the tables referenced by this level's entries.
+    type NextLevel: TableLevel;
+}
+
+/// Page map level 4 marker.
+pub enum Level4 {}
+/// Page directory pointer table marker.
+pub enum Level3 {}
+/// Page directory marker.
+pub enum Level2 {}
+/// Page table marker.
+pub enum Level1 {}
+
+impl TableLevel for Level4 {
+    const INDEX_SHIFT: usize = 39;
+}
+
+impl TableLevel for Level3 {
+    const INDEX_SHIFT: usize = 30;
+}
+
+impl TableLevel for Level2 {
+    const INDEX_SHIFT: usize = 21;
+}
+
+impl TableLevel for Level1 {
+    const INDEX_SHIFT: usize = 12;
+}
+
+impl HierarchicalLevel for Level4 {
+    type NextLevel = Level3;
+}
+
+impl HierarchicalLevel for Level3 {
+    type NextLevel = Level2;
+}
+
+impl HierarchicalLevel for Level2 {
+    type NextLevel = Level1;
+}
+
+/// A 4 KiB aligned paging structure of a given level.
+#[repr(C, align(4096))]
+pub struct PageTable<L: TableLevel> {
+    entries: [PageTableEntry; ENTRY_COUNT],
+    level: PhantomData<L>,
+}
+
+/// Page map level 4 table.
+pub type Pml4 = PageTable<Level4>;
+/// Page directory pointer table.
+pub type Pdpt = PageTable<Level3>;
+/// Page directory.
+pub type PageDirectory = PageTable<Level2>;
+/// Page table.
+pub type Pt = PageTable<Level1>;
+
+impl<L: TableLevel> PageTable<L> {
+    /// Create a new [`PageTable`] with every entry unused.
+    pub const fn const_default() -> Self {
+        Self {
+            entries: [PageTableEntry::const_default(); ENTRY_COUNT],
+            level: PhantomData,
+        }
+    }
+
+    /// Get the index of the entry translating a virtual address in a table of
+    /// this level.
+    pub fn index_of(address: VirtualAddress) -> usize {
+        u64::from(address).get_bits(L::INDEX_SHIFT..L::INDEX_SHIFT + 9) as usize
+    }
+
+    /// Mark every entry of the table as unused.
+    pub fn clear(&mut self) {
+        self.entries.fill(PageTableEntry::const_default());
+    }
+
+    /// Whether every entry of the table is unused.
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(PageTableEntry::is_unused)
+    }
+}
+
+impl<L: HierarchicalLevel> PageTable<L> {
+    /// Get the table referenced by an entry.
+    ///
+    /// # Safety
+    ///
+    /// The entry must reference a valid table of the next level, and the
+    /// whole physical memory must be mapped at [`PHYSICAL_MEMORY_OFFSET`].
+    unsafe fn next_table(&mut self, index: usize) -> Option<&mut PageTable<L::NextLevel>> {
+        let entry = self.entries[index];
+        if !entry.is_present() || entry.is_huge() {
+            return None;
+        }
+        Some(&mut *physical_to_virtual(entry.get_address()))
+    }
+
+    /// Get the table referenced by an entry, allocating a new one if the entry
+    /// is unused.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`PageTable::next_table`].
+    unsafe fn next_table_create(
+        &mut self,
+        index: usize,
+        user: bool,
+        allocator: &mut impl FrameAllocator,
+    ) -> Result<&mut PageTable<L::NextLevel>, PagingError> {
+        let entry = self.entries[index];
+        if entry.is_present() && entry.is_huge() {
+            return Err(PagingError::HugePage);
+        }
+        if !entry.is_present() {
+            let frame = allocator
+                .allocate()
+                .ok_or(PagingError::FrameAllocationFailed)?;
+            let table: &mut PageTable<L::NextLevel> = &mut *physical_to_virtual(frame);
+            table.clear();
+            self.entries[index] = PageTableEntry::new(frame, PageFlags(flags::PRESENT))
+                .writable(true)
+                .user(user);
+        } else if user {
+            self.entries[index] = entry.user(true);
+        }
+        Ok(self.next_table(index).unwrap())
+    }
+}
+
+impl<L: TableLevel> Index<usize> for PageTable<L> {
+    type Output = PageTableEntry;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.entries[index]
+    }
+}
+
+impl<L: TableLevel> IndexMut<usize> for PageTable<L> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.entries[index]
+    }
+}
+
+/// The size of a mapped page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    /// 4 KiB page mapped by a PT entry.
+    Size4KiB,
+    /// 2 MiB page mapped by a PD entry.
+    Size2MiB,
+    /// 1 GiB page mapped by a PDPT entry.
+    Size1GiB,
+}
+
+impl PageSize {
+    /// Get the page size in bytes.
+    pub fn bytes(&self) -> u64 {
+        match self {
+            PageSize::Size4KiB => FRAME_SIZE,
+            PageSize::Size2MiB => 512 * FRAME_SIZE,
+            PageSize::Size1GiB => 512 * 512 * FRAME_SIZE,
+        }
+    }
+}
+
+/// Set of errors that may occur while modifying an [`AddressSpace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagingError {
+    /// The page or the frame is not aligned on the page size.
+    Unaligned,
+    /// The page is already mapped.
+    AlreadyMapped,
+    /// The page is not mapped.
+    NotMapped,
+    /// A huge page covers the requested page.
+    HugePage,
+    /// No frame was available for a new paging structure.
+    FrameAllocationFailed,
+}
+
+impl fmt::Display for PagingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                PagingError::Unaligned => "Address not aligned on the page size",
+                PagingError::AlreadyMapped => "Page already mapped",
+                PagingError::NotMapped => "Page not mapped",
+                PagingError::HugePage => "Page covered by a huge page",
+                PagingError::FrameAllocationFailed => "Cannot allocate a paging structure",
+            }
+        )
+    }
+}
+
+/// Get a pointer to a physical address through the physical memory mapping.
+fn physical_to_virtual<T>(address: PhysicalAddress) -> *mut T {
+    (u64::from(address) + physical_memory_offset()) as *mut T
+}
+
+/// Invalidate the TLB entry translating the given virtual address.
+pub fn invalidate(address: VirtualAddress) {
+    unsafe {
+        asm!("invlpg [{}]", in(reg) u64::from(address), options(nostack, preserves_flags));
+    }
+}
+
+/// Invalidate every non-global TLB entry by reloading CR3.
+pub fn invalidate_all() {
+    unsafe { registers::set_cr3(registers::cr3()) }
+}
+
+/// A virtual address space described by a PML4 table.
+pub struct AddressSpace {
+    /// Physical address of the PML4 table.
+    pml4: PhysicalAddress,
+}
+
+impl AddressSpace {
+    /// Get the currently active [`AddressSpace`] from CR3.
+    pub fn active() -> Self {
+        Self {
+            pml4: PhysicalAddress::new(registers::cr3().get_bits(12..52) << 12),
+        }
+    }
+
+    /// Create an [`AddressSpace`] from the physical address of a PML4 table.
+    ///
+    /// # Safety
+    ///
+    /// The address must reference a valid, 4 KiB aligned PML4 table that will
+    /// outlive the [`AddressSpace`].
+    pub unsafe fn from_pml4(pml4: PhysicalAddress) -> Self {
+        Self { pml4 }
+    }
+
+    /// Get the physical address of the PML4 table.
+    pub fn pml4_address(&self) -> PhysicalAddress {
+        self.pml4
+    }
+
+    fn pml4(&mut self) -> &mut Pml4 {
+        unsafe { &mut *physical_to_virtual(self.pml4) }
+    }
+
+    /// Find the entry mapping a page along with the mapped page's size.
+    fn leaf_entry(&mut self, page: VirtualAddress) -> Option<(&mut PageTableEntry, PageSize)> {
+        unsafe {
+            let pdpt = self.pml4().next_table(Pml4::index_of(page))?;
+            let index = Pdpt::index_of(page);
+            if pdpt[index].is_present() && pdpt[index].is_huge() {
+                return Some((&mut pdpt[index], PageSize::Size1GiB));
+            }
+            let pd = pdpt.next_table(index)?;
+            let index = PageDirectory::index_of(page);
+            if pd[index].is_present() && pd[index].is_huge() {
+                return Some((&mut pd[index], PageSize::Size2MiB));
+            }
+            let pt = pd.next_table(index)?;
+            let entry = &mut pt[Pt::index_of(page)];
+            entry.is_present().then_some((entry, PageSize::Size4KiB))
+        }
+    }
+
+    /// Translate a virtual address into the physical address it is mapped
+    /// to, `None` if the address is not mapped.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The virtual address to translate.
+    pub fn translate(&mut self, address: VirtualAddress) -> Option<PhysicalAddress> {
+        let (entry, size) = self.leaf_entry(address)?;
+        let offset = u64::from(address) % size.bytes();
+        let base = u64::from(entry.get_address()) & !(size.bytes() - 1);
+        Some(PhysicalAddress::new(base + offset))
+    }
+
+    /// Get the flags of the entry mapping a page, `None` if the page is not
+    /// mapped.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the page.
+    pub fn page_flags(&mut self, page: VirtualAddress) -> Option<PageFlags> {
+        self.leaf_entry(page).map(|(entry, _)| entry.get_flags())
+    }
+
+    /// Map a page to a physical frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the page, aligned on `size`.
+    /// * `frame` - The physical address of the frame, aligned on `size`.
+    /// * `size` - The size of the page, the [`flags::HUGE`] bit is handled
+    ///   according to it.
+    /// * `page_flags` - The page's flags, [`flags::PRESENT`] is always set.
+    /// * `allocator` - The allocator providing frames for the missing paging
+    ///   structures.
+    ///
+    /// # Safety
+    ///
+    /// When the address space is active, the new mapping must not alias memory
+    /// used elsewhere in an incompatible way, e.g. a frame owned by the frame
+    /// allocator, the paging structures themselves or device memory mapped
+    /// with another cache type.
+    pub unsafe fn map(
+        &mut self,
+        page: VirtualAddress,
+        frame: PhysicalAddress,
+        size: PageSize,
+        page_flags: PageFlags,
+        allocator: &mut impl FrameAllocator,
+    ) -> Result<(), PagingError> {
+        if u64::from(page) % size.bytes() != 0 || u64::from(frame) % size.bytes() != 0 {
+            return Err(PagingError::Unaligned);
+        }
+
+        let user = page_flags.contains(PageFlags(flags::USER));
+        let entry = unsafe {
+            let pdpt = self
+                .pml4()
+                .next_table_create(Pml4::index_of(page), user, allocator)?;
+            if size == PageSize::Size1GiB {
+                &mut pdpt[Pdpt::index_of(page)]
+            } else {
+                let pd = pdpt.next_table_create(Pdpt::index_of(page), user, allocator)?;
+                if size == PageSize::Size2MiB {
+                    &mut pd[PageDirectory::index_of(page)]
+                } else {
+                    let pt =
+                        pd.next_table_create(PageDirectory::index_of(page), user, allocator)?;
+                    &mut pt[Pt::index_of(page)]
+                }
+            }
+        };
+
+        if entry.is_present() {
+            return Err(PagingError::AlreadyMapped);
+        }
+        *entry = PageTableEntry::new(frame, page_flags)
+            .present(true)
+            .huge(size != PageSize::Size4KiB);
+        Ok(())
+    }
+
+    /// Map a contiguous range of pages to a contiguous range of frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the first page, aligned on `size`.
+    /// * `frame` - The physical address of the first frame, aligned on `size`.
+    /// * `count` - The number of pages to map.
+    /// * `size` - The size of every page.
+    /// * `page_flags` - The flags of every page.
+    /// * `allocator` - The allocator providing frames for the missing paging
+    ///   structures.
+    ///
+    /// # Note
+    ///
+    /// Pages mapped before an error occurs are left mapped.
+    ///
+    /// # Safety
+    ///
+    /// See [`AddressSpace::map`].
+    pub unsafe fn map_range(
+        &mut self,
+        page: VirtualAddress,
+        frame: PhysicalAddress,
+        count: usize,
+        size: PageSize,
+        page_flags: PageFlags,
+        allocator: &mut impl FrameAllocator,
+    ) -> Result<(), PagingError> {
+        for i in 0..count as u64 {
+            let offset = i * size.bytes();
+            self.map(
+                VirtualAddress::new(u64::from(page) + offset),
+                PhysicalAddress::new(u64::from(frame) + offset),
+                size,
+                page_flags,
+                allocator,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Unmap a page and return the frame it was mapped to, the paging
+    /// structures themselves are never released.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the page, aligned on its size.
+    ///
+    /// # Safety
+    ///
+    /// When the address space is active, the page must no longer be used: it
+    /// must not hold the running code, a stack or data still referenced.
+    pub unsafe fn unmap(&mut self, page: VirtualAddress) -> Result<PhysicalAddress, PagingError> {
+        let (entry, size) = self.leaf_entry(page).ok_or(PagingError::NotMapped)?;
+        if u64::from(page) % size.bytes() != 0 {
+            return Err(PagingError::HugePage);
+        }
+        let frame = entry.get_address();
+        *entry = PageTableEntry::const_default();
+        invalidate(page);
+        Ok(frame)
+    }
+
+    /// Unmap a contiguous range of pages of the same size.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the first page.
+    /// * `count` - The number of pages to unmap.
+    /// * `size` - The size of every page.
+    ///
+    /// # Safety
+    ///
+    /// See [`AddressSpace::unmap`].
+    pub unsafe fn unmap_range(
+        &mut self,
+        page: VirtualAddress,
+        count: usize,
+        size: PageSize,
+    ) -> Result<(), PagingError> {
+        for i in 0..count as u64 {
+            self.unmap(VirtualAddress::new(u64::from(page) + i * size.bytes()))?;
+        }
+        Ok(())
+    }
+
+    /// Replace the flags of a mapped page, the [`flags::PRESENT`] and
+    /// [`flags::HUGE`] bits are kept as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the page.
+    /// * `page_flags` - The new flags of the page.
+    ///
+    /// # Safety
+    ///
+    /// When the address space is active, the new flags must not revoke an
+    /// access still in use, e.g. execution of the running code or writes to
+    /// a stack.
+    pub unsafe fn update_flags(
+        &mut self,
+        page: VirtualAddress,
+        page_flags: PageFlags,
+    ) -> Result<(), PagingError> {
+        let (entry, size) = self.leaf_entry(page).ok_or(PagingError::NotMapped)?;
+        *entry = entry
+            .flags(page_flags)
+            .present(true)
+            .huge(size != PageSize::Size4KiB);
+        invalidate(page);
+        Ok(())
+    }
+
+    /// Replace the flags of a contiguous range of pages of the same size.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The virtual address of the first page.
+    /// * `count` - The number of pages to update.
+    /// * `size` - The size of every page.
+    /// * `page_flags` - The new flags of every page.
+    ///
+    /// # Safety
+    ///
+    /// See [`AddressSpace::update_flags`].
+    pub unsafe fn update_flags_range(
+        &mut self,
+        page: VirtualAddress,
+        count: usize,
+        size: PageSize,
+        page_flags: PageFlags,
+    ) -> Result<(), PagingError> {
+        for i in 0..count as u64 {
+            self.update_flags(
+                VirtualAddress::new(u64::from(page) + i * size.bytes()),
+                page_flags,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the virtual address of the physical memory mapping.
+pub fn physical_memory_offset() -> u64 {
+    PHYSICAL_MEMORY_OFFSET.load(Ordering::Relaxed)
+}
+
+/// Record where the bootloader mapped the whole physical memory so the paging
+/// structures can be accessed.
+///
+/// # Arguments
+///
+/// * `physical_memory_offset` - The virtual address of the physical memory
+///   mapping.
+pub fn setup(physical_memory_offset: u64) {
+    trace!("Setting up paging...");
+    PHYSICAL_MEMORY_OFFSET.store(physical_memory_offset, Ordering::Relaxed);
+    trace!("Active PML4 at {}", AddressSpace::active().pml4_address());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn table_size() {
+        use core::mem::{align_of, size_of};
+        assert_eq!(size_of::<Pml4>(), 4096);
+        assert_eq!(align_of::<Pt>(), 4096);
+    }
+
+    #[test_case]
+    fn indexes() {
+        let address = VirtualAddress::new(0xFFFF_8080_C060_3000);
+        assert_eq!(Pml4::index_of(address), 257);
+        assert_eq!(Pdpt::index_of(address), 3);
+        assert_eq!(PageDirectory::index_of(address), 3);
+        assert_eq!(Pt::index_of(address), 3);
+    }
+
+    #[test_case]
+    fn page_sizes() {
+        assert_eq!(PageSize::Size4KiB.bytes(), 0x1000);
+        assert_eq!(PageSize::Size2MiB.bytes(), 0x20_0000);
+        assert_eq!(PageSize::Size1GiB.bytes(), 0x4000_0000);
+    }
+}
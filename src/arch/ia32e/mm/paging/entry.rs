@@ -0,0 +1,300 @@
+//! A module containing the implementation and tests for the
+//! [`PageTableEntry`] structure shared by every paging structure level.
+use crate::arch::ia32::address::PhysicalAddress;
+use crate::utils::bitfield::*;
+use core::fmt;
+
+/// The set of all field offsets for the [`PageTableEntry`] structure.
+mod offset {
+    /// Offset of the present bit (P).
+    pub const PRESENT: usize = 0;
+    /// Offset of the read/write bit (R/W).
+    pub const WRITABLE: usize = 1;
+    /// Offset of the user/supervisor bit (U/S).
+    pub const USER: usize = 2;
+    /// Offset of the page-level write-through bit (PWT).
+    pub const WRITE_THROUGH: usize = 3;
+    /// Offset of the page-level cache disable bit (PCD).
+    pub const CACHE_DISABLE: usize = 4;
+    /// Offset of the accessed bit (A).
+    pub const ACCESSED: usize = 5;
+    /// Offset of the dirty bit (D).
+    pub const DIRTY: usize = 6;
+    /// Offset of the page size bit (PS).
+    pub const HUGE: usize = 7;
+    /// Offset of the global bit (G).
+    pub const GLOBAL: usize = 8;
+    /// Offset of the execute-disable bit (XD).
+    pub const NO_EXECUTE: usize = 63;
+
+    /// Bounds of the physical address bits.
+    pub mod address {
+        /// Lower bit offset.
+        pub const LOWER: usize = 12;
+        /// Upper bit offset.
+        pub const UPPER: usize = 51;
+    }
+}
+
+/// Flags values and bitmasks for a [`PageTableEntry`].
+pub mod flags {
+    use super::offset;
+
+    /// The entry references a page or a paging structure.
+    pub const PRESENT: u64 = 1 << offset::PRESENT;
+    /// Writes are allowed to the referenced memory.
+    pub const WRITABLE: u64 = 1 << offset::WRITABLE;
+    /// User mode accesses are allowed to the referenced memory.
+    pub const USER: u64 = 1 << offset::USER;
+    /// Write-through caching policy.
+    pub const WRITE_THROUGH: u64 = 1 << offset::WRITE_THROUGH;
+    /// The referenced memory is not cached.
+    pub const CACHE_DISABLE: u64 = 1 << offset::CACHE_DISABLE;
+    /// Set by the processor when the entry is used for translation.
+    pub const ACCESSED: u64 = 1 << offset::ACCESSED;
+    /// Set by the processor when the referenced page is written to.
+    pub const DIRTY: u64 = 1 << offset::DIRTY;
+    /// The entry maps a 2 MiB or 1 GiB page instead of a paging structure.
+    pub const HUGE: u64 = 1 << offset::HUGE;
+    /// The translation is kept in the TLB across CR3 reloads.
+    pub const GLOBAL: u64 = 1 << offset::GLOBAL;
+    /// Instruction fetches are not allowed from the referenced memory.
+    pub const NO_EXECUTE: u64 = 1 << offset::NO_EXECUTE;
+
+    /// Every flag bit of an entry.
+    pub const ALL: u64 = PRESENT
+        | WRITABLE
+        | USER
+        | WRITE_THROUGH
+        | CACHE_DISABLE
+        | ACCESSED
+        | DIRTY
+        | HUGE
+        | GLOBAL
+        | NO_EXECUTE;
+}
+
+/// A set of [`flags`] applied to a [`PageTableEntry`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PageFlags(pub u64);
+
+impl From<u64> for PageFlags {
+    fn from(value: u64) -> Self {
+        PageFlags(value & flags::ALL)
+    }
+}
+
+impl PageFlags {
+    /// Whether every flag of `other` is also set in `self`.
+    pub fn contains(&self, other: PageFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// A paging structure entry, the same layout is used by the PML4, PDPT, PD
+/// and PT tables (cf. Intel volume III, 4.5).
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct PageTableEntry(u64);
+
+impl PageTableEntry {
+    /// Create a new unused [`PageTableEntry`].
+    pub const fn const_default() -> Self {
+        PageTableEntry(0)
+    }
+
+    /// Create a new [`PageTableEntry`] referencing a physical address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The 4 KiB aligned physical address to reference.
+    /// * `flags` - The entry's flags.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the address is not 4 KiB aligned.
+    pub fn new(address: PhysicalAddress, flags: PageFlags) -> Self {
+        Self::const_default().address(address).flags(flags)
+    }
+
+    /// Whether the entry is not used at all.
+    pub fn is_unused(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Change a [`PageTableEntry`]'s referenced physical address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The 4 KiB aligned physical address to reference.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the address is not 4 KiB aligned.
+    pub fn address(self, address: PhysicalAddress) -> Self {
+        use offset::address::{LOWER, UPPER};
+        let address = u64::from(address);
+        assert!(
+            address.get_bits(..LOWER) == 0,
+            "Page table entry address must be 4 KiB aligned"
+        );
+        Self(self.0.set_bits(LOWER..=UPPER, address >> LOWER))
+    }
+
+    /// Replace every flag of a [`PageTableEntry`].
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - The desired flags.
+    pub fn flags(self, flags: PageFlags) -> Self {
+        Self((self.0 & !flags::ALL) | (flags.0 & flags::ALL))
+    }
+
+    /// Change a [`PageTableEntry`]'s present bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `present` - The desired bit value, `true` for bit value 1 and `false`
+    ///   for bit value 0.
+    pub fn present(self, present: bool) -> Self {
+        Self(self.0.set_bit(offset::PRESENT, present))
+    }
+
+    /// Change a [`PageTableEntry`]'s read/write bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `writable` - The desired bit value, `true` for bit value 1 and
+    ///   `false` for bit value 0.
+    pub fn writable(self, writable: bool) -> Self {
+        Self(self.0.set_bit(offset::WRITABLE, writable))
+    }
+
+    /// Change a [`PageTableEntry`]'s user/supervisor bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - The desired bit value, `true` for bit value 1 and `false`
+    ///   for bit value 0.
+    pub fn user(self, user: bool) -> Self {
+        Self(self.0.set_bit(offset::USER, user))
+    }
+
+    /// Change a [`PageTableEntry`]'s page size bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `huge` - The desired bit value, `true` for bit value 1 and `false`
+    ///   for bit value 0.
+    pub fn huge(self, huge: bool) -> Self {
+        Self(self.0.set_bit(offset::HUGE, huge))
+    }
+
+    /// Change a [`PageTableEntry`]'s global bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `global` - The desired bit value, `true` for bit value 1 and `false`
+    ///   for bit value 0.
+    pub fn global(self, global: bool) -> Self {
+        Self(self.0.set_bit(offset::GLOBAL, global))
+    }
+
+    /// Change a [`PageTableEntry`]'s execute-disable bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `no_execute` - The desired bit value, `true` for bit value 1 and
+    ///   `false` for bit value 0.
+    pub fn no_execute(self, no_execute: bool) -> Self {
+        Self(self.0.set_bit(offset::NO_EXECUTE, no_execute))
+    }
+
+    /// Get the physical address referenced by the entry.
+    pub fn get_address(&self) -> PhysicalAddress {
+        use offset::address::{LOWER, UPPER};
+        PhysicalAddress::new(self.0.get_bits(LOWER..=UPPER) << LOWER)
+    }
+
+    /// Get the flags of the entry.
+    pub fn get_flags(&self) -> PageFlags {
+        self.0.into()
+    }
+
+    /// Whether the present bit is set.
+    pub fn is_present(&self) -> bool {
+        self.0.get_bit(offset::PRESENT)
+    }
+
+    /// Whether the page size bit is set.
+    pub fn is_huge(&self) -> bool {
+        self.0.get_bit(offset::HUGE)
+    }
+}
+
+impl fmt::Debug for PageTableEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for PageTableEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Address: {}\nPresent: {}\nWritable: {}\nUser: {}\nHuge: {}\nGlobal: {}\nNo execute: {}",
+            self.get_address(),
+            self.is_present(),
+            self.0.get_bit(offset::WRITABLE),
+            self.0.get_bit(offset::USER),
+            self.is_huge(),
+            self.0.get_bit(offset::GLOBAL),
+            self.0.get_bit(offset::NO_EXECUTE)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn structure_size() {
+        use core::mem::size_of;
+        assert_eq!(size_of::<PageTableEntry>(), 8);
+    }
+
+    #[test_case]
+    fn default_is_unused() {
+        assert!(PageTableEntry::default().is_unused());
+    }
+
+    #[test_case]
+    fn address_round_trip() {
+        let address = PhysicalAddress::new(0xF_FFFF_FFFF_F000);
+        let entry = PageTableEntry::new(address, PageFlags(flags::PRESENT));
+        assert_eq!(entry.get_address(), address);
+        assert_eq!(entry.get_flags(), PageFlags(flags::PRESENT));
+    }
+
+    #[test_case]
+    fn flags_keep_address() {
+        let address = PhysicalAddress::new(0x1000);
+        let entry = PageTableEntry::new(address, PageFlags(flags::PRESENT))
+            .flags(PageFlags(flags::WRITABLE | flags::NO_EXECUTE));
+        assert_eq!(entry.get_address(), address);
+        assert_eq!(entry.0, 0x8000_0000_0000_1002);
+    }
+
+    #[test_case]
+    fn builder_bits() {
+        let entry = PageTableEntry::default()
+            .present(true)
+            .writable(true)
+            .user(true)
+            .huge(true)
+            .global(true)
+            .no_execute(true);
+        assert_eq!(entry.0, 0x8000_0000_0000_0187);
+    }
+}
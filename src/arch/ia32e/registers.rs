@@ -0,0 +1,116 @@
+//! A module giving access to the processor's control and model specific
+//! registers.
+use core::arch::asm;
+use core::arch::x86_64::__cpuid_count;
+pub use core::arch::x86_64::CpuidResult;
+
+/// Offset of the write protect bit in CR0, when set supervisor writes to
+/// read-only pages fault.
+pub const CR0_WRITE_PROTECT: usize = 16;
+
+/// Read the CR0 register, holding the processor's operating mode flags.
+pub fn cr0() -> u64 {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, cr0", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    value
+}
+
+/// Write the CR0 register.
+///
+/// # Safety
+///
+/// Changing the operating mode flags, such as disabling paging or write
+/// protection, may break memory safety.
+pub unsafe fn set_cr0(value: u64) {
+    asm!("mov cr0, {}", in(reg) value, options(nostack, preserves_flags));
+}
+
+/// Read the CR3 register, holding the physical address of the active PML4
+/// table along with the PCD and PWT flags.
+pub fn cr3() -> u64 {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, cr3", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    value
+}
+
+/// Write the CR3 register, switching the active address space.
+///
+/// # Safety
+///
+/// The given value must point to a valid PML4 table mapping the currently
+/// executed code, otherwise the processor will fault on the next fetch.
+pub unsafe fn set_cr3(value: u64) {
+    asm!("mov cr3, {}", in(reg) value, options(nostack, preserves_flags));
+}
+
+/// Read the CR2 register, holding the linear address that caused the last
+/// page fault.
+pub fn cr2() -> u64 {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, cr2", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    value
+}
+
+/// Read the CR4 register, holding the processor's architectural extension
+/// flags.
+pub fn cr4() -> u64 {
+    let value: u64;
+    unsafe {
+        asm!("mov {}, cr4", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    value
+}
+
+/// Model specific register holding the local APIC base address and its
+/// global enable flag.
+pub const IA32_APIC_BASE: u32 = 0x1B;
+/// Model specific register enabling IA-32e mode and the no-execute bit.
+pub const IA32_EFER: u32 = 0xC000_0080;
+
+/// Read a model specific register.
+///
+/// # Safety
+///
+/// Reading a register not supported by the processor raises a general
+/// protection fault.
+pub unsafe fn read_msr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags));
+    u64::from(high) << 32 | u64::from(low)
+}
+
+/// Write a model specific register.
+///
+/// # Safety
+///
+/// Writing a register not supported by the processor raises a general
+/// protection fault, and some registers change the processor's behavior.
+pub unsafe fn write_msr(msr: u32, value: u64) {
+    asm!(
+        "wrmsr",
+        in("ecx") msr,
+        in("eax") value as u32,
+        in("edx") (value >> 32) as u32,
+        options(nostack, preserves_flags)
+    );
+}
+
+/// Execute the CPUID instruction for a leaf and sub-leaf.
+///
+/// # Arguments
+///
+/// * `leaf` - The information leaf, loaded in EAX.
+/// * `sub_leaf` - The sub-leaf, loaded in ECX.
+pub fn cpuid(leaf: u32, sub_leaf: u32) -> CpuidResult {
+    // CPUID is available on every IA-32e capable processor.
+    #[allow(unused_unsafe)]
+    unsafe {
+        __cpuid_count(leaf, sub_leaf)
+    }
+}
@@ -0,0 +1,216 @@
+//! A module containing the implementation and tests for the 64 bits
+//! [`TaskStateSegment`] structure (cf. Intel volume III, 8.7) along with the
+//! kernel's dedicated interrupt stacks.
+use crate::arch::ia32e::selector::SegmentSelector;
+use crate::sync::Once;
+use core::arch::asm;
+use core::mem::size_of;
+use core::ptr::addr_of;
+use log::trace;
+
+/// Number of entries of the interrupt stack table.
+pub const IST_LEN: usize = 7;
+
+/// Size in bytes of every dedicated interrupt stack.
+pub const INTERRUPT_STACK_SIZE: usize = 4096 * 5;
+
+/// Interrupt stack table index used by the double fault handler.
+pub const DOUBLE_FAULT_IST_INDEX: u8 = 1;
+/// Interrupt stack table index used by the non maskable interrupt handler.
+pub const NMI_IST_INDEX: u8 = 2;
+/// Interrupt stack table index used by the machine check handler.
+pub const MACHINE_CHECK_IST_INDEX: u8 = 3;
+
+/// A 64 bits task state segment, in IA-32e mode it does not hold any task
+/// context anymore but only the stack pointers used on privilege level
+/// changes and by the interrupt stack table mechanism.
+#[derive(Copy, Clone)]
+#[repr(C, packed)]
+pub struct TaskStateSegment {
+    reserved_1: u32,
+    /// Stack pointers loaded on a privilege level change to ring 0 to 2.
+    privilege_stack_table: [u64; 3],
+    reserved_2: u64,
+    /// Stack pointers loaded when a gate references an IST entry, the first
+    /// entry of the array is IST1.
+    interrupt_stack_table: [u64; IST_LEN],
+    reserved_3: u64,
+    reserved_4: u16,
+    /// Offset of the I/O permission bit map from the segment's base address.
+    io_map_base_address: u16,
+}
+
+impl TaskStateSegment {
+    /// Create a new [`TaskStateSegment`] with no stack and no I/O permission
+    /// bit map.
+    pub const fn const_default() -> Self {
+        TaskStateSegment {
+            reserved_1: 0,
+            privilege_stack_table: [0; 3],
+            reserved_2: 0,
+            interrupt_stack_table: [0; IST_LEN],
+            reserved_3: 0,
+            reserved_4: 0,
+            // A base beyond the segment limit means there is no bit map.
+            io_map_base_address: size_of::<TaskStateSegment>() as u16,
+        }
+    }
+
+    /// Change the stack pointer loaded on a privilege level change.
+    ///
+    /// # Arguments
+    ///
+    /// * `ring` - The target privilege level, between 0 and 2.
+    /// * `stack_top` - The address of the top of the stack.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the ring is greater than 2.
+    pub fn privilege_stack(self, ring: usize, stack_top: u64) -> Self {
+        assert!(ring < 3, "Privilege stack table only covers rings 0 to 2");
+        let mut table = self.privilege_stack_table;
+        table[ring] = stack_top;
+        Self {
+            privilege_stack_table: table,
+            ..self
+        }
+    }
+
+    /// Change an interrupt stack table entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The IST index as used by a gate, between 1 and 7.
+    /// * `stack_top` - The address of the top of the stack.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the index does not reference an IST entry.
+    pub fn interrupt_stack(self, index: u8, stack_top: u64) -> Self {
+        let mut table = self.interrupt_stack_table;
+        table[Self::ist_slot(index)] = stack_top;
+        Self {
+            interrupt_stack_table: table,
+            ..self
+        }
+    }
+
+    /// Get the stack pointer loaded on a privilege level change to `ring`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the ring is greater than 2.
+    pub fn get_privilege_stack(&self, ring: usize) -> u64 {
+        let table = self.privilege_stack_table;
+        table[ring]
+    }
+
+    /// Get the stack pointer of an interrupt stack table entry.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the index does not reference an IST entry.
+    pub fn get_interrupt_stack(&self, index: u8) -> u64 {
+        let table = self.interrupt_stack_table;
+        table[Self::ist_slot(index)]
+    }
+
+    fn ist_slot(index: u8) -> usize {
+        assert!(
+            (1..=IST_LEN as u8).contains(&index),
+            "Interrupt stack table index must be between 1 and 7"
+        );
+        usize::from(index - 1)
+    }
+}
+
+impl Default for TaskStateSegment {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+#[repr(C, align(16))]
+struct InterruptStack([u8; INTERRUPT_STACK_SIZE]);
+
+/// Stacks used by the IST entries, in IST index order.
+static mut INTERRUPT_STACKS: [InterruptStack; 3] = [
+    InterruptStack([0; INTERRUPT_STACK_SIZE]),
+    InterruptStack([0; INTERRUPT_STACK_SIZE]),
+    InterruptStack([0; INTERRUPT_STACK_SIZE]),
+];
+
+static TSS: Once<TaskStateSegment> = Once::new();
+
+/// Get the address of the top of one of the kernel interrupt stacks.
+fn interrupt_stack_top(index: u8) -> u64 {
+    let stack = unsafe { addr_of!(INTERRUPT_STACKS[usize::from(index - 1)]) };
+    // Stacks grow downward.
+    stack as u64 + INTERRUPT_STACK_SIZE as u64
+}
+
+/// Fill the kernel task state segment and return it so a descriptor can be
+/// built and the segment loaded.
+pub fn setup_tss() -> &'static TaskStateSegment {
+    trace!("Setting up 64bits tss...");
+    TSS.call_once(|| {
+        [
+            DOUBLE_FAULT_IST_INDEX,
+            NMI_IST_INDEX,
+            MACHINE_CHECK_IST_INDEX,
+        ]
+        .into_iter()
+        .fold(TaskStateSegment::default(), |tss, index| {
+            tss.interrupt_stack(index, interrupt_stack_top(index))
+        })
+    })
+}
+
+/// Load a task state segment selector in the task register.
+///
+/// # Arguments
+///
+/// * `selector` - The selector of a TSS descriptor from the loaded GDT.
+///
+/// # Safety
+///
+/// The selector must reference a valid and available TSS descriptor,
+/// otherwise a general protection fault will be raised.
+pub unsafe fn load(selector: SegmentSelector) {
+    trace!("Loading task register...");
+    asm!("ltr {:x}", in(reg) u16::from(selector), options(nostack, preserves_flags));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn structure_size() {
+        assert_eq!(size_of::<TaskStateSegment>(), 104);
+    }
+
+    #[test_case]
+    fn no_io_map() {
+        let tss = TaskStateSegment::default();
+        let base = tss.io_map_base_address;
+        assert_eq!(base, 104);
+    }
+
+    #[test_case]
+    fn interrupt_stack_entries() {
+        let tss = TaskStateSegment::default()
+            .interrupt_stack(1, 0x1000)
+            .interrupt_stack(7, 0x7000);
+        assert_eq!(tss.get_interrupt_stack(1), 0x1000);
+        assert_eq!(tss.get_interrupt_stack(7), 0x7000);
+        assert_eq!(tss.get_interrupt_stack(2), 0);
+    }
+
+    #[test_case]
+    fn privilege_stack_entries() {
+        let tss = TaskStateSegment::default().privilege_stack(0, 0xCAFE0);
+        assert_eq!(tss.get_privilege_stack(0), 0xCAFE0);
+        assert_eq!(tss.get_privilege_stack(1), 0);
+    }
+}
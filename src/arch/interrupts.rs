@@ -3,6 +3,8 @@ use crate::arch::ia32::interrupts;
 #[cfg(target_arch = "x86_64")]
 use crate::arch::ia32e::interrupts;
 
+pub use interrupts::{are_enabled, disable, enable, without_interrupts};
+
 pub fn setup() {
     interrupts::setup();
 }
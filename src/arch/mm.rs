@@ -3,6 +3,6 @@ use crate::arch::ia32::mm;
 #[cfg(target_arch = "x86_64")]
 use crate::arch::ia32e::mm;
 
-pub fn setup() {
-    mm::setup();
+pub fn setup(physical_memory_offset: u64) {
+    mm::setup(physical_memory_offset);
 }
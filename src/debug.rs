@@ -0,0 +1,3 @@
+//! Kernel debugging facilities.
+pub mod backtrace;
+pub mod gdbstub;
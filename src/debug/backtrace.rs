@@ -0,0 +1,356 @@
+//! Stack unwinding through frame pointers, with return addresses resolved
+//! to function names through a symbol table embedded in the kernel image.
+//!
+//! The kernel is built with frame pointers, every frame starts with the
+//! caller's frame pointer followed by the return address:
+//!
+//! ```text
+//! [rbp + 8] return address
+//! [rbp]     caller's rbp
+//! ```
+//!
+//! The symbol table is reserved in the `.flint_symbols` section and filled
+//! after linking by `tools/embed_symbols.py`, which the runner calls before
+//! booting the kernel. Addresses are printed without names when the table
+//! is empty.
+//!
+//! Frame records are only read once checked against the page tables, before
+//! memory management is set up a backtrace holds at most the faulting
+//! instruction.
+use crate::arch::ia32::address::VirtualAddress;
+use crate::arch::ia32e::interrupts::trap::TrapFrame;
+use crate::arch::ia32e::mm::paging::{physical_memory_offset, AddressSpace};
+use crate::sync::SpinLock;
+use core::arch::asm;
+use core::fmt;
+
+/// Maximum number of frames walked.
+pub const MAX_FRAMES: usize = 64;
+/// Size of the space reserved for the symbol table.
+pub const SYMBOL_TABLE_SIZE: usize = 256 * 1024;
+
+/// Maximum distance between two consecutive frames, a larger one means the
+/// chain left the stack.
+const MAX_FRAME_SIZE: u64 = 1024 * 1024;
+/// Magic number starting a filled symbol table.
+const SYMBOL_TABLE_MAGIC: &[u8; 4] = b"FSYM";
+/// Size of the symbol table header: the magic number then the symbol count.
+const HEADER_SIZE: usize = 8;
+/// Size of a symbol entry: address (u64), size (u32), name offset (u32),
+/// name length (u32) and a reserved u32, all little endian.
+const ENTRY_SIZE: usize = 24;
+
+/// The symbol table, filled after linking.
+#[used]
+#[link_section = ".flint_symbols"]
+static SYMBOL_TABLE: [u8; SYMBOL_TABLE_SIZE] = [0; SYMBOL_TABLE_SIZE];
+
+/// Where the next panic backtrace starts, set when panicking on an exception.
+static PANIC_ORIGIN: SpinLock<Option<(u64, u64)>> = SpinLock::new(None);
+
+/// A function symbol of the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    /// The address of the function.
+    pub address: u64,
+    /// The size of the function in bytes.
+    pub size: u64,
+    /// The demangled name of the function.
+    pub name: &'a str,
+}
+
+/// A sorted table of function symbols.
+#[derive(Clone, Copy)]
+pub struct SymbolTable<'a> {
+    data: &'a [u8],
+    count: usize,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Parse a symbol table, returns `None` if it was not filled or is
+    /// truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The encoded table.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.get(..4)? != SYMBOL_TABLE_MAGIC {
+            return None;
+        }
+        let count = read_u32(data, 4)? as usize;
+        if data.len() < HEADER_SIZE + count * ENTRY_SIZE {
+            return None;
+        }
+        Some(SymbolTable { data, count })
+    }
+
+    /// Get the symbol table embedded in the kernel, if it was filled.
+    pub fn kernel() -> Option<Self> {
+        // The table is patched after compilation, it must not be assumed to
+        // be zeroed.
+        Self::parse(core::hint::black_box(&SYMBOL_TABLE))
+    }
+
+    /// Get the number of symbols.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Get a symbol by its index in the table.
+    pub fn get(&self, index: usize) -> Option<Symbol<'a>> {
+        if index >= self.count {
+            return None;
+        }
+        let entry = HEADER_SIZE + index * ENTRY_SIZE;
+        let address = read_u64(self.data, entry)?;
+        let size = read_u32(self.data, entry + 8)?.into();
+        let offset = read_u32(self.data, entry + 12)? as usize;
+        let length = read_u32(self.data, entry + 16)? as usize;
+        let name = self.data.get(offset..offset.checked_add(length)?)?;
+        Some(Symbol {
+            address,
+            size,
+            name: core::str::from_utf8(name).ok()?,
+        })
+    }
+
+    /// Find the function containing an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - An address within the kernel's code.
+    pub fn resolve(&self, address: u64) -> Option<Symbol<'a>> {
+        // Find the last symbol starting at or before the address.
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.get(middle)?.address <= address {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let symbol = self.get(low.checked_sub(1)?)?;
+        (address - symbol.address < symbol.size.max(1)).then_some(symbol)
+    }
+}
+
+/// A frame of a backtrace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// The instruction being executed in the frame, a return address for
+    /// every frame but the first one.
+    pub address: u64,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#018X}", self.address)?;
+        let symbol = SymbolTable::kernel().and_then(|table| table.resolve(self.address));
+        if let Some(symbol) = symbol {
+            write!(f, " {}+{:#X}", symbol.name, self.address - symbol.address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a frame record can be read.
+fn is_readable(frame_pointer: u64) -> bool {
+    if frame_pointer == 0 || (frame_pointer / 8) * 8 != frame_pointer {
+        return false;
+    }
+    // The page tables cannot be walked before memory management is set up,
+    // an unchecked frame pointer could fault while reporting a panic.
+    if physical_memory_offset() == 0 {
+        return false;
+    }
+    [frame_pointer, frame_pointer.wrapping_add(8)]
+        .into_iter()
+        .all(|address| {
+            VirtualAddress::try_new(address)
+                .ok()
+                .and_then(|address| AddressSpace::active().translate(address))
+                .is_some()
+        })
+}
+
+/// An iterator over the frames of a stack.
+pub struct Backtrace {
+    /// The first frame's address, reported before walking the chain.
+    first: Option<u64>,
+    frame_pointer: u64,
+    depth: usize,
+}
+
+impl Backtrace {
+    /// Walk the stack of the caller.
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let frame_pointer: u64;
+        unsafe {
+            asm!("mov {}, rbp", out(reg) frame_pointer, options(nomem, nostack, preserves_flags));
+        }
+        Self::from_frame_pointer(None, frame_pointer)
+    }
+
+    /// Walk the stack of the context interrupted by an exception, starting
+    /// with the faulting instruction.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The registers saved on the exception.
+    pub fn from_trap_frame(frame: &TrapFrame) -> Self {
+        Self::from_frame_pointer(Some(frame.rip), frame.rbp)
+    }
+
+    /// Walk a stack from a frame pointer.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - An address to report before the return addresses, such
+    ///   as an interrupted instruction.
+    /// * `frame_pointer` - The frame pointer (RBP) of the innermost frame.
+    pub fn from_frame_pointer(first: Option<u64>, frame_pointer: u64) -> Self {
+        Backtrace {
+            first,
+            frame_pointer,
+            depth: 0,
+        }
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if let Some(address) = self.first.take() {
+            return Some(Frame { address });
+        }
+        if self.depth >= MAX_FRAMES || !is_readable(self.frame_pointer) {
+            return None;
+        }
+        let (caller, return_address) = unsafe {
+            let record = self.frame_pointer as *const u64;
+            (record.read_volatile(), record.add(1).read_volatile())
+        };
+        if return_address == 0 {
+            return None;
+        }
+        // The stack grows down, callers' frames are above. Anything else is
+        // a corrupt chain, or its end.
+        self.frame_pointer =
+            if caller > self.frame_pointer && caller - self.frame_pointer <= MAX_FRAME_SIZE {
+                caller
+            } else {
+                0
+            };
+        self.depth += 1;
+        Some(Frame {
+            address: return_address,
+        })
+    }
+}
+
+/// Start the next panic backtrace from an exception's context rather than
+/// from the panic site, called before panicking on a fatal exception.
+///
+/// # Arguments
+///
+/// * `frame` - The registers saved on the exception.
+pub fn set_panic_origin(frame: &TrapFrame) {
+    *PANIC_ORIGIN.lock() = Some((frame.rip, frame.rbp));
+}
+
+/// Print a numbered backtrace.
+///
+/// # Arguments
+///
+/// * `backtrace` - The frames to print.
+pub fn print(backtrace: Backtrace) {
+    println!("Backtrace:");
+    for (index, frame) in backtrace.enumerate() {
+        println!("{:>3}: {}", index, frame);
+    }
+}
+
+/// Print the backtrace of a panic, from the exception which caused it if
+/// any, otherwise from the caller.
+#[inline(always)]
+pub fn print_panic_backtrace() {
+    // The lock may be held by the panicking code, the exception origin is
+    // then ignored.
+    let origin = PANIC_ORIGIN.try_lock().and_then(|mut origin| origin.take());
+    match origin {
+        Some((rip, rbp)) => print(Backtrace::from_frame_pointer(Some(rip), rbp)),
+        None => print(Backtrace::capture()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a symbol table as done by `tools/embed_symbols.py`.
+    fn encode(symbols: &[(u64, u32, &str)], data: &mut [u8]) {
+        data[..4].copy_from_slice(SYMBOL_TABLE_MAGIC);
+        data[4..8].copy_from_slice(&(symbols.len() as u32).to_le_bytes());
+        let mut name_offset = HEADER_SIZE + symbols.len() * ENTRY_SIZE;
+        for (index, (address, size, name)) in symbols.iter().enumerate() {
+            let entry = HEADER_SIZE + index * ENTRY_SIZE;
+            data[entry..entry + 8].copy_from_slice(&address.to_le_bytes());
+            data[entry + 8..entry + 12].copy_from_slice(&size.to_le_bytes());
+            data[entry + 12..entry + 16].copy_from_slice(&(name_offset as u32).to_le_bytes());
+            data[entry + 16..entry + 20].copy_from_slice(&(name.len() as u32).to_le_bytes());
+            data[name_offset..name_offset + name.len()].copy_from_slice(name.as_bytes());
+            name_offset += name.len();
+        }
+    }
+
+    #[test_case]
+    fn symbol_resolution() {
+        let mut data = [0; 256];
+        encode(
+            &[(0x1000, 0x20, "first"), (0x1040, 0x10, "second")],
+            &mut data,
+        );
+        let table = SymbolTable::parse(&data).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.resolve(0x1000).unwrap().name, "first");
+        assert_eq!(table.resolve(0x101F).unwrap().name, "first");
+        assert_eq!(table.resolve(0x1020), None);
+        assert_eq!(table.resolve(0x1045).unwrap().name, "second");
+        assert_eq!(table.resolve(0xFFF), None);
+    }
+
+    #[test_case]
+    fn empty_symbol_table() {
+        assert!(SymbolTable::parse(&[0; 16]).is_none());
+        let mut data = [0; 8];
+        encode(&[], &mut data);
+        assert!(SymbolTable::parse(&data).unwrap().is_empty());
+    }
+
+    #[test_case]
+    fn corrupt_frame_stops() {
+        let mut backtrace = Backtrace::from_frame_pointer(Some(0x1234), 0x1001);
+        assert_eq!(backtrace.next(), Some(Frame { address: 0x1234 }));
+        assert_eq!(backtrace.next(), None);
+    }
+}
@@ -0,0 +1,612 @@
+//! A GDB remote serial protocol stub running over a dedicated [`Serial`]
+//! port.
+//!
+//! Once [`init`] has been called, #BP and #DB exceptions stop the kernel and
+//! hand control over to GDB, until it resumes execution. Call [`breakpoint`]
+//! to wait for GDB to attach, then from GDB:
+//!
+//! ```text
+//! target remote localhost:1234
+//! ```
+//!
+//! with QEMU started with `-serial tcp::1234,server` on the stub's port, or
+//! `target remote /dev/pts/N` with a pty.
+//!
+//! # Note
+//!
+//! Memory management must be set up, memory accesses are checked against
+//! the active address space. GDB cannot interrupt a running kernel.
+//!
+//! Traps raised by the stub's own code while talking to GDB, such as a
+//! kernel hardware breakpoint on data it touches, cannot be reported: they
+//! are only passed to the kernel's hardware breakpoint handlers.
+use crate::arch::ia32::address::VirtualAddress;
+use crate::arch::ia32e::debug;
+use crate::arch::ia32e::interrupts::idt::vector;
+use crate::arch::ia32e::interrupts::trap::{self, TrapFrame};
+use crate::arch::ia32e::mm::paging::AddressSpace;
+use crate::arch::ia32e::registers::{self, CR0_WRITE_PROTECT};
+use crate::serial::{BaudRateError, Serial, SerialConfig};
+use crate::sync::IrqSafeSpinLock;
+use crate::utils::bitfield::*;
+use core::arch::asm;
+use log::info;
+
+/// Maximum size of a packet's data, advertised to GDB.
+pub const PACKET_SIZE: usize = 1024;
+/// Maximum number of software breakpoints.
+pub const MAX_BREAKPOINTS: usize = 32;
+
+/// The breakpoint instruction.
+const INT3: u8 = 0xCC;
+/// Offset of the trap flag in RFLAGS, enabling single stepping.
+const TRAP_FLAG: usize = 8;
+/// Stop reply reporting a SIGTRAP.
+const STOP_REPLY: &[u8] = b"S05";
+/// Stop reply reporting a SIGTRAP on a software breakpoint, with RIP already
+/// moved back to the breakpoint as advertised by `swbreak+`.
+const SWBREAK_STOP_REPLY: &[u8] = b"T05swbreak:;";
+/// Number of registers in GDB's amd64 general register set: 16 general
+/// purpose registers, RIP, EFLAGS and 6 segment selectors.
+const REGISTER_COUNT: usize = 24;
+
+/// A software breakpoint along with the byte it replaced.
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    address: u64,
+    original: u8,
+}
+
+/// The state of the stub.
+struct GdbStub {
+    /// The port GDB is attached to.
+    serial: Serial,
+    /// The inserted software breakpoints.
+    breakpoints: [Option<Breakpoint>; MAX_BREAKPOINTS],
+    /// Whether GDB is expecting a stop reply.
+    attached: bool,
+}
+
+static STUB: IrqSafeSpinLock<Option<GdbStub>> = IrqSafeSpinLock::new(None);
+
+/// Start the stub on a serial port, #BP and #DB exceptions are then reported
+/// to GDB.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the port GDB is attached to, the port
+///   should not be used for anything else.
+pub fn init(config: SerialConfig) -> Result<(), BaudRateError> {
+    let port = config.get_port();
+    *STUB.lock() = Some(GdbStub {
+        serial: config.build()?,
+        breakpoints: [None; MAX_BREAKPOINTS],
+        attached: false,
+    });
+    trap::register(vector::DEBUG.index(), Some(handle_trap));
+    trap::register(vector::BREAKPOINT.index(), Some(handle_trap));
+    info!("GDB stub listening on {:#X}", port);
+    Ok(())
+}
+
+/// Stop the kernel and wait for GDB.
+pub fn breakpoint() {
+    unsafe {
+        asm!("int3");
+    }
+}
+
+fn handle_trap(frame: &mut TrapFrame) {
+    // Hardware breakpoints set by the kernel keep going to their handlers.
+    if frame.vector == vector::DEBUG.index() as u64 {
+        let status = debug::status();
+        debug::clear_status();
+        let remaining = debug::handle_breakpoints(status, frame);
+        if remaining != status && !remaining.single_step() {
+            return;
+        }
+    }
+    // The lock is held for the whole session, a trap raised by the session
+    // code would otherwise deadlock.
+    let Some(mut stub) = STUB.try_lock() else {
+        return;
+    };
+    let stub = stub.as_mut().expect("GDB stub is not initialized");
+    // The processor reports the address following an int3, GDB expects the
+    // address of the breakpoint.
+    let software_breakpoint = frame.vector == vector::BREAKPOINT.index() as u64
+        && stub.find_breakpoint(frame.rip.wrapping_sub(1)).is_some();
+    if software_breakpoint {
+        frame.rip -= 1;
+    }
+    stub.session(frame, software_breakpoint);
+}
+
+/// Get the value of a hexadecimal digit.
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Get the lowercase hexadecimal digit of a nibble.
+fn hex_digit(nibble: u8) -> u8 {
+    b"0123456789abcdef"[usize::from(nibble & 0xF)]
+}
+
+/// Parse a big endian hexadecimal number.
+fn parse_hex(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    digits.iter().try_fold(0, |value, &digit| {
+        Some(value << 4 | u64::from(hex_value(digit)?))
+    })
+}
+
+/// Parse a little endian hexadecimal value of a register, as sent by GDB.
+fn parse_register(digits: &[u8]) -> Option<u64> {
+    if (digits.len() / 2) * 2 != digits.len() || digits.len() > 16 {
+        return None;
+    }
+    digits.chunks(2).rev().try_fold(0, |value, pair| {
+        Some(value << 8 | u64::from(hex_value(pair[0])? << 4 | hex_value(pair[1])?))
+    })
+}
+
+/// Split a packet's arguments on a separator.
+fn split(arguments: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let position = arguments.iter().position(|&byte| byte == separator)?;
+    Some((&arguments[..position], &arguments[position + 1..]))
+}
+
+/// A reply being built.
+struct Reply {
+    data: [u8; PACKET_SIZE],
+    length: usize,
+}
+
+impl Reply {
+    fn new() -> Self {
+        Reply {
+            data: [0; PACKET_SIZE],
+            length: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> &mut Self {
+        let length = bytes.len().min(PACKET_SIZE - self.length);
+        self.data[self.length..self.length + length].copy_from_slice(&bytes[..length]);
+        self.length += length;
+        self
+    }
+
+    fn push_hex_byte(&mut self, byte: u8) -> &mut Self {
+        self.push(&[hex_digit(byte >> 4), hex_digit(byte)])
+    }
+
+    /// Push a register value, little endian.
+    fn push_register(&mut self, value: u64, size: usize) -> &mut Self {
+        for byte in &value.to_le_bytes()[..size] {
+            self.push_hex_byte(*byte);
+        }
+        self
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.length]
+    }
+}
+
+/// Read a segment selector register which is not saved in the frame.
+fn segment_register(index: usize) -> u64 {
+    let value: u16;
+    unsafe {
+        match index {
+            20 => asm!("mov {:x}, ds", out(reg) value, options(nomem, nostack)),
+            21 => asm!("mov {:x}, es", out(reg) value, options(nomem, nostack)),
+            22 => asm!("mov {:x}, fs", out(reg) value, options(nomem, nostack)),
+            _ => asm!("mov {:x}, gs", out(reg) value, options(nomem, nostack)),
+        }
+    }
+    value.into()
+}
+
+/// Get a register in GDB's amd64 numbering along with its size in bytes.
+fn register(frame: &TrapFrame, index: usize) -> Option<(u64, usize)> {
+    let value = match index {
+        0 => frame.rax,
+        1 => frame.rbx,
+        2 => frame.rcx,
+        3 => frame.rdx,
+        4 => frame.rsi,
+        5 => frame.rdi,
+        6 => frame.rbp,
+        7 => frame.stack_pointer,
+        8 => frame.r8,
+        9 => frame.r9,
+        10 => frame.r10,
+        11 => frame.r11,
+        12 => frame.r12,
+        13 => frame.r13,
+        14 => frame.r14,
+        15 => frame.r15,
+        16 => frame.rip,
+        17 => return Some((frame.rflags, 4)),
+        18 => return Some((frame.code_segment, 4)),
+        19 => return Some((frame.stack_segment, 4)),
+        20..=23 => return Some((segment_register(index), 4)),
+        _ => return None,
+    };
+    Some((value, 8))
+}
+
+/// Set a register in GDB's amd64 numbering, segment selectors cannot be
+/// changed and are ignored.
+fn set_register(frame: &mut TrapFrame, index: usize, value: u64) -> bool {
+    let register = match index {
+        0 => &mut frame.rax,
+        1 => &mut frame.rbx,
+        2 => &mut frame.rcx,
+        3 => &mut frame.rdx,
+        4 => &mut frame.rsi,
+        5 => &mut frame.rdi,
+        6 => &mut frame.rbp,
+        7 => &mut frame.stack_pointer,
+        8 => &mut frame.r8,
+        9 => &mut frame.r9,
+        10 => &mut frame.r10,
+        11 => &mut frame.r11,
+        12 => &mut frame.r12,
+        13 => &mut frame.r13,
+        14 => &mut frame.r14,
+        15 => &mut frame.r15,
+        16 => &mut frame.rip,
+        17 => &mut frame.rflags,
+        18..=23 => return true,
+        _ => return false,
+    };
+    *register = value;
+    true
+}
+
+/// Whether a byte of memory is mapped in the active address space.
+fn is_mapped(address: u64) -> bool {
+    VirtualAddress::try_new(address)
+        .ok()
+        .and_then(|address| AddressSpace::active().translate(address))
+        .is_some()
+}
+
+/// Write a byte of memory, even on a read-only page.
+///
+/// # Safety
+///
+/// The address must be mapped and writing it must not break the kernel.
+unsafe fn write_byte(address: u64, value: u8) {
+    let cr0 = registers::cr0();
+    registers::set_cr0(cr0.set_bit(CR0_WRITE_PROTECT, false));
+    core::ptr::write_volatile(address as *mut u8, value);
+    registers::set_cr0(cr0);
+}
+
+impl GdbStub {
+    fn find_breakpoint(&self, address: u64) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| breakpoint.is_some_and(|b| b.address == address))
+    }
+
+    /// Insert a software breakpoint.
+    fn insert_breakpoint(&mut self, address: u64) -> bool {
+        if self.find_breakpoint(address).is_some() {
+            return true;
+        }
+        let Some(slot) = self.breakpoints.iter().position(Option::is_none) else {
+            return false;
+        };
+        if !is_mapped(address) {
+            return false;
+        }
+        unsafe {
+            let original = core::ptr::read_volatile(address as *const u8);
+            write_byte(address, INT3);
+            self.breakpoints[slot] = Some(Breakpoint { address, original });
+        }
+        true
+    }
+
+    /// Remove a software breakpoint.
+    fn remove_breakpoint(&mut self, address: u64) -> bool {
+        let Some(slot) = self.find_breakpoint(address) else {
+            return false;
+        };
+        if let Some(breakpoint) = self.breakpoints[slot].take() {
+            unsafe { write_byte(breakpoint.address, breakpoint.original) };
+        }
+        true
+    }
+
+    fn remove_all_breakpoints(&mut self) {
+        for slot in 0..MAX_BREAKPOINTS {
+            if let Some(breakpoint) = self.breakpoints[slot].take() {
+                unsafe { write_byte(breakpoint.address, breakpoint.original) };
+            }
+        }
+    }
+
+    /// Receive a packet's data, acknowledging it once its checksum matches.
+    fn receive_packet<'a>(&self, buffer: &'a mut [u8; PACKET_SIZE]) -> &'a [u8] {
+        loop {
+            // Acknowledgements and interrupts outside of packets are ignored.
+            while self.serial.read_byte() != b'$' {}
+            let mut length = 0;
+            let mut checksum: u8 = 0;
+            loop {
+                match self.serial.read_byte() {
+                    b'#' => break,
+                    byte => {
+                        if length < PACKET_SIZE {
+                            buffer[length] = byte;
+                            length += 1;
+                        }
+                        checksum = checksum.wrapping_add(byte);
+                    }
+                }
+            }
+            let high = hex_value(self.serial.read_byte());
+            let low = hex_value(self.serial.read_byte());
+            if high.zip(low).map(|(high, low)| high << 4 | low) == Some(checksum) {
+                self.serial.write_byte(b'+');
+                self.serial.flush();
+                return &buffer[..length];
+            }
+            self.serial.write_byte(b'-');
+            self.serial.flush();
+        }
+    }
+
+    /// Send a packet, until GDB acknowledges it.
+    fn send_packet(&self, data: &[u8]) {
+        loop {
+            let checksum = data.iter().fold(0_u8, |sum, &byte| sum.wrapping_add(byte));
+            self.serial.write_byte(b'$');
+            for &byte in data {
+                self.serial.write_byte(byte);
+            }
+            self.serial.write_byte(b'#');
+            self.serial.write_byte(hex_digit(checksum >> 4));
+            self.serial.write_byte(hex_digit(checksum));
+            self.serial.flush();
+            loop {
+                match self.serial.read_byte() {
+                    b'+' => return,
+                    b'-' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Talk to GDB until it resumes execution.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The registers of the stopped context.
+    /// * `software_breakpoint` - Whether the stop comes from an inserted
+    ///   breakpoint, RIP then points to it.
+    fn session(&mut self, frame: &mut TrapFrame, software_breakpoint: bool) {
+        let stop_reply = if software_breakpoint {
+            SWBREAK_STOP_REPLY
+        } else {
+            STOP_REPLY
+        };
+        if self.attached {
+            self.send_packet(stop_reply);
+        }
+        let mut buffer = [0; PACKET_SIZE];
+        loop {
+            let packet = self.receive_packet(&mut buffer);
+            let Some((&command, arguments)) = packet.split_first() else {
+                self.send_packet(b"");
+                continue;
+            };
+            let mut reply = Reply::new();
+            match command {
+                b'?' => {
+                    self.attached = true;
+                    reply.push(stop_reply);
+                }
+                b'g' => {
+                    for index in 0..REGISTER_COUNT {
+                        if let Some((value, size)) = register(frame, index) {
+                            reply.push_register(value, size);
+                        }
+                    }
+                }
+                b'G' => {
+                    let mut digits = arguments;
+                    for index in 0..REGISTER_COUNT {
+                        let Some((_, size)) = register(frame, index) else {
+                            continue;
+                        };
+                        if digits.len() < size * 2 {
+                            break;
+                        }
+                        if let Some(value) = parse_register(&digits[..size * 2]) {
+                            set_register(frame, index, value);
+                        }
+                        digits = &digits[size * 2..];
+                    }
+                    reply.push(b"OK");
+                }
+                b'p' => match parse_hex(arguments).and_then(|i| register(frame, i as usize)) {
+                    Some((value, size)) => {
+                        reply.push_register(value, size);
+                    }
+                    None => {
+                        reply.push(b"E00");
+                    }
+                },
+                b'P' => {
+                    let written =
+                        split(arguments, b'=').is_some_and(|(index, value)| {
+                            parse_hex(index).zip(parse_register(value)).is_some_and(
+                                |(index, value)| set_register(frame, index as usize, value),
+                            )
+                        });
+                    reply.push(if written { b"OK" } else { b"E00" });
+                }
+                b'm' => self.read_memory(arguments, &mut reply),
+                b'M' => self.write_memory(arguments, &mut reply),
+                b'c' | b's' => {
+                    if let Some(address) = parse_hex(arguments) {
+                        frame.rip = address;
+                    }
+                    frame.rflags = frame.rflags.set_bit(TRAP_FLAG, command == b's');
+                    self.attached = true;
+                    return;
+                }
+                b'Z' | b'z' => self.breakpoint_packet(command, arguments, &mut reply),
+                b'D' | b'k' => {
+                    self.remove_all_breakpoints();
+                    frame.rflags = frame.rflags.set_bit(TRAP_FLAG, false);
+                    self.attached = false;
+                    if command == b'D' {
+                        self.send_packet(b"OK");
+                    }
+                    return;
+                }
+                b'H' => {
+                    reply.push(b"OK");
+                }
+                b'q' if arguments.starts_with(b"Supported") => {
+                    reply.push(b"PacketSize=400;swbreak+");
+                }
+                b'q' if arguments.starts_with(b"Attached") => {
+                    reply.push(b"1");
+                }
+                // Unsupported packets get an empty reply.
+                _ => {}
+            }
+            self.send_packet(reply.as_bytes());
+        }
+    }
+
+    /// Handle `m addr,length`.
+    fn read_memory(&self, arguments: &[u8], reply: &mut Reply) {
+        let Some((address, length)) = split(arguments, b',')
+            .and_then(|(address, length)| parse_hex(address).zip(parse_hex(length)))
+        else {
+            reply.push(b"E01");
+            return;
+        };
+        let length = length.min(PACKET_SIZE as u64 / 2);
+        for offset in 0..length {
+            let address = address.wrapping_add(offset);
+            if !is_mapped(address) {
+                if offset == 0 {
+                    reply.push(b"E14");
+                }
+                return;
+            }
+            let value = match self.find_breakpoint(address) {
+                Some(slot) => self.breakpoints[slot].map_or(INT3, |b| b.original),
+                None => unsafe { core::ptr::read_volatile(address as *const u8) },
+            };
+            reply.push_hex_byte(value);
+        }
+    }
+
+    /// Handle `M addr,length:XX...`.
+    fn write_memory(&mut self, arguments: &[u8], reply: &mut Reply) {
+        let parsed = split(arguments, b':').and_then(|(header, data)| {
+            let (address, length) = split(header, b',')?;
+            Some((parse_hex(address)?, parse_hex(length)?, data))
+        });
+        let Some((address, length, data)) = parsed else {
+            reply.push(b"E01");
+            return;
+        };
+        if data.len() as u64 != length * 2 {
+            reply.push(b"E01");
+            return;
+        }
+        for (offset, pair) in data.chunks(2).enumerate() {
+            let address = address.wrapping_add(offset as u64);
+            let Some(value) = hex_value(pair[0]).zip(hex_value(pair[1])) else {
+                reply.push(b"E01");
+                return;
+            };
+            if !is_mapped(address) {
+                reply.push(b"E14");
+                return;
+            }
+            let value = value.0 << 4 | value.1;
+            // Keep inserted breakpoints, only their saved byte changes.
+            match self.find_breakpoint(address) {
+                Some(slot) => {
+                    if let Some(breakpoint) = self.breakpoints[slot].as_mut() {
+                        breakpoint.original = value;
+                    }
+                }
+                None => unsafe { write_byte(address, value) },
+            }
+        }
+        reply.push(b"OK");
+    }
+
+    /// Handle `Z0,addr,kind` and `z0,addr,kind`, only software breakpoints
+    /// are supported.
+    fn breakpoint_packet(&mut self, command: u8, arguments: &[u8], reply: &mut Reply) {
+        let Some((kind, rest)) = split(arguments, b',') else {
+            reply.push(b"E01");
+            return;
+        };
+        if kind != b"0" {
+            return;
+        }
+        let Some(address) = split(rest, b',').and_then(|(address, _)| parse_hex(address)) else {
+            reply.push(b"E01");
+            return;
+        };
+        let done = if command == b'Z' {
+            self.insert_breakpoint(address)
+        } else {
+            self.remove_breakpoint(address)
+        };
+        reply.push(if done { b"OK" } else { b"E0E" });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn hex_parsing() {
+        assert_eq!(parse_hex(b"ff10"), Some(0xFF10));
+        assert_eq!(parse_hex(b""), None);
+        assert_eq!(parse_hex(b"0x1"), None);
+        assert_eq!(parse_register(b"10ff0000"), Some(0xFF10));
+        assert_eq!(split(b"10,4", b','), Some((&b"10"[..], &b"4"[..])));
+    }
+
+    #[test_case]
+    fn register_encoding() {
+        let frame = TrapFrame {
+            rip: 0x1122,
+            rflags: 0x202,
+            ..TrapFrame::default()
+        };
+        let mut reply = Reply::new();
+        let (value, size) = register(&frame, 16).unwrap();
+        reply.push_register(value, size);
+        let (value, size) = register(&frame, 17).unwrap();
+        reply.push_register(value, size);
+        assert_eq!(reply.as_bytes(), b"221100000000000002020000");
+    }
+}
@@ -0,0 +1,2 @@
+//! Drivers of the devices found on a PC, outside of the processor.
+pub mod ps2;
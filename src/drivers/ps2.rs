@@ -0,0 +1,112 @@
+//! A module driving the 8042 PS/2 controller, along with the keyboard on its
+//! first port and the mouse on its second port.
+use log::{info, warn};
+
+pub use controller::{Configuration, Ps2Controller, Ps2Error, Ps2Port};
+
+pub mod controller;
+pub mod keyboard;
+pub mod keymap;
+pub mod mouse;
+
+/// The controller at its standard ports.
+pub static CONTROLLER: Ps2Controller = Ps2Controller::new();
+
+/// Initialize the controller and its devices, then enable their interrupts.
+///
+/// The keyboard decodes scancode set 1 when the controller translates
+/// scancodes, as set up by most firmwares, and scancode set 2 otherwise. A
+/// missing or failing mouse is only reported.
+pub fn setup() -> Result<(), Ps2Error> {
+    let controller = &CONTROLLER;
+    controller.disable_port(Ps2Port::First)?;
+    controller.disable_port(Ps2Port::Second)?;
+    controller.flush();
+
+    let config = controller
+        .configuration()?
+        .interrupt(Ps2Port::First, false)
+        .interrupt(Ps2Port::Second, false);
+    controller.set_configuration(config)?;
+    controller.self_test()?;
+    // The self test may reset the controller.
+    controller.set_configuration(config)?;
+
+    let mouse = controller.has_second_port()?
+        && controller
+            .test_port(Ps2Port::Second)
+            .map_err(|error| warn!("{}", error))
+            .is_ok();
+    controller.test_port(Ps2Port::First)?;
+
+    keyboard::set_scancode_set(if config.is_translating() {
+        keyboard::ScancodeSet::Set1
+    } else {
+        keyboard::ScancodeSet::Set2
+    });
+
+    // The keyboard stays disabled while the mouse answers, its bytes would
+    // be mixed with them.
+    let mut mouse_enabled = false;
+    if mouse {
+        controller.enable_port(Ps2Port::Second)?;
+        match mouse::setup(controller) {
+            Ok(kind) => {
+                info!("PS/2 {} detected", kind);
+                mouse_enabled = true;
+            }
+            Err(error) => {
+                warn!("PS/2 mouse: {}", error);
+                controller.disable_port(Ps2Port::Second)?;
+            }
+        }
+    }
+    controller.enable_port(Ps2Port::First)?;
+    controller.set_configuration(enabled_configuration(config, mouse_enabled))
+}
+
+/// Get the configuration enabling the interrupts of the working devices.
+///
+/// The configuration was read while both ports were disabled, their clocks
+/// are enabled again as writing it would otherwise disable the ports.
+///
+/// # Arguments
+///
+/// * `config` - The configuration read during the setup.
+/// * `mouse` - Whether the mouse is enabled.
+fn enabled_configuration(config: Configuration, mouse: bool) -> Configuration {
+    let config = config
+        .interrupt(Ps2Port::First, true)
+        .clock(Ps2Port::First, true);
+    if mouse {
+        config
+            .interrupt(Ps2Port::Second, true)
+            .clock(Ps2Port::Second, true)
+    } else {
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn enabled_configuration_keeps_keyboard_clock() {
+        // Both ports disabled, translation on.
+        let config = Configuration(0b0111_0100);
+        let enabled = enabled_configuration(config, false);
+        assert!(!enabled.is_clock_disabled(Ps2Port::First));
+        assert!(enabled.is_interrupt_enabled(Ps2Port::First));
+        assert!(!enabled.is_interrupt_enabled(Ps2Port::Second));
+    }
+
+    #[test_case]
+    fn enabled_configuration_keeps_both_clocks() {
+        let enabled = enabled_configuration(Configuration(0b0111_0100), true);
+        // Bits 4 and 5 disable the clocks.
+        assert_eq!(enabled.0 & 0b0011_0000, 0);
+        assert!(enabled.is_interrupt_enabled(Ps2Port::First));
+        assert!(enabled.is_interrupt_enabled(Ps2Port::Second));
+    }
+}
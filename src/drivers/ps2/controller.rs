@@ -0,0 +1,379 @@
+//! A module driving the Intel 8042 PS/2 controller (cf. the IBM PC AT
+//! technical reference).
+use crate::arch::ia32::pause;
+use crate::arch::io::port::Port;
+use crate::arch::io::register::{ReadRegister, WriteRegister};
+use crate::utils::bitfield::*;
+use core::fmt;
+
+/// Number of status polls before an operation times out.
+const TIMEOUT: usize = 100_000;
+/// Number of times a device command is sent again on a resend request.
+const RETRIES: usize = 3;
+
+/// Controller commands, written to the command register.
+mod command {
+    pub const READ_CONFIGURATION: u8 = 0x20;
+    pub const WRITE_CONFIGURATION: u8 = 0x60;
+    pub const DISABLE_SECOND_PORT: u8 = 0xA7;
+    pub const ENABLE_SECOND_PORT: u8 = 0xA8;
+    pub const TEST_SECOND_PORT: u8 = 0xA9;
+    pub const SELF_TEST: u8 = 0xAA;
+    pub const TEST_FIRST_PORT: u8 = 0xAB;
+    pub const DISABLE_FIRST_PORT: u8 = 0xAD;
+    pub const ENABLE_FIRST_PORT: u8 = 0xAE;
+    /// Send the next data byte to the second port's device.
+    pub const WRITE_SECOND_PORT: u8 = 0xD4;
+    /// Pulse the output line wired to the processor reset.
+    pub const PULSE_RESET: u8 = 0xFE;
+}
+
+/// Answer to a successful controller self test.
+const SELF_TEST_PASSED: u8 = 0x55;
+/// Answer to a successful port test.
+const PORT_TEST_PASSED: u8 = 0x00;
+/// Acknowledgement of a device command.
+pub const ACK: u8 = 0xFA;
+/// Request to resend the last device command.
+pub const RESEND: u8 = 0xFE;
+
+/// The set of all field offsets for the status register.
+mod status {
+    /// Offset of the output buffer full bit, set when a byte can be read.
+    pub const OUTPUT_FULL: usize = 0;
+    /// Offset of the input buffer full bit, set while a written byte has not
+    /// been consumed.
+    pub const INPUT_FULL: usize = 1;
+    /// Offset of the bit set when the byte to read comes from the second
+    /// port, on most controllers.
+    pub const SECOND_PORT_OUTPUT: usize = 5;
+}
+
+/// The set of all field offsets for the [`Configuration`] byte.
+mod offset {
+    pub const FIRST_PORT_INTERRUPT: usize = 0;
+    pub const SECOND_PORT_INTERRUPT: usize = 1;
+    pub const SYSTEM_FLAG: usize = 2;
+    pub const FIRST_PORT_CLOCK_DISABLED: usize = 4;
+    pub const SECOND_PORT_CLOCK_DISABLED: usize = 5;
+    pub const TRANSLATION: usize = 6;
+}
+
+/// A port of the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Port {
+    /// The first port, usually a keyboard on IRQ 1.
+    First,
+    /// The second port, usually a mouse on IRQ 12.
+    Second,
+}
+
+impl fmt::Display for Ps2Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Ps2Port::First => "first",
+                Ps2Port::Second => "second",
+            }
+        )
+    }
+}
+
+/// An error of the PS/2 controller or of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Error {
+    /// The controller or device did not answer in time.
+    Timeout,
+    /// The controller self test failed, with the answer received.
+    SelfTestFailed(u8),
+    /// A port test failed, with the answer received.
+    PortTestFailed(Ps2Port, u8),
+    /// A device did not acknowledge a command, with the answer received.
+    NotAcknowledged(u8),
+    /// The controller has no second port.
+    NoSecondPort,
+}
+
+impl fmt::Display for Ps2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ps2Error::Timeout => write!(f, "PS/2 controller timed out"),
+            Ps2Error::SelfTestFailed(answer) => {
+                write!(f, "PS/2 controller self test failed ({:#X})", answer)
+            }
+            Ps2Error::PortTestFailed(port, answer) => {
+                write!(f, "PS/2 {} port test failed ({:#X})", port, answer)
+            }
+            Ps2Error::NotAcknowledged(answer) => {
+                write!(f, "PS/2 device did not acknowledge ({:#X})", answer)
+            }
+            Ps2Error::NoSecondPort => write!(f, "PS/2 controller has no second port"),
+        }
+    }
+}
+
+/// The controller configuration byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration(pub u8);
+
+impl Configuration {
+    /// Enable or disable the interrupt of a port.
+    pub fn interrupt(self, port: Ps2Port, enabled: bool) -> Self {
+        let offset = match port {
+            Ps2Port::First => offset::FIRST_PORT_INTERRUPT,
+            Ps2Port::Second => offset::SECOND_PORT_INTERRUPT,
+        };
+        Configuration(self.0.set_bit(offset, enabled))
+    }
+
+    /// Enable or disable the clock of a port, a port with its clock disabled
+    /// receives nothing from its device.
+    pub fn clock(self, port: Ps2Port, enabled: bool) -> Self {
+        let offset = match port {
+            Ps2Port::First => offset::FIRST_PORT_CLOCK_DISABLED,
+            Ps2Port::Second => offset::SECOND_PORT_CLOCK_DISABLED,
+        };
+        Configuration(self.0.set_bit(offset, !enabled))
+    }
+
+    /// Enable or disable the translation of first port scancodes to set 1.
+    pub fn translation(self, enabled: bool) -> Self {
+        Configuration(self.0.set_bit(offset::TRANSLATION, enabled))
+    }
+
+    /// Whether the interrupt of a port is enabled.
+    pub fn is_interrupt_enabled(&self, port: Ps2Port) -> bool {
+        match port {
+            Ps2Port::First => self.0.get_bit(offset::FIRST_PORT_INTERRUPT),
+            Ps2Port::Second => self.0.get_bit(offset::SECOND_PORT_INTERRUPT),
+        }
+    }
+
+    /// Whether the clock of a port is disabled, which happens when the port
+    /// is disabled.
+    pub fn is_clock_disabled(&self, port: Ps2Port) -> bool {
+        match port {
+            Ps2Port::First => self.0.get_bit(offset::FIRST_PORT_CLOCK_DISABLED),
+            Ps2Port::Second => self.0.get_bit(offset::SECOND_PORT_CLOCK_DISABLED),
+        }
+    }
+
+    /// Whether first port scancodes are translated to set 1.
+    pub fn is_translating(&self) -> bool {
+        self.0.get_bit(offset::TRANSLATION)
+    }
+
+    /// Whether the system passed its power-on self test.
+    pub fn system_flag(&self) -> bool {
+        self.0.get_bit(offset::SYSTEM_FLAG)
+    }
+}
+
+/// The 8042 PS/2 controller.
+pub struct Ps2Controller {
+    /// The data port, reading device bytes and writing bytes to devices or
+    /// command arguments.
+    data: Port<u8>,
+    /// The status register when read, the command register when written.
+    command: Port<u8>,
+}
+
+impl Ps2Controller {
+    /// Get the controller at its standard ports.
+    pub const fn new() -> Self {
+        Ps2Controller {
+            data: Port::new(0x60),
+            command: Port::new(0x64),
+        }
+    }
+
+    /// Read the status register.
+    pub fn status(&self) -> u8 {
+        unsafe { self.command.read() }
+    }
+
+    /// Whether a byte can be read from the data port.
+    pub fn has_data(&self) -> bool {
+        self.status().get_bit(status::OUTPUT_FULL)
+    }
+
+    /// Whether the byte to read comes from the second port.
+    pub fn is_second_port_data(&self) -> bool {
+        self.status().get_bit(status::SECOND_PORT_OUTPUT)
+    }
+
+    /// Wait until the controller accepts a byte.
+    fn wait_input_empty(&self) -> Result<(), Ps2Error> {
+        for _ in 0..TIMEOUT {
+            if !self.status().get_bit(status::INPUT_FULL) {
+                return Ok(());
+            }
+            pause();
+        }
+        Err(Ps2Error::Timeout)
+    }
+
+    /// Send a command to the controller.
+    fn send_command(&self, command: u8) -> Result<(), Ps2Error> {
+        self.wait_input_empty()?;
+        unsafe { self.command.write(command) };
+        Ok(())
+    }
+
+    /// Read a byte, waiting for one.
+    pub fn read_data(&self) -> Result<u8, Ps2Error> {
+        for _ in 0..TIMEOUT {
+            if self.has_data() {
+                return Ok(self.read_data_unchecked());
+            }
+            pause();
+        }
+        Err(Ps2Error::Timeout)
+    }
+
+    /// Read a byte without waiting, as done by interrupt handlers.
+    pub fn read_data_unchecked(&self) -> u8 {
+        unsafe { self.data.read() }
+    }
+
+    /// Discard every pending byte.
+    pub fn flush(&self) {
+        while self.has_data() {
+            self.read_data_unchecked();
+        }
+    }
+
+    /// Write a byte to the data port.
+    fn write_data(&self, value: u8) -> Result<(), Ps2Error> {
+        self.wait_input_empty()?;
+        unsafe { self.data.write(value) };
+        Ok(())
+    }
+
+    /// Run the controller self test.
+    ///
+    /// # Note
+    ///
+    /// The self test may reset the controller, the configuration byte should
+    /// be written again.
+    pub fn self_test(&self) -> Result<(), Ps2Error> {
+        self.send_command(command::SELF_TEST)?;
+        match self.read_data()? {
+            SELF_TEST_PASSED => Ok(()),
+            answer => Err(Ps2Error::SelfTestFailed(answer)),
+        }
+    }
+
+    /// Read the configuration byte.
+    pub fn configuration(&self) -> Result<Configuration, Ps2Error> {
+        self.send_command(command::READ_CONFIGURATION)?;
+        self.read_data().map(Configuration)
+    }
+
+    /// Write the configuration byte.
+    pub fn set_configuration(&self, configuration: Configuration) -> Result<(), Ps2Error> {
+        self.send_command(command::WRITE_CONFIGURATION)?;
+        self.write_data(configuration.0)
+    }
+
+    /// Enable a port.
+    pub fn enable_port(&self, port: Ps2Port) -> Result<(), Ps2Error> {
+        self.send_command(match port {
+            Ps2Port::First => command::ENABLE_FIRST_PORT,
+            Ps2Port::Second => command::ENABLE_SECOND_PORT,
+        })
+    }
+
+    /// Disable a port, its device can no longer send bytes.
+    pub fn disable_port(&self, port: Ps2Port) -> Result<(), Ps2Error> {
+        self.send_command(match port {
+            Ps2Port::First => command::DISABLE_FIRST_PORT,
+            Ps2Port::Second => command::DISABLE_SECOND_PORT,
+        })
+    }
+
+    /// Test the lines of a port.
+    pub fn test_port(&self, port: Ps2Port) -> Result<(), Ps2Error> {
+        self.send_command(match port {
+            Ps2Port::First => command::TEST_FIRST_PORT,
+            Ps2Port::Second => command::TEST_SECOND_PORT,
+        })?;
+        match self.read_data()? {
+            PORT_TEST_PASSED => Ok(()),
+            answer => Err(Ps2Error::PortTestFailed(port, answer)),
+        }
+    }
+
+    /// Whether the controller has a second port, its clock follows its
+    /// enable state only if it exists.
+    ///
+    /// # Note
+    ///
+    /// Both ports are left disabled.
+    pub fn has_second_port(&self) -> Result<bool, Ps2Error> {
+        self.enable_port(Ps2Port::Second)?;
+        let exists = !self.configuration()?.is_clock_disabled(Ps2Port::Second);
+        self.disable_port(Ps2Port::Second)?;
+        Ok(exists)
+    }
+
+    /// Write a byte to the device of a port.
+    pub fn write_device(&self, port: Ps2Port, value: u8) -> Result<(), Ps2Error> {
+        if port == Ps2Port::Second {
+            self.send_command(command::WRITE_SECOND_PORT)?;
+        }
+        self.write_data(value)
+    }
+
+    /// Send a command to the device of a port and wait for its
+    /// acknowledgement, sending it again when the device asks to.
+    ///
+    /// # Note
+    ///
+    /// The port's interrupt must be disabled, or its handler would consume
+    /// the answer.
+    pub fn send_device_command(&self, port: Ps2Port, value: u8) -> Result<(), Ps2Error> {
+        for _ in 0..RETRIES {
+            self.write_device(port, value)?;
+            match self.read_data()? {
+                ACK => return Ok(()),
+                RESEND => continue,
+                answer => return Err(Ps2Error::NotAcknowledged(answer)),
+            }
+        }
+        Err(Ps2Error::NotAcknowledged(RESEND))
+    }
+
+    /// Pulse the processor reset line, returning only if the reset did not
+    /// happen.
+    pub fn pulse_reset(&self) -> Result<(), Ps2Error> {
+        self.send_command(command::PULSE_RESET)
+    }
+}
+
+impl Default for Ps2Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn configuration_byte() {
+        let configuration = Configuration(0b0111_0100)
+            .interrupt(Ps2Port::First, true)
+            .interrupt(Ps2Port::Second, true)
+            .translation(false);
+        assert_eq!(configuration.0, 0b0011_0111);
+        assert_eq!(configuration.clock(Ps2Port::First, true).0, 0b0010_0111);
+        assert!(configuration.is_interrupt_enabled(Ps2Port::Second));
+        assert!(configuration.is_clock_disabled(Ps2Port::First));
+        assert!(!configuration.is_translating());
+        assert!(configuration.system_flag());
+    }
+}
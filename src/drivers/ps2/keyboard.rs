@@ -0,0 +1,935 @@
+//! A module decoding the scancodes of a PS/2 keyboard into [`KeyEvent`]s,
+//! translated to characters through a [`Keymap`].
+//!
+//! Events are queued by the keyboard interrupt and read with
+//! [`read_event`] or [`read_char`].
+use super::controller::{Ps2Port, ACK, RESEND};
+use super::keymap::{self, Keymap};
+use super::CONTROLLER;
+use crate::sync::IrqSafeSpinLock;
+use crate::utils::ring_buffer::RingBuffer;
+use core::fmt;
+
+/// Capacity of the event queue, events are dropped when it is full.
+pub const EVENT_QUEUE_SIZE: usize = 128;
+
+/// Prefix of extended scancodes.
+const EXTENDED: u8 = 0xE0;
+/// Prefix of the pause key sequence.
+const PAUSE: u8 = 0xE1;
+/// Prefix of release scancodes in set 2.
+const RELEASE: u8 = 0xF0;
+/// Offset of the release bit of set 1 scancodes.
+const RELEASE_BIT: u8 = 0x80;
+/// Command setting the lock LEDs, followed by the LED bits.
+const SET_LEDS: u8 = 0xED;
+/// Number of times a LED update byte is sent again on a resend request.
+const LED_RETRIES: u8 = 3;
+
+/// The scancode set sent by the keyboard, as seen by the processor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScancodeSet {
+    /// The IBM XT set, sent when the controller translates scancodes.
+    Set1,
+    /// The IBM AT set, the default of keyboards.
+    Set2,
+}
+
+/// A physical key, named after its meaning on a US keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCode {
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+    Backquote,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+    Minus,
+    Equal,
+    Backspace,
+    Tab,
+    Q,
+    W,
+    E,
+    R,
+    T,
+    Y,
+    U,
+    I,
+    O,
+    P,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    CapsLock,
+    A,
+    S,
+    D,
+    F,
+    G,
+    H,
+    J,
+    K,
+    L,
+    Semicolon,
+    Quote,
+    Enter,
+    LeftShift,
+    /// The key between left shift and Z on ISO keyboards.
+    NonUsBackslash,
+    Z,
+    X,
+    C,
+    V,
+    B,
+    N,
+    M,
+    Comma,
+    Period,
+    Slash,
+    RightShift,
+    LeftControl,
+    LeftGui,
+    LeftAlt,
+    Space,
+    /// Right alt, AltGr on most non US layouts.
+    RightAlt,
+    RightGui,
+    Menu,
+    RightControl,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    NumLock,
+    KeypadSlash,
+    KeypadStar,
+    KeypadMinus,
+    KeypadPlus,
+    KeypadEnter,
+    KeypadPeriod,
+    Keypad0,
+    Keypad1,
+    Keypad2,
+    Keypad3,
+    Keypad4,
+    Keypad5,
+    Keypad6,
+    Keypad7,
+    Keypad8,
+    Keypad9,
+}
+
+/// Whether a key went down or up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Pressed,
+    Released,
+}
+
+/// The state of the modifier and lock keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_control: bool,
+    pub right_control: bool,
+    pub left_alt: bool,
+    /// Right alt, AltGr on most non US layouts.
+    pub right_alt: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    /// Get the state of modifiers with every key released and locks off.
+    pub const fn const_default() -> Self {
+        Modifiers {
+            left_shift: false,
+            right_shift: false,
+            left_control: false,
+            right_control: false,
+            left_alt: false,
+            right_alt: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+        }
+    }
+
+    /// Whether either shift key is pressed.
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    /// Whether either control key is pressed.
+    pub fn control(&self) -> bool {
+        self.left_control || self.right_control
+    }
+
+    /// Whether the left alt key is pressed.
+    pub fn alt(&self) -> bool {
+        self.left_alt
+    }
+
+    /// Whether AltGr, the right alt key, is pressed.
+    pub fn alt_gr(&self) -> bool {
+        self.right_alt
+    }
+
+    /// Whether letters are upper case, from shift and caps lock.
+    pub fn upper_case(&self) -> bool {
+        self.shift() != self.caps_lock
+    }
+
+    /// Update the state on a key event.
+    ///
+    /// Returns whether a lock toggled.
+    fn update(&mut self, code: KeyCode, state: KeyState) -> bool {
+        let pressed = state == KeyState::Pressed;
+        match code {
+            KeyCode::LeftShift => self.left_shift = pressed,
+            KeyCode::RightShift => self.right_shift = pressed,
+            KeyCode::LeftControl => self.left_control = pressed,
+            KeyCode::RightControl => self.right_control = pressed,
+            KeyCode::LeftAlt => self.left_alt = pressed,
+            KeyCode::RightAlt => self.right_alt = pressed,
+            KeyCode::CapsLock if pressed => {
+                self.caps_lock = !self.caps_lock;
+                return true;
+            }
+            KeyCode::NumLock if pressed => {
+                self.num_lock = !self.num_lock;
+                return true;
+            }
+            KeyCode::ScrollLock if pressed => {
+                self.scroll_lock = !self.scroll_lock;
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Get the LED bits of the locks, as expected by the set LEDs command.
+    fn leds(&self) -> u8 {
+        u8::from(self.scroll_lock) | u8::from(self.num_lock) << 1 | u8::from(self.caps_lock) << 2
+    }
+}
+
+/// A key going down or up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The physical key.
+    pub code: KeyCode,
+    pub state: KeyState,
+    /// The modifiers once the event is applied.
+    pub modifiers: Modifiers,
+    /// The character typed by a key press, if any.
+    pub character: Option<char>,
+}
+
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {:?}", self.code, self.state)?;
+        if let Some(character) = self.character {
+            write!(f, " ({:?})", character)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the key of a set 1 scancode, without the release bit.
+fn set1_key(scancode: u8, extended: bool) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = if extended {
+        match scancode {
+            0x1C => KeypadEnter,
+            0x1D => RightControl,
+            0x35 => KeypadSlash,
+            0x37 => PrintScreen,
+            0x38 => RightAlt,
+            0x47 => Home,
+            0x48 => ArrowUp,
+            0x49 => PageUp,
+            0x4B => ArrowLeft,
+            0x4D => ArrowRight,
+            0x4F => End,
+            0x50 => ArrowDown,
+            0x51 => PageDown,
+            0x52 => Insert,
+            0x53 => Delete,
+            0x5B => LeftGui,
+            0x5C => RightGui,
+            0x5D => Menu,
+            // Including the fake shifts around print screen and the
+            // navigation keys.
+            _ => return None,
+        }
+    } else {
+        match scancode {
+            0x01 => Escape,
+            0x02 => Digit1,
+            0x03 => Digit2,
+            0x04 => Digit3,
+            0x05 => Digit4,
+            0x06 => Digit5,
+            0x07 => Digit6,
+            0x08 => Digit7,
+            0x09 => Digit8,
+            0x0A => Digit9,
+            0x0B => Digit0,
+            0x0C => Minus,
+            0x0D => Equal,
+            0x0E => Backspace,
+            0x0F => Tab,
+            0x10 => Q,
+            0x11 => W,
+            0x12 => E,
+            0x13 => R,
+            0x14 => T,
+            0x15 => Y,
+            0x16 => U,
+            0x17 => I,
+            0x18 => O,
+            0x19 => P,
+            0x1A => LeftBracket,
+            0x1B => RightBracket,
+            0x1C => Enter,
+            0x1D => LeftControl,
+            0x1E => A,
+            0x1F => S,
+            0x20 => D,
+            0x21 => F,
+            0x22 => G,
+            0x23 => H,
+            0x24 => J,
+            0x25 => K,
+            0x26 => L,
+            0x27 => Semicolon,
+            0x28 => Quote,
+            0x29 => Backquote,
+            0x2A => LeftShift,
+            0x2B => Backslash,
+            0x2C => Z,
+            0x2D => X,
+            0x2E => C,
+            0x2F => V,
+            0x30 => B,
+            0x31 => N,
+            0x32 => M,
+            0x33 => Comma,
+            0x34 => Period,
+            0x35 => Slash,
+            0x36 => RightShift,
+            0x37 => KeypadStar,
+            0x38 => LeftAlt,
+            0x39 => Space,
+            0x3A => CapsLock,
+            0x3B => F1,
+            0x3C => F2,
+            0x3D => F3,
+            0x3E => F4,
+            0x3F => F5,
+            0x40 => F6,
+            0x41 => F7,
+            0x42 => F8,
+            0x43 => F9,
+            0x44 => F10,
+            0x45 => NumLock,
+            0x46 => ScrollLock,
+            0x47 => Keypad7,
+            0x48 => Keypad8,
+            0x49 => Keypad9,
+            0x4A => KeypadMinus,
+            0x4B => Keypad4,
+            0x4C => Keypad5,
+            0x4D => Keypad6,
+            0x4E => KeypadPlus,
+            0x4F => Keypad1,
+            0x50 => Keypad2,
+            0x51 => Keypad3,
+            0x52 => Keypad0,
+            0x53 => KeypadPeriod,
+            0x56 => NonUsBackslash,
+            0x57 => F11,
+            0x58 => F12,
+            _ => return None,
+        }
+    };
+    Some(code)
+}
+
+/// Get the key of a set 2 scancode, without the release prefix.
+fn set2_key(scancode: u8, extended: bool) -> Option<KeyCode> {
+    use KeyCode::*;
+    let code = if extended {
+        match scancode {
+            0x11 => RightAlt,
+            0x14 => RightControl,
+            0x1F => LeftGui,
+            0x27 => RightGui,
+            0x2F => Menu,
+            0x4A => KeypadSlash,
+            0x5A => KeypadEnter,
+            0x69 => End,
+            0x6B => ArrowLeft,
+            0x6C => Home,
+            0x70 => Insert,
+            0x71 => Delete,
+            0x72 => ArrowDown,
+            0x74 => ArrowRight,
+            0x75 => ArrowUp,
+            0x7A => PageDown,
+            0x7C => PrintScreen,
+            0x7D => PageUp,
+            _ => return None,
+        }
+    } else {
+        match scancode {
+            0x01 => F9,
+            0x03 => F5,
+            0x04 => F3,
+            0x05 => F1,
+            0x06 => F2,
+            0x07 => F12,
+            0x09 => F10,
+            0x0A => F8,
+            0x0B => F6,
+            0x0C => F4,
+            0x0D => Tab,
+            0x0E => Backquote,
+            0x11 => LeftAlt,
+            0x12 => LeftShift,
+            0x14 => LeftControl,
+            0x15 => Q,
+            0x16 => Digit1,
+            0x1A => Z,
+            0x1B => S,
+            0x1C => A,
+            0x1D => W,
+            0x1E => Digit2,
+            0x21 => C,
+            0x22 => X,
+            0x23 => D,
+            0x24 => E,
+            0x25 => Digit4,
+            0x26 => Digit3,
+            0x29 => Space,
+            0x2A => V,
+            0x2B => F,
+            0x2C => T,
+            0x2D => R,
+            0x2E => Digit5,
+            0x31 => N,
+            0x32 => B,
+            0x33 => H,
+            0x34 => G,
+            0x35 => Y,
+            0x36 => Digit6,
+            0x3A => M,
+            0x3B => J,
+            0x3C => U,
+            0x3D => Digit7,
+            0x3E => Digit8,
+            0x41 => Comma,
+            0x42 => K,
+            0x43 => I,
+            0x44 => O,
+            0x45 => Digit0,
+            0x46 => Digit9,
+            0x49 => Period,
+            0x4A => Slash,
+            0x4B => L,
+            0x4C => Semicolon,
+            0x4D => P,
+            0x4E => Minus,
+            0x52 => Quote,
+            0x54 => LeftBracket,
+            0x55 => Equal,
+            0x58 => CapsLock,
+            0x59 => RightShift,
+            0x5A => Enter,
+            0x5B => RightBracket,
+            0x5D => Backslash,
+            0x61 => NonUsBackslash,
+            0x66 => Backspace,
+            0x69 => Keypad1,
+            0x6B => Keypad4,
+            0x6C => Keypad7,
+            0x70 => Keypad0,
+            0x71 => KeypadPeriod,
+            0x72 => Keypad2,
+            0x73 => Keypad5,
+            0x74 => Keypad6,
+            0x75 => Keypad8,
+            0x76 => Escape,
+            0x77 => NumLock,
+            0x78 => F11,
+            0x79 => KeypadPlus,
+            0x7A => Keypad3,
+            0x7B => KeypadMinus,
+            0x7C => KeypadStar,
+            0x7D => Keypad9,
+            0x7E => ScrollLock,
+            0x83 => F7,
+            _ => return None,
+        }
+    };
+    Some(code)
+}
+
+/// A state machine assembling scancode bytes into key events.
+#[derive(Debug, Clone, Copy)]
+pub struct ScancodeDecoder {
+    set: ScancodeSet,
+    extended: bool,
+    release: bool,
+    /// Remaining bytes of the pause key sequence, which has no release.
+    pause_remaining: u8,
+}
+
+impl ScancodeDecoder {
+    /// Create a decoder for a scancode set.
+    pub const fn new(set: ScancodeSet) -> Self {
+        ScancodeDecoder {
+            set,
+            extended: false,
+            release: false,
+            pause_remaining: 0,
+        }
+    }
+
+    /// Get the decoded scancode set.
+    pub fn set(&self) -> ScancodeSet {
+        self.set
+    }
+
+    /// Feed a byte received from the keyboard.
+    ///
+    /// Returns the key event completed by the byte, if any.
+    pub fn feed(&mut self, byte: u8) -> Option<(KeyCode, KeyState)> {
+        if self.pause_remaining > 0 {
+            self.pause_remaining -= 1;
+            return (self.pause_remaining == 0).then_some((KeyCode::Pause, KeyState::Pressed));
+        }
+        match byte {
+            ACK | RESEND => return None,
+            EXTENDED => {
+                self.extended = true;
+                return None;
+            }
+            PAUSE => {
+                self.pause_remaining = match self.set {
+                    ScancodeSet::Set1 => 5,
+                    ScancodeSet::Set2 => 7,
+                };
+                return None;
+            }
+            RELEASE if self.set == ScancodeSet::Set2 => {
+                self.release = true;
+                return None;
+            }
+            _ => {}
+        }
+        let extended = core::mem::take(&mut self.extended);
+        let (code, released) = match self.set {
+            ScancodeSet::Set1 => (
+                set1_key(byte & !RELEASE_BIT, extended),
+                byte & RELEASE_BIT != 0,
+            ),
+            ScancodeSet::Set2 => (set2_key(byte, extended), core::mem::take(&mut self.release)),
+        };
+        let state = if released {
+            KeyState::Released
+        } else {
+            KeyState::Pressed
+        };
+        Some((code?, state))
+    }
+}
+
+/// A keyboard's decoder, modifiers and keymap.
+pub struct Keyboard {
+    decoder: ScancodeDecoder,
+    modifiers: Modifiers,
+    keymap: &'static dyn Keymap,
+    leds: LedUpdate,
+}
+
+impl Keyboard {
+    /// Create a keyboard with the US keymap.
+    pub const fn new(set: ScancodeSet) -> Self {
+        Keyboard {
+            decoder: ScancodeDecoder::new(set),
+            modifiers: Modifiers::const_default(),
+            keymap: &keymap::Us,
+            leds: LedUpdate::new(),
+        }
+    }
+
+    /// Get the state of the modifier and lock keys.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Process a byte received from the keyboard.
+    ///
+    /// Returns the completed event along with whether a lock toggled.
+    pub fn process(&mut self, byte: u8) -> Option<(KeyEvent, bool)> {
+        let (code, state) = self.decoder.feed(byte)?;
+        let lock_toggled = self.modifiers.update(code, state);
+        let character = match state {
+            KeyState::Pressed => translate(self.keymap, code, &self.modifiers),
+            KeyState::Released => None,
+        };
+        let event = KeyEvent {
+            code,
+            state,
+            modifiers: self.modifiers,
+            character,
+        };
+        Some((event, lock_toggled))
+    }
+}
+
+/// The step of a LED update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LedStep {
+    Idle,
+    /// The set LEDs command waits for its acknowledgement.
+    CommandSent,
+    /// The LED bits wait for their acknowledgement.
+    LedsSent,
+}
+
+/// A state machine updating the keyboard LEDs from its interrupt handler,
+/// sending every byte once the keyboard acknowledged the previous one.
+#[derive(Debug, Clone, Copy)]
+struct LedUpdate {
+    step: LedStep,
+    /// The LED bits to send.
+    leds: u8,
+    /// Whether the locks changed during the current update.
+    pending: bool,
+    retries: u8,
+}
+
+impl LedUpdate {
+    const fn new() -> Self {
+        LedUpdate {
+            step: LedStep::Idle,
+            leds: 0,
+            pending: false,
+            retries: 0,
+        }
+    }
+
+    /// Start an update, or queue it after the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `leds` - The LED bits.
+    /// * `send` - A function sending a byte to the keyboard.
+    fn request(&mut self, leds: u8, send: impl FnOnce(u8)) {
+        self.leds = leds;
+        if self.step == LedStep::Idle {
+            self.start(send);
+        } else {
+            self.pending = true;
+        }
+    }
+
+    fn start(&mut self, send: impl FnOnce(u8)) {
+        self.step = LedStep::CommandSent;
+        self.pending = false;
+        self.retries = 0;
+        send(SET_LEDS);
+    }
+
+    /// Handle a byte received from the keyboard.
+    ///
+    /// Returns whether the byte answered the update, it must then not be
+    /// decoded.
+    fn answer(&mut self, byte: u8, send: impl FnOnce(u8)) -> bool {
+        match (self.step, byte) {
+            (LedStep::Idle, _) => return false,
+            (step, RESEND) if self.retries < LED_RETRIES => {
+                self.retries += 1;
+                send(match step {
+                    LedStep::CommandSent => SET_LEDS,
+                    _ => self.leds,
+                });
+            }
+            (LedStep::CommandSent, ACK) => {
+                self.step = LedStep::LedsSent;
+                self.retries = 0;
+                send(self.leds);
+            }
+            (LedStep::LedsSent, ACK) => {
+                self.step = LedStep::Idle;
+                if self.pending {
+                    self.start(send);
+                }
+            }
+            // Too many resend requests, the LEDs are updated on the next
+            // lock toggle.
+            (_, RESEND) => self.step = LedStep::Idle,
+            // A keyboard does not scan keys before answering, the update
+            // was lost.
+            _ => {
+                self.step = LedStep::Idle;
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Translate a key press to a character, handling the keys common to every
+/// layout before the keymap.
+fn translate(keymap: &dyn Keymap, code: KeyCode, modifiers: &Modifiers) -> Option<char> {
+    use KeyCode::*;
+    let character = match code {
+        Enter | KeypadEnter => '\n',
+        Tab => '\t',
+        Backspace => '\x08',
+        Escape => '\x1B',
+        Space => ' ',
+        KeypadSlash => '/',
+        KeypadStar => '*',
+        KeypadMinus => '-',
+        KeypadPlus => '+',
+        Keypad0 | Keypad1 | Keypad2 | Keypad3 | Keypad4 | Keypad5 | Keypad6 | Keypad7 | Keypad8
+        | Keypad9 | KeypadPeriod
+            if !modifiers.num_lock =>
+        {
+            return None
+        }
+        KeypadPeriod => '.',
+        Keypad0 => '0',
+        Keypad1 => '1',
+        Keypad2 => '2',
+        Keypad3 => '3',
+        Keypad4 => '4',
+        Keypad5 => '5',
+        Keypad6 => '6',
+        Keypad7 => '7',
+        Keypad8 => '8',
+        Keypad9 => '9',
+        _ => {
+            let character = keymap.translate(code, modifiers)?;
+            // Control and a letter types the matching control character.
+            if modifiers.control() && character.is_ascii_alphabetic() {
+                return char::from_u32(character.to_ascii_uppercase() as u32 - 0x40);
+            }
+            character
+        }
+    };
+    Some(character)
+}
+
+static KEYBOARD: IrqSafeSpinLock<Keyboard> = IrqSafeSpinLock::new(Keyboard::new(ScancodeSet::Set1));
+static EVENTS: RingBuffer<KeyEvent, EVENT_QUEUE_SIZE> = RingBuffer::new();
+
+/// Set the scancode set sent by the keyboard.
+pub fn set_scancode_set(set: ScancodeSet) {
+    KEYBOARD.lock().decoder = ScancodeDecoder::new(set);
+}
+
+/// Set the keymap translating keys to characters.
+///
+/// # Arguments
+///
+/// * `keymap` - The keymap, such as [`keymap::US`] or [`keymap::AZERTY`].
+pub fn set_keymap(keymap: &'static dyn Keymap) {
+    KEYBOARD.lock().keymap = keymap;
+}
+
+/// Get the state of the modifier and lock keys.
+pub fn modifiers() -> Modifiers {
+    KEYBOARD.lock().modifiers()
+}
+
+/// Process the byte sent by the keyboard, called by the keyboard interrupt.
+pub fn handle_interrupt() {
+    let byte = CONTROLLER.read_data_unchecked();
+    let mut keyboard = KEYBOARD.lock();
+    if keyboard.leds.answer(byte, send_to_keyboard) {
+        return;
+    }
+    let Some((event, lock_toggled)) = keyboard.process(byte) else {
+        return;
+    };
+    if lock_toggled {
+        keyboard
+            .leds
+            .request(event.modifiers.leds(), send_to_keyboard);
+    }
+    drop(keyboard);
+    // Events are dropped when nobody reads them.
+    EVENTS.push(event).ok();
+}
+
+/// Send a byte to the keyboard without waiting for its answer, which comes
+/// through the interrupt.
+fn send_to_keyboard(byte: u8) {
+    CONTROLLER.write_device(Ps2Port::First, byte).ok();
+}
+
+/// Get the oldest key event, if any.
+pub fn read_event() -> Option<KeyEvent> {
+    EVENTS.pop()
+}
+
+/// Get the character of the oldest key press typing one, skipping other
+/// events.
+pub fn read_char() -> Option<char> {
+    while let Some(event) = read_event() {
+        if let Some(character) = event.character {
+            return Some(character);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn led_update_waits_for_acknowledgements() {
+        let mut update = LedUpdate::new();
+        let mut sent = None;
+        update.request(0b100, |byte| sent = Some(byte));
+        assert_eq!(sent.take(), Some(SET_LEDS));
+        // A toggle during the update is sent after it.
+        update.request(0b110, |byte| sent = Some(byte));
+        assert_eq!(sent.take(), None);
+        assert!(update.answer(RESEND, |byte| sent = Some(byte)));
+        assert_eq!(sent.take(), Some(SET_LEDS));
+        assert!(update.answer(ACK, |byte| sent = Some(byte)));
+        assert_eq!(sent.take(), Some(0b110));
+        assert!(update.answer(ACK, |byte| sent = Some(byte)));
+        assert_eq!(sent.take(), Some(SET_LEDS));
+        assert!(update.answer(ACK, |byte| sent = Some(byte)));
+        assert!(update.answer(ACK, |byte| sent = Some(byte)));
+        // Once idle, bytes are scancodes.
+        assert!(!update.answer(ACK, |byte| sent = Some(byte)));
+        assert!(!update.answer(0x1E, |byte| sent = Some(byte)));
+    }
+
+    #[test_case]
+    fn set1_decoding() {
+        let mut decoder = ScancodeDecoder::new(ScancodeSet::Set1);
+        assert_eq!(decoder.feed(0x1E), Some((KeyCode::A, KeyState::Pressed)));
+        assert_eq!(decoder.feed(0x9E), Some((KeyCode::A, KeyState::Released)));
+        assert_eq!(decoder.feed(EXTENDED), None);
+        assert_eq!(
+            decoder.feed(0x48),
+            Some((KeyCode::ArrowUp, KeyState::Pressed))
+        );
+        assert_eq!(decoder.feed(EXTENDED), None);
+        assert_eq!(
+            decoder.feed(0xB8),
+            Some((KeyCode::RightAlt, KeyState::Released))
+        );
+        // Print screen, around fake shifts.
+        for byte in [EXTENDED, 0x2A, EXTENDED] {
+            assert_eq!(decoder.feed(byte), None);
+        }
+        assert_eq!(
+            decoder.feed(0x37),
+            Some((KeyCode::PrintScreen, KeyState::Pressed))
+        );
+    }
+
+    #[test_case]
+    fn set2_decoding() {
+        let mut decoder = ScancodeDecoder::new(ScancodeSet::Set2);
+        assert_eq!(decoder.feed(0x1C), Some((KeyCode::A, KeyState::Pressed)));
+        assert_eq!(decoder.feed(RELEASE), None);
+        assert_eq!(decoder.feed(0x1C), Some((KeyCode::A, KeyState::Released)));
+        for byte in [EXTENDED, RELEASE] {
+            assert_eq!(decoder.feed(byte), None);
+        }
+        assert_eq!(
+            decoder.feed(0x6B),
+            Some((KeyCode::ArrowLeft, KeyState::Released))
+        );
+        // The extended prefix applies to the next key only.
+        assert_eq!(
+            decoder.feed(0x6B),
+            Some((KeyCode::Keypad4, KeyState::Pressed))
+        );
+    }
+
+    #[test_case]
+    fn pause_sequence() {
+        let mut decoder = ScancodeDecoder::new(ScancodeSet::Set1);
+        let sequence = [PAUSE, 0x1D, 0x45, PAUSE, 0x9D, 0xC5];
+        for byte in &sequence[..5] {
+            assert_eq!(decoder.feed(*byte), None);
+        }
+        assert_eq!(
+            decoder.feed(sequence[5]),
+            Some((KeyCode::Pause, KeyState::Pressed))
+        );
+        assert_eq!(decoder.feed(0x10), Some((KeyCode::Q, KeyState::Pressed)));
+    }
+
+    #[test_case]
+    fn modifiers_and_locks() {
+        let mut keyboard = Keyboard::new(ScancodeSet::Set1);
+        let (event, _) = keyboard.process(0x2A).unwrap();
+        assert!(event.modifiers.shift());
+        assert_eq!(keyboard.process(0x1E).unwrap().0.character, Some('A'));
+        keyboard.process(0xAA);
+        assert!(!keyboard.modifiers().shift());
+        let (event, toggled) = keyboard.process(0x3A).unwrap();
+        assert!(toggled && event.modifiers.caps_lock);
+        assert_eq!(
+            keyboard.process(0xBA).map(|(_, toggled)| toggled),
+            Some(false)
+        );
+        assert_eq!(keyboard.process(0x1E).unwrap().0.character, Some('A'));
+        keyboard.process(0x1D);
+        assert_eq!(keyboard.process(0x2E).unwrap().0.character, Some('\x03'));
+    }
+
+    #[test_case]
+    fn keypad_needs_num_lock() {
+        let mut keyboard = Keyboard::new(ScancodeSet::Set2);
+        assert_eq!(keyboard.process(0x69).unwrap().0.character, None);
+        keyboard.process(0x77);
+        assert_eq!(keyboard.process(0x69).unwrap().0.character, Some('1'));
+    }
+}
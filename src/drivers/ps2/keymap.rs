@@ -0,0 +1,198 @@
+//! A module translating keys to characters according to a keyboard layout.
+use super::keyboard::{KeyCode, Modifiers};
+
+/// A keyboard layout, translating the printable keys. Keys common to every
+/// layout, such as enter or the keypad, are translated by the keyboard.
+pub trait Keymap: Sync {
+    /// Get the character typed by a key, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The pressed key.
+    /// * `modifiers` - The state of the modifier and lock keys.
+    fn translate(&self, code: KeyCode, modifiers: &Modifiers) -> Option<char>;
+}
+
+/// The US QWERTY layout.
+pub struct Us;
+
+/// The French AZERTY layout, dead keys type their accent directly.
+pub struct Azerty;
+
+/// The US QWERTY layout.
+pub static US: Us = Us;
+/// The French AZERTY layout.
+pub static AZERTY: Azerty = Azerty;
+
+/// Get the letter of a key on a QWERTY keyboard, in lower case.
+fn qwerty_letter(code: KeyCode) -> Option<char> {
+    use KeyCode::*;
+    let letter = match code {
+        A => 'a',
+        B => 'b',
+        C => 'c',
+        D => 'd',
+        E => 'e',
+        F => 'f',
+        G => 'g',
+        H => 'h',
+        I => 'i',
+        J => 'j',
+        K => 'k',
+        L => 'l',
+        M => 'm',
+        N => 'n',
+        O => 'o',
+        P => 'p',
+        Q => 'q',
+        R => 'r',
+        S => 's',
+        T => 't',
+        U => 'u',
+        V => 'v',
+        W => 'w',
+        X => 'x',
+        Y => 'y',
+        Z => 'z',
+        _ => return None,
+    };
+    Some(letter)
+}
+
+/// Apply the case of the modifiers to a letter.
+fn with_case(letter: char, modifiers: &Modifiers) -> char {
+    if modifiers.upper_case() {
+        letter.to_ascii_uppercase()
+    } else {
+        letter
+    }
+}
+
+impl Keymap for Us {
+    fn translate(&self, code: KeyCode, modifiers: &Modifiers) -> Option<char> {
+        use KeyCode::*;
+        if let Some(letter) = qwerty_letter(code) {
+            return Some(with_case(letter, modifiers));
+        }
+        let (normal, shifted) = match code {
+            Backquote => ('`', '~'),
+            Digit1 => ('1', '!'),
+            Digit2 => ('2', '@'),
+            Digit3 => ('3', '#'),
+            Digit4 => ('4', '$'),
+            Digit5 => ('5', '%'),
+            Digit6 => ('6', '^'),
+            Digit7 => ('7', '&'),
+            Digit8 => ('8', '*'),
+            Digit9 => ('9', '('),
+            Digit0 => ('0', ')'),
+            Minus => ('-', '_'),
+            Equal => ('=', '+'),
+            LeftBracket => ('[', '{'),
+            RightBracket => (']', '}'),
+            Backslash | NonUsBackslash => ('\\', '|'),
+            Semicolon => (';', ':'),
+            Quote => ('\'', '"'),
+            Comma => (',', '<'),
+            Period => ('.', '>'),
+            Slash => ('/', '?'),
+            _ => return None,
+        };
+        Some(if modifiers.shift() { shifted } else { normal })
+    }
+}
+
+impl Keymap for Azerty {
+    fn translate(&self, code: KeyCode, modifiers: &Modifiers) -> Option<char> {
+        use KeyCode::*;
+        if modifiers.alt_gr() {
+            return Some(match code {
+                Digit2 => '~',
+                Digit3 => '#',
+                Digit4 => '{',
+                Digit5 => '[',
+                Digit6 => '|',
+                Digit7 => '`',
+                Digit8 => '\\',
+                Digit9 => '^',
+                Digit0 => '@',
+                Minus => ']',
+                Equal => '}',
+                E => '€',
+                RightBracket => '¤',
+                _ => return None,
+            });
+        }
+        let letter = match code {
+            Q => Some('a'),
+            W => Some('z'),
+            A => Some('q'),
+            Z => Some('w'),
+            Semicolon => Some('m'),
+            M => None,
+            code => qwerty_letter(code),
+        };
+        if let Some(letter) = letter {
+            return Some(with_case(letter, modifiers));
+        }
+        let (normal, shifted) = match code {
+            Backquote => ('²', '²'),
+            Digit1 => ('&', '1'),
+            Digit2 => ('é', '2'),
+            Digit3 => ('"', '3'),
+            Digit4 => ('\'', '4'),
+            Digit5 => ('(', '5'),
+            Digit6 => ('-', '6'),
+            Digit7 => ('è', '7'),
+            Digit8 => ('_', '8'),
+            Digit9 => ('ç', '9'),
+            Digit0 => ('à', '0'),
+            Minus => (')', '°'),
+            Equal => ('=', '+'),
+            LeftBracket => ('^', '¨'),
+            RightBracket => ('$', '£'),
+            Quote => ('ù', '%'),
+            Backslash => ('*', 'µ'),
+            NonUsBackslash => ('<', '>'),
+            M => (',', '?'),
+            Comma => (';', '.'),
+            Period => (':', '/'),
+            Slash => ('!', '§'),
+            _ => return None,
+        };
+        Some(if modifiers.shift() { shifted } else { normal })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn us_layout() {
+        let mut modifiers = Modifiers::default();
+        assert_eq!(US.translate(KeyCode::Q, &modifiers), Some('q'));
+        assert_eq!(US.translate(KeyCode::Digit2, &modifiers), Some('2'));
+        modifiers.left_shift = true;
+        assert_eq!(US.translate(KeyCode::Q, &modifiers), Some('Q'));
+        assert_eq!(US.translate(KeyCode::Digit2, &modifiers), Some('@'));
+        modifiers.caps_lock = true;
+        assert_eq!(US.translate(KeyCode::Q, &modifiers), Some('q'));
+        assert_eq!(US.translate(KeyCode::F1, &modifiers), None);
+    }
+
+    #[test_case]
+    fn azerty_layout() {
+        let mut modifiers = Modifiers::default();
+        assert_eq!(AZERTY.translate(KeyCode::Q, &modifiers), Some('a'));
+        assert_eq!(AZERTY.translate(KeyCode::Semicolon, &modifiers), Some('m'));
+        assert_eq!(AZERTY.translate(KeyCode::M, &modifiers), Some(','));
+        assert_eq!(AZERTY.translate(KeyCode::Digit2, &modifiers), Some('é'));
+        modifiers.right_shift = true;
+        assert_eq!(AZERTY.translate(KeyCode::Digit2, &modifiers), Some('2'));
+        assert_eq!(AZERTY.translate(KeyCode::W, &modifiers), Some('Z'));
+        modifiers.right_shift = false;
+        modifiers.right_alt = true;
+        assert_eq!(AZERTY.translate(KeyCode::Digit0, &modifiers), Some('@'));
+    }
+}
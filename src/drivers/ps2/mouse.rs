@@ -0,0 +1,314 @@
+//! A module decoding the packets of a PS/2 mouse into [`MouseEvent`]s.
+//!
+//! Wheel mice (IntelliMouse) are detected and send 4-byte packets, other
+//! mice send 3-byte packets. Events are queued by the mouse interrupt and
+//! read with [`read_event`].
+use super::controller::{Ps2Controller, Ps2Error, Ps2Port};
+use crate::sync::IrqSafeSpinLock;
+use crate::utils::bitfield::*;
+use crate::utils::ring_buffer::RingBuffer;
+use core::fmt;
+
+/// Capacity of the event queue, events are dropped when it is full.
+pub const EVENT_QUEUE_SIZE: usize = 128;
+
+/// Mouse commands.
+mod command {
+    pub const SET_SAMPLE_RATE: u8 = 0xF3;
+    pub const GET_DEVICE_ID: u8 = 0xF2;
+    pub const ENABLE_REPORTING: u8 = 0xF4;
+    pub const SET_DEFAULTS: u8 = 0xF6;
+}
+
+/// The set of all field offsets for the first byte of a packet.
+mod offset {
+    pub const LEFT_BUTTON: usize = 0;
+    pub const RIGHT_BUTTON: usize = 1;
+    pub const MIDDLE_BUTTON: usize = 2;
+    /// Always set, used to synchronize on packets.
+    pub const ALWAYS_ONE: usize = 3;
+    pub const X_SIGN: usize = 4;
+    pub const Y_SIGN: usize = 5;
+    pub const X_OVERFLOW: usize = 6;
+    pub const Y_OVERFLOW: usize = 7;
+    /// Offset of the fourth button in the fourth byte of 5 buttons mice.
+    pub const FOURTH_BUTTON: usize = 4;
+    /// Offset of the fifth button in the fourth byte of 5 buttons mice.
+    pub const FIFTH_BUTTON: usize = 5;
+}
+
+/// The kind of mouse, identified by its device ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseKind {
+    /// A 3 buttons mouse without wheel, sending 3-byte packets.
+    Standard,
+    /// A mouse with a wheel, sending 4-byte packets.
+    Wheel,
+    /// A 5 buttons mouse with a wheel, sending 4-byte packets.
+    FiveButtons,
+}
+
+impl MouseKind {
+    /// Get the size of the packets sent by the mouse.
+    pub fn packet_size(&self) -> usize {
+        match self {
+            MouseKind::Standard => 3,
+            MouseKind::Wheel | MouseKind::FiveButtons => 4,
+        }
+    }
+}
+
+impl fmt::Display for MouseKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MouseKind::Standard => "standard PS/2 mouse",
+                MouseKind::Wheel => "wheel mouse",
+                MouseKind::FiveButtons => "5 buttons wheel mouse",
+            }
+        )
+    }
+}
+
+/// The state of the mouse buttons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MouseButtons {
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+    pub fourth: bool,
+    pub fifth: bool,
+}
+
+impl MouseButtons {
+    /// Get the state of buttons with every button released.
+    pub const fn const_default() -> Self {
+        MouseButtons {
+            left: false,
+            right: false,
+            middle: false,
+            fourth: false,
+            fifth: false,
+        }
+    }
+
+    /// Get the buttons whose state differs.
+    pub fn changes(&self, other: &MouseButtons) -> MouseButtons {
+        MouseButtons {
+            left: self.left != other.left,
+            right: self.right != other.right,
+            middle: self.middle != other.middle,
+            fourth: self.fourth != other.fourth,
+            fifth: self.fifth != other.fifth,
+        }
+    }
+}
+
+/// A movement or button change of the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// Horizontal movement, positive to the right.
+    pub dx: i16,
+    /// Vertical movement, positive upwards.
+    pub dy: i16,
+    /// Wheel movement, positive downwards.
+    pub wheel: i8,
+    /// The state of the buttons.
+    pub buttons: MouseButtons,
+    /// The buttons pressed or released since the previous packet.
+    pub changed: MouseButtons,
+}
+
+impl fmt::Display for MouseEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Move ({}, {}) Wheel {} Buttons {:?}",
+            self.dx, self.dy, self.wheel, self.buttons
+        )
+    }
+}
+
+/// A state machine assembling bytes into packets.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketDecoder {
+    kind: MouseKind,
+    packet: [u8; 4],
+    length: usize,
+    buttons: MouseButtons,
+}
+
+impl PacketDecoder {
+    /// Create a decoder for a kind of mouse.
+    pub const fn new(kind: MouseKind) -> Self {
+        PacketDecoder {
+            kind,
+            packet: [0; 4],
+            length: 0,
+            buttons: MouseButtons::const_default(),
+        }
+    }
+
+    /// Feed a byte received from the mouse.
+    ///
+    /// Returns the event completed by the byte, if any.
+    pub fn feed(&mut self, byte: u8) -> Option<MouseEvent> {
+        // A first byte without its always set bit means a byte was lost,
+        // skip bytes until the next packet.
+        if self.length == 0 && !byte.get_bit(offset::ALWAYS_ONE) {
+            return None;
+        }
+        self.packet[self.length] = byte;
+        self.length += 1;
+        if self.length < self.kind.packet_size() {
+            return None;
+        }
+        self.length = 0;
+        Some(self.decode())
+    }
+
+    fn decode(&mut self) -> MouseEvent {
+        let [flags, x, y, extra] = self.packet;
+        let movement = |value: u8, sign: usize, overflow: usize| -> i16 {
+            // The movement is meaningless on overflow.
+            if flags.get_bit(overflow) {
+                0
+            } else if flags.get_bit(sign) {
+                i16::from(value) - 0x100
+            } else {
+                i16::from(value)
+            }
+        };
+        let (wheel, fourth, fifth) = match self.kind {
+            MouseKind::Standard => (0, false, false),
+            MouseKind::Wheel => (extra as i8, false, false),
+            // The wheel movement is a 4 bits signed value.
+            MouseKind::FiveButtons => (
+                ((extra << 4) as i8) >> 4,
+                extra.get_bit(offset::FOURTH_BUTTON),
+                extra.get_bit(offset::FIFTH_BUTTON),
+            ),
+        };
+        let buttons = MouseButtons {
+            left: flags.get_bit(offset::LEFT_BUTTON),
+            right: flags.get_bit(offset::RIGHT_BUTTON),
+            middle: flags.get_bit(offset::MIDDLE_BUTTON),
+            fourth,
+            fifth,
+        };
+        let changed = buttons.changes(&self.buttons);
+        self.buttons = buttons;
+        MouseEvent {
+            dx: movement(x, offset::X_SIGN, offset::X_OVERFLOW),
+            dy: movement(y, offset::Y_SIGN, offset::Y_OVERFLOW),
+            wheel,
+            buttons,
+            changed,
+        }
+    }
+}
+
+static DECODER: IrqSafeSpinLock<Option<PacketDecoder>> = IrqSafeSpinLock::new(None);
+static EVENTS: RingBuffer<MouseEvent, EVENT_QUEUE_SIZE> = RingBuffer::new();
+
+/// Send the magic sample rate sequence enabling an extension, then read the
+/// resulting device ID.
+fn knock(controller: &Ps2Controller, rates: [u8; 3]) -> Result<u8, Ps2Error> {
+    for rate in rates {
+        controller.send_device_command(Ps2Port::Second, command::SET_SAMPLE_RATE)?;
+        controller.send_device_command(Ps2Port::Second, rate)?;
+    }
+    controller.send_device_command(Ps2Port::Second, command::GET_DEVICE_ID)?;
+    controller.read_data()
+}
+
+/// Identify the mouse on the second port, enable its extensions and start
+/// its reporting.
+///
+/// # Note
+///
+/// The second port must be enabled with its interrupt disabled.
+pub fn setup(controller: &Ps2Controller) -> Result<MouseKind, Ps2Error> {
+    controller.send_device_command(Ps2Port::Second, command::SET_DEFAULTS)?;
+    let kind = match knock(controller, [200, 100, 80])? {
+        3 => match knock(controller, [200, 200, 80])? {
+            4 => MouseKind::FiveButtons,
+            _ => MouseKind::Wheel,
+        },
+        _ => MouseKind::Standard,
+    };
+    *DECODER.lock() = Some(PacketDecoder::new(kind));
+    controller.send_device_command(Ps2Port::Second, command::ENABLE_REPORTING)?;
+    Ok(kind)
+}
+
+/// Process the byte sent by the mouse, called by the mouse interrupt.
+pub fn handle_interrupt() {
+    let byte = super::CONTROLLER.read_data_unchecked();
+    let event = DECODER
+        .lock()
+        .as_mut()
+        .and_then(|decoder| decoder.feed(byte));
+    if let Some(event) = event {
+        // Events are dropped when nobody reads them.
+        EVENTS.push(event).ok();
+    }
+}
+
+/// Get the oldest mouse event, if any.
+pub fn read_event() -> Option<MouseEvent> {
+    EVENTS.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn standard_packet() {
+        let mut decoder = PacketDecoder::new(MouseKind::Standard);
+        assert_eq!(decoder.feed(0b0011_1001), None);
+        assert_eq!(decoder.feed(0xFE), None);
+        let event = decoder.feed(0x05).unwrap();
+        assert_eq!((event.dx, event.dy), (-2, -251));
+        assert!(event.buttons.left && event.changed.left);
+        let event = [0b0000_1000, 3, 4]
+            .into_iter()
+            .find_map(|byte| decoder.feed(byte))
+            .unwrap();
+        assert_eq!((event.dx, event.dy), (3, 4));
+        assert!(!event.buttons.left && event.changed.left);
+    }
+
+    #[test_case]
+    fn resynchronization() {
+        let mut decoder = PacketDecoder::new(MouseKind::Standard);
+        // A lost first byte, the following ones are skipped until a valid
+        // first byte.
+        assert_eq!(decoder.feed(0x05), None);
+        assert_eq!(decoder.feed(0x00), None);
+        assert!([0b0000_1010, 1, 1]
+            .into_iter()
+            .find_map(|byte| decoder.feed(byte))
+            .is_some_and(|event| event.buttons.right));
+    }
+
+    #[test_case]
+    fn wheel_packets() {
+        let mut decoder = PacketDecoder::new(MouseKind::Wheel);
+        let event = [0b0000_1000, 0, 0, 0xFF]
+            .into_iter()
+            .find_map(|byte| decoder.feed(byte))
+            .unwrap();
+        assert_eq!(event.wheel, -1);
+        let mut decoder = PacketDecoder::new(MouseKind::FiveButtons);
+        let event = [0b0000_1000, 0, 0, 0b0001_0001]
+            .into_iter()
+            .find_map(|byte| decoder.feed(byte))
+            .unwrap();
+        assert_eq!(event.wheel, 1);
+        assert!(event.buttons.fourth && !event.buttons.fifth);
+    }
+}
@@ -4,4 +4,5 @@ use log::trace;
 pub fn setup() {
     trace!("Setting up interrupts");
     interrupts::setup();
+    crate::serial::set_buffered_transmit(true);
 }
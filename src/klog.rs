@@ -5,44 +5,64 @@ use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
 #[cfg(feature = "serial_log")]
 mod serial_logger {
     use crate::serial::Serial;
-    /// An option containing the default serial to use for communication.
-    static mut DEFAULT: Option<Serial> = None;
+    use crate::sync::{IrqSafeSpinLock, IrqSafeSpinLockGuard, Lazy};
 
-    /// Retrieve a mutable reference to the default serial.
+    /// The default serial to use for communication.
+    static DEFAULT: Lazy<IrqSafeSpinLock<Serial>> =
+        Lazy::new(|| IrqSafeSpinLock::new(Serial::default()));
+
+    /// Lock the default serial.
     ///
     /// # Note
     /// This function will initialize the default serial during it's first call.
-    pub fn default() -> &'static mut Serial {
-        unsafe {
-            if DEFAULT.is_none() {
-                DEFAULT = Some(Serial::default());
-            }
-            DEFAULT.as_mut().unwrap()
-        }
+    pub fn default() -> IrqSafeSpinLockGuard<'static, Serial> {
+        DEFAULT.lock()
+    }
+
+    /// Release the default serial lock, see [`super::force_unlock`].
+    pub unsafe fn force_unlock() {
+        DEFAULT.force_unlock();
     }
 }
 
 #[cfg(feature = "vga_log")]
 mod vga_logger {
+    use crate::sync::{IrqSafeSpinLock, IrqSafeSpinLockGuard, Lazy};
     use crate::vga::text::Writer;
 
-    /// An option containing the default vga writer to use for communication.
-    static mut DEFAULT: Option<Writer> = None;
+    /// The default vga writer to use for communication.
+    static DEFAULT: Lazy<IrqSafeSpinLock<Writer>> =
+        Lazy::new(|| IrqSafeSpinLock::new(Writer::default()));
 
-    /// Retrieve a mutable reference to the default vga writer
+    /// Lock the default vga writer.
     ///
     /// # Note
-    /// This function will initialize the default serial during it's first call.
-    pub fn default() -> &'static mut Writer {
-        unsafe {
-            if DEFAULT.is_none() {
-                DEFAULT = Some(Writer::default());
-            }
-            DEFAULT.as_mut().unwrap()
-        }
+    /// This function will initialize the default writer during it's first
+    /// call.
+    pub fn default() -> IrqSafeSpinLockGuard<'static, Writer> {
+        DEFAULT.lock()
+    }
+
+    /// Release the default vga writer lock, see [`super::force_unlock`].
+    pub unsafe fn force_unlock() {
+        DEFAULT.force_unlock();
     }
 }
 
+/// Release the locks of the default outputs, so that a panic raised while
+/// printing can still be reported instead of spinning forever.
+///
+/// # Safety
+///
+/// The interrupted printing code must never resume, this is meant for the
+/// panic handlers.
+pub unsafe fn force_unlock() {
+    #[cfg(feature = "serial_log")]
+    serial_logger::force_unlock();
+    #[cfg(feature = "vga_log")]
+    vga_logger::force_unlock();
+}
+
 /// Print a precompiled format string and it's arguments to the default serial
 /// output.
 pub fn serial_print(args: fmt::Arguments) {
@@ -55,6 +75,13 @@ pub fn vga_print(args: fmt::Arguments) {
     vga_logger::default().write_fmt(args).ok();
 }
 
+/// Lock the writer of the default vga output, e.g. to change its colors or
+/// write a status line.
+#[cfg(feature = "vga_log")]
+pub fn vga_writer() -> crate::sync::IrqSafeSpinLockGuard<'static, crate::vga::text::Writer> {
+    vga_logger::default()
+}
+
 pub fn print_fmt(args: fmt::Arguments) {
     #[cfg(feature = "serial_log")]
     serial_print(args);
@@ -3,37 +3,93 @@
 #![feature(custom_test_frameworks)]
 #![feature(abi_x86_interrupt)]
 #![feature(const_trait_impl)]
+#![cfg_attr(target_arch = "x86_64", feature(alloc_error_handler))]
 #![test_runner(crate::test::runner)]
 #![reexport_test_harness_main = "test_main"]
 
+#[cfg(target_arch = "x86_64")]
+extern crate alloc;
+
+pub mod drivers;
 mod interrupts;
-mod mm;
+#[cfg(target_arch = "x86_64")]
+pub mod mm;
 pub mod vga;
 #[macro_use]
 pub mod klog;
+#[cfg(target_arch = "x86_64")]
+pub mod debug;
 pub mod qemu;
 pub mod serial;
+#[cfg(target_arch = "x86_64")]
+pub mod shell;
+pub mod sync;
 pub mod test;
+pub mod time;
 pub mod utils;
 #[macro_use]
 pub mod arch;
 
+#[cfg(all(test, target_arch = "x86_64"))]
+use bootloader::entry_point;
+#[cfg(target_arch = "x86_64")]
+use bootloader::BootInfo;
+
 #[cfg(test)]
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     test::panic_handler(info)
 }
 
-/// Test specific start function.
+#[cfg(all(test, target_arch = "x86_64"))]
+entry_point!(test_bootloader_main);
+
+#[cfg(all(test, target_arch = "x86_64"))]
+fn test_bootloader_main(_boot_info: &'static BootInfo) -> ! {
+    test_kernel_main()
+}
+
+#[cfg(all(test, target_arch = "x86"))]
+multiboot_entry!(test_kernel_main);
+
+/// Test specific entry point.
 #[cfg(test)]
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+fn test_kernel_main() -> ! {
     klog::init().ok();
     test_main();
     arch::endless();
 }
 
+#[cfg(target_arch = "x86_64")]
+pub fn setup(boot_info: &'static BootInfo) {
+    mm::setup(boot_info);
+    log_serial_ports();
+    setup_ps2();
+    interrupts::setup();
+}
+
+/// Set up the 32 bits kernel, memory management is limited to the flat
+/// segmentation model as paging is not enabled.
+#[cfg(target_arch = "x86")]
 pub fn setup() {
-    mm::setup();
+    arch::mm::setup(0);
+    log_serial_ports();
+    setup_ps2();
     interrupts::setup();
 }
+
+/// Set up the PS/2 controller, the kernel keeps running without keyboard if
+/// it fails.
+fn setup_ps2() {
+    if let Err(error) = drivers::ps2::setup() {
+        log::warn!("{}", error);
+    }
+}
+
+/// Log every working COM port, probing them before serial output becomes
+/// interrupt-driven.
+fn log_serial_ports() {
+    for uart in serial::enumerate() {
+        log::info!("{}", uart);
+    }
+}
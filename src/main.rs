@@ -4,8 +4,9 @@
 #![test_runner(flint::test::runner)]
 #![reexport_test_harness_main = "test_main"]
 
+#[cfg(target_arch = "x86_64")]
+use bootloader::{entry_point, BootInfo};
 use core::panic::PanicInfo;
-use flint::arch::endless;
 use flint::klog;
 
 #[test_case]
@@ -16,8 +17,14 @@ fn trivial_assertion() {
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    flint::serial::set_buffered_transmit(false);
+    // The panic may come from code holding an output lock, which never
+    // resumes.
+    unsafe { klog::force_unlock() };
     log::error!("Kernel Panic!:\n{}", info);
-    endless();
+    #[cfg(target_arch = "x86_64")]
+    flint::debug::backtrace::print_panic_backtrace();
+    flint::arch::endless();
 }
 
 #[cfg(test)]
@@ -26,8 +33,26 @@ fn panic(info: &PanicInfo) -> ! {
     flint::test::panic_handler(info)
 }
 
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+#[cfg(target_arch = "x86_64")]
+entry_point!(kernel_main);
+
+#[cfg(target_arch = "x86_64")]
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    klog::init().ok();
+
+    #[cfg(test)]
+    test_main();
+
+    flint::setup(boot_info);
+
+    flint::shell::run();
+}
+
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(kernel_main);
+
+#[cfg(target_arch = "x86")]
+fn kernel_main() -> ! {
     klog::init().ok();
 
     #[cfg(test)]
@@ -35,5 +60,5 @@ pub extern "C" fn _start() -> ! {
 
     flint::setup();
 
-    endless();
+    flint::arch::endless();
 }
@@ -1,7 +1,38 @@
 use crate::arch::mm;
-use log::trace;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use bootloader::BootInfo;
+use log::{info, trace};
 
-pub fn setup() {
+pub mod frame;
+pub mod heap;
+
+/// Log the amount of usable, kernel and reserved memory described by the
+/// bootloader's memory map.
+fn report(memory_map: &MemoryMap) {
+    let (mut usable, mut kernel, mut reserved) = (0, 0, 0);
+    for region in memory_map.iter() {
+        let size = region.range.end_addr() - region.range.start_addr();
+        match region.region_type {
+            MemoryRegionType::Usable => usable += size,
+            MemoryRegionType::Kernel | MemoryRegionType::KernelStack => kernel += size,
+            _ => reserved += size,
+        }
+        trace!(
+            "{:#X}-{:#X}: {:?}",
+            region.range.start_addr(),
+            region.range.end_addr(),
+            region.region_type
+        );
+    }
+    info!("Usable memory: {} KiB", usable / 1024);
+    info!("Kernel memory: {} KiB", kernel / 1024);
+    info!("Reserved memory: {} KiB", reserved / 1024);
+}
+
+pub fn setup(boot_info: &'static BootInfo) {
     trace!("Setting up memory");
-    mm::setup();
+    report(&boot_info.memory_map);
+    frame::setup(&boot_info.memory_map);
+    mm::setup(boot_info.physical_memory_offset);
+    heap::setup().expect("Cannot set up the kernel heap");
 }
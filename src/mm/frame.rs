@@ -0,0 +1,254 @@
+//! A module containing the physical frame allocator built from the memory map
+//! handed over by the bootloader.
+use crate::arch::ia32::address::PhysicalAddress;
+use crate::sync::{IrqSafeSpinLock, IrqSafeSpinLockGuard};
+use crate::utils::bitfield::*;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use log::trace;
+
+/// Size in bytes of a physical frame.
+pub const FRAME_SIZE: u64 = 4096;
+
+/// Amount of physical memory tracked by the kernel frame allocator, frames
+/// above this limit are never handed out.
+const MAX_PHYSICAL_MEMORY: u64 = 4 << 30;
+
+/// Number of bitmap words required to track [`MAX_PHYSICAL_MEMORY`].
+const KERNEL_BITMAP_LEN: usize = (MAX_PHYSICAL_MEMORY / FRAME_SIZE / 64) as usize;
+
+/// Common interface of every physical frame allocator.
+pub trait FrameAllocator {
+    /// Allocate a 4 KiB physical frame and return its base address, or `None`
+    /// if no frame is available anymore.
+    fn allocate(&mut self) -> Option<PhysicalAddress>;
+
+    /// Give a previously allocated frame back to the allocator.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The base address of the frame to release.
+    fn free(&mut self, frame: PhysicalAddress);
+}
+
+/// A frame allocator keeping track of `N * 64` frames in a bitmap, a set bit
+/// meaning the frame is unavailable.
+pub struct BitmapFrameAllocator<const N: usize> {
+    /// One bit per frame, set when the frame is either allocated, reserved or
+    /// not backed by usable memory.
+    bitmap: [u64; N],
+    /// Index of the bitmap word where the next allocation search starts.
+    next: usize,
+}
+
+impl<const N: usize> BitmapFrameAllocator<N> {
+    /// Number of frames this allocator is able to track.
+    pub const FRAME_COUNT: usize = N * 64;
+
+    /// Create a new [`BitmapFrameAllocator`] where every frame is unavailable.
+    pub const fn new() -> Self {
+        Self {
+            bitmap: [!0; N],
+            next: 0,
+        }
+    }
+
+    /// Get the index of the frame containing the given address, `None` if the
+    /// address is out of the allocator's reach.
+    fn frame_index(address: u64) -> Option<usize> {
+        let index = (address / FRAME_SIZE) as usize;
+        (index < Self::FRAME_COUNT).then_some(index)
+    }
+
+    /// Whether the frame at the given index is unavailable.
+    fn is_used(&self, index: usize) -> bool {
+        self.bitmap[index / 64].get_bit(index % 64)
+    }
+
+    /// Change the state of the frame at the given index.
+    fn set_used(&mut self, index: usize, used: bool) {
+        self.bitmap[index / 64] = self.bitmap[index / 64].set_bit(index % 64, used);
+    }
+
+    /// Change the state of a range of frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The index of the first frame.
+    /// * `end` - The excluded index of the last frame.
+    /// * `used` - The new state of the frames.
+    fn set_range(&mut self, start: u64, end: u64, used: bool) {
+        for index in start..end.min(Self::FRAME_COUNT as u64) {
+            self.set_used(index as usize, used);
+        }
+    }
+
+    /// Make every frame lying entirely within a range of physical memory
+    /// available for allocation, frames only partly in the range are left
+    /// as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start address of the range.
+    /// * `end` - The excluded end address of the range.
+    ///
+    /// # Note
+    ///
+    /// Frames beyond the allocator's capacity are silently ignored.
+    pub fn free_range(&mut self, start: PhysicalAddress, end: PhysicalAddress) {
+        let start = u64::from(start).div_ceil(FRAME_SIZE);
+        let end = u64::from(end) / FRAME_SIZE;
+        self.set_range(start, end, false);
+        self.next = 0;
+    }
+
+    /// Mark every frame intersecting a range of physical memory as
+    /// unavailable so it will never be handed out.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start address of the range.
+    /// * `end` - The excluded end address of the range.
+    pub fn reserve_range(&mut self, start: PhysicalAddress, end: PhysicalAddress) {
+        let start = u64::from(start) / FRAME_SIZE;
+        let end = u64::from(end).div_ceil(FRAME_SIZE);
+        self.set_range(start, end, true);
+    }
+
+    /// Return the number of frames that can still be allocated.
+    pub fn free_frames(&self) -> usize {
+        self.bitmap
+            .iter()
+            .map(|word| word.count_zeros() as usize)
+            .sum()
+    }
+}
+
+impl<const N: usize> Default for BitmapFrameAllocator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FrameAllocator for BitmapFrameAllocator<N> {
+    fn allocate(&mut self) -> Option<PhysicalAddress> {
+        let word = (self.next..N).find(|&word| self.bitmap[word] != !0)?;
+        let index = word * 64 + self.bitmap[word].trailing_ones() as usize;
+        self.set_used(index, true);
+        self.next = word;
+        Some(PhysicalAddress::new(index as u64 * FRAME_SIZE))
+    }
+
+    /// # Panics
+    ///
+    /// This method will panic if the address is not frame aligned, out of the
+    /// allocator's reach or if the frame is not currently in use.
+    fn free(&mut self, frame: PhysicalAddress) {
+        let address = u64::from(frame);
+        assert!(address % FRAME_SIZE == 0, "Unaligned frame {}", frame);
+        let index = Self::frame_index(address).expect("Frame out of the allocator's reach");
+        assert!(self.is_used(index), "Double free of frame {}", frame);
+        self.set_used(index, false);
+        self.next = self.next.min(index / 64);
+    }
+}
+
+/// The frame allocator type used by the kernel.
+pub type KernelFrameAllocator = BitmapFrameAllocator<KERNEL_BITMAP_LEN>;
+
+/// The kernel physical frame allocator, filled from the boot memory map.
+static FRAME_ALLOCATOR: IrqSafeSpinLock<KernelFrameAllocator> =
+    IrqSafeSpinLock::new(KernelFrameAllocator::new());
+
+/// Lock the kernel frame allocator.
+pub fn allocator() -> IrqSafeSpinLockGuard<'static, KernelFrameAllocator> {
+    FRAME_ALLOCATOR.lock()
+}
+
+/// Make every usable region of the memory map available to the kernel frame
+/// allocator.
+///
+/// # Arguments
+///
+/// * `memory_map` - The memory map handed over by the bootloader.
+pub fn setup(memory_map: &MemoryMap) {
+    trace!("Setting up frame allocator...");
+    let mut allocator = allocator();
+    for region in memory_map
+        .iter()
+        .filter(|region| region.region_type == MemoryRegionType::Usable)
+    {
+        allocator.free_range(
+            PhysicalAddress::new(region.range.start_addr()),
+            PhysicalAddress::new(region.range.end_addr()),
+        );
+    }
+    trace!("{} frames available", allocator.free_frames());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(index: u64) -> PhysicalAddress {
+        PhysicalAddress::new(index * FRAME_SIZE)
+    }
+
+    #[test_case]
+    fn new_is_empty() {
+        let mut allocator = BitmapFrameAllocator::<1>::new();
+        assert_eq!(allocator.free_frames(), 0);
+        assert!(allocator.allocate().is_none());
+    }
+
+    #[test_case]
+    fn allocate_lowest() {
+        let mut allocator = BitmapFrameAllocator::<2>::new();
+        allocator.free_range(frame(70), frame(72));
+        assert_eq!(allocator.allocate(), Some(frame(70)));
+        assert_eq!(allocator.allocate(), Some(frame(71)));
+        assert!(allocator.allocate().is_none());
+    }
+
+    #[test_case]
+    fn free_range_unaligned() {
+        let mut allocator = BitmapFrameAllocator::<1>::new();
+        // No whole frame in the range.
+        allocator.free_range(PhysicalAddress::new(0x1800), PhysicalAddress::new(0x2800));
+        assert_eq!(allocator.free_frames(), 0);
+        allocator.free_range(PhysicalAddress::new(0x1800), PhysicalAddress::new(0x3800));
+        assert_eq!(allocator.allocate(), Some(frame(2)));
+        assert!(allocator.allocate().is_none());
+    }
+
+    #[test_case]
+    fn reserve_range_unaligned() {
+        let mut allocator = BitmapFrameAllocator::<1>::new();
+        allocator.free_range(frame(0), frame(4));
+        // Every frame intersecting the range is reserved.
+        allocator.reserve_range(PhysicalAddress::new(0x1800), PhysicalAddress::new(0x2800));
+        assert_eq!(allocator.free_frames(), 2);
+        assert_eq!(allocator.allocate(), Some(frame(0)));
+        assert_eq!(allocator.allocate(), Some(frame(3)));
+    }
+
+    #[test_case]
+    fn reserve_range() {
+        let mut allocator = BitmapFrameAllocator::<1>::new();
+        allocator.free_range(frame(0), frame(4));
+        allocator.reserve_range(frame(0), frame(3));
+        assert_eq!(allocator.allocate(), Some(frame(3)));
+        assert!(allocator.allocate().is_none());
+    }
+
+    #[test_case]
+    fn free_and_reallocate() {
+        let mut allocator = BitmapFrameAllocator::<1>::new();
+        allocator.free_range(frame(0), frame(64));
+        let first = allocator.allocate().unwrap();
+        let second = allocator.allocate().unwrap();
+        allocator.free(first);
+        assert_eq!(allocator.free_frames(), 63);
+        assert_eq!(allocator.allocate(), Some(first));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,132 @@
+//! A module containing the kernel heap backing the `alloc` crate.
+//!
+//! The allocation strategy is pluggable through the [`HeapAllocator`] trait,
+//! the fixed size block allocator is used unless the `linked_list_heap`
+//! feature is enabled.
+use crate::arch::ia32::address::{PhysicalAddress, VirtualAddress};
+use crate::arch::ia32e::mm::paging::{flags, AddressSpace, PageFlags, PageSize, PagingError};
+use crate::mm::frame::{self, FrameAllocator, FRAME_SIZE};
+use crate::sync::IrqSafeSpinLock;
+use core::alloc::{GlobalAlloc, Layout};
+use log::{error, trace};
+
+pub mod fixed_size_block;
+pub mod linked_list;
+
+/// Virtual address of the kernel heap.
+pub const HEAP_START: u64 = 0x4444_4444_0000;
+/// Size in bytes of the kernel heap.
+pub const HEAP_SIZE: u64 = 1024 * 1024;
+/// Virtual address of a page the kernel never maps, left to tests mapping a
+/// page of their own. It must stay clear of the heap and of the APIC
+/// registers mapped at `apic::LOCAL_APIC_ADDRESS`.
+pub const TEST_PAGE_ADDRESS: u64 = 0x6666_6666_0000;
+
+/// An allocation strategy managing a single memory region.
+pub trait HeapAllocator {
+    /// Give the allocator the memory region it manages.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start address of the region.
+    /// * `size` - The size in bytes of the region.
+    ///
+    /// # Safety
+    ///
+    /// The region must be mapped, unused and should be given only once.
+    unsafe fn init(&mut self, start: usize, size: usize);
+
+    /// Allocate memory fitting the given layout, returning a null pointer on
+    /// failure.
+    fn allocate(&mut self, layout: Layout) -> *mut u8;
+
+    /// Give back memory previously obtained with [`HeapAllocator::allocate`].
+    ///
+    /// # Safety
+    ///
+    /// The pointer must come from this allocator with the same layout.
+    unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout);
+}
+
+/// A [`HeapAllocator`] behind a lock so it can be used as the global
+/// allocator, interrupt handlers are allowed to allocate.
+pub struct Heap<A: HeapAllocator> {
+    allocator: IrqSafeSpinLock<A>,
+}
+
+impl<A: HeapAllocator> Heap<A> {
+    /// Wrap a [`HeapAllocator`].
+    pub const fn new(allocator: A) -> Self {
+        Heap {
+            allocator: IrqSafeSpinLock::new(allocator),
+        }
+    }
+
+    /// Run a function with exclusive access to the inner allocator.
+    fn with<R>(&self, f: impl FnOnce(&mut A) -> R) -> R {
+        f(&mut self.allocator.lock())
+    }
+}
+
+unsafe impl<A: HeapAllocator> GlobalAlloc for Heap<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with(|allocator| allocator.allocate(layout))
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.with(|allocator| allocator.deallocate(ptr, layout))
+    }
+}
+
+#[cfg(not(feature = "linked_list_heap"))]
+type Strategy = fixed_size_block::FixedSizeBlockAllocator;
+#[cfg(feature = "linked_list_heap")]
+type Strategy = linked_list::LinkedListAllocator;
+
+#[global_allocator]
+static ALLOCATOR: Heap<Strategy> = Heap::new(Strategy::new());
+
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    error!("Heap allocation failed: {:?}", layout);
+    panic!("Out of heap memory");
+}
+
+/// Align an address upwards.
+///
+/// # Arguments
+///
+/// * `address` - The address to align.
+/// * `align` - The alignment, must be a power of 2.
+fn align_up(address: usize, align: usize) -> usize {
+    (address + align - 1) & !(align - 1)
+}
+
+/// Map the kernel heap region on newly allocated frames and hand it over to
+/// the global allocator.
+pub fn setup() -> Result<(), PagingError> {
+    trace!("Setting up kernel heap...");
+    let mut space = AddressSpace::active();
+    let mut allocator = frame::allocator();
+    for offset in (0..HEAP_SIZE).step_by(FRAME_SIZE as usize) {
+        let frame: PhysicalAddress = allocator
+            .allocate()
+            .ok_or(PagingError::FrameAllocationFailed)?;
+        // The frame was just allocated and the heap region is unused.
+        unsafe {
+            space.map(
+                VirtualAddress::new(HEAP_START + offset),
+                frame,
+                PageSize::Size4KiB,
+                PageFlags(flags::WRITABLE | flags::NO_EXECUTE),
+                &mut *allocator,
+            )?;
+        }
+    }
+
+    unsafe {
+        ALLOCATOR.with(|heap| heap.init(HEAP_START as usize, HEAP_SIZE as usize));
+    }
+    trace!("Kernel heap mapped at {:#X}", HEAP_START);
+    Ok(())
+}
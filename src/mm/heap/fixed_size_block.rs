@@ -0,0 +1,126 @@
+//! A module containing an allocator serving small allocations from lists of
+//! fixed size blocks, falling back on a [`LinkedListAllocator`] otherwise.
+use super::{linked_list::LinkedListAllocator, HeapAllocator};
+use core::alloc::Layout;
+use core::mem;
+
+/// The block sizes to use, every size is also used as the block alignment so
+/// they must be powers of 2.
+const BLOCK_SIZES: &[usize] = &[8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// A free block, stored at the start of the block itself.
+struct ListNode {
+    /// The next free block of the same size.
+    next: Option<&'static mut ListNode>,
+}
+
+/// An allocator keeping one list of free blocks per size of [`BLOCK_SIZES`].
+pub struct FixedSizeBlockAllocator {
+    /// Head of the free block list of every block size.
+    list_heads: [Option<&'static mut ListNode>; BLOCK_SIZES.len()],
+    /// Allocator used for new blocks and allocations bigger than the biggest
+    /// block size.
+    fallback: LinkedListAllocator,
+}
+
+impl FixedSizeBlockAllocator {
+    /// Create a new empty [`FixedSizeBlockAllocator`].
+    pub const fn new() -> Self {
+        const EMPTY: Option<&'static mut ListNode> = None;
+        FixedSizeBlockAllocator {
+            list_heads: [EMPTY; BLOCK_SIZES.len()],
+            fallback: LinkedListAllocator::new(),
+        }
+    }
+
+    /// Get the index in [`BLOCK_SIZES`] of the smallest block able to hold
+    /// the given layout, `None` if the layout is too big.
+    fn list_index(layout: &Layout) -> Option<usize> {
+        let required = layout.size().max(layout.align());
+        BLOCK_SIZES.iter().position(|&size| size >= required)
+    }
+}
+
+impl Default for FixedSizeBlockAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeapAllocator for FixedSizeBlockAllocator {
+    unsafe fn init(&mut self, start: usize, size: usize) {
+        self.fallback.init(start, size);
+    }
+
+    fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        let Some(index) = Self::list_index(&layout) else {
+            return self.fallback.allocate(layout);
+        };
+        match self.list_heads[index].take() {
+            Some(node) => {
+                self.list_heads[index] = node.next.take();
+                node as *mut ListNode as *mut u8
+            }
+            None => {
+                // No free block left, carve a new one from the fallback.
+                let size = BLOCK_SIZES[index];
+                self.fallback
+                    .allocate(Layout::from_size_align(size, size).unwrap())
+            }
+        }
+    }
+
+    unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+        let Some(index) = Self::list_index(&layout) else {
+            return self.fallback.deallocate(ptr, layout);
+        };
+        assert!(mem::size_of::<ListNode>() <= BLOCK_SIZES[index]);
+        assert!(mem::align_of::<ListNode>() <= BLOCK_SIZES[index]);
+        let node_ptr = ptr as *mut ListNode;
+        node_ptr.write(ListNode {
+            next: self.list_heads[index].take(),
+        });
+        self.list_heads[index] = Some(&mut *node_ptr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn block_index() {
+        let layout = Layout::from_size_align(3, 1).unwrap();
+        assert_eq!(FixedSizeBlockAllocator::list_index(&layout), Some(0));
+        let layout = Layout::from_size_align(8, 64).unwrap();
+        assert_eq!(FixedSizeBlockAllocator::list_index(&layout), Some(3));
+        let layout = Layout::from_size_align(4096, 8).unwrap();
+        assert_eq!(FixedSizeBlockAllocator::list_index(&layout), None);
+    }
+
+    #[test_case]
+    fn reuse_freed_block() {
+        let mut arena = [0_u64; 512];
+        let mut allocator = FixedSizeBlockAllocator::new();
+        unsafe { allocator.init(arena.as_mut_ptr() as usize, 4096) };
+
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let first = allocator.allocate(layout);
+        assert!(!first.is_null());
+        unsafe { allocator.deallocate(first, layout) };
+        assert_eq!(allocator.allocate(layout), first);
+    }
+
+    #[test_case]
+    fn fallback_allocation() {
+        let mut arena = [0_u64; 512];
+        let mut allocator = FixedSizeBlockAllocator::new();
+        unsafe { allocator.init(arena.as_mut_ptr() as usize, 4096) };
+
+        let layout = Layout::from_size_align(3000, 8).unwrap();
+        let big = allocator.allocate(layout);
+        assert!(!big.is_null());
+        unsafe { allocator.deallocate(big, layout) };
+        assert_eq!(allocator.allocate(layout), big);
+    }
+}
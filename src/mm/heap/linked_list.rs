@@ -0,0 +1,213 @@
+//! A module containing a first-fit allocator keeping the free memory regions
+//! in an address ordered linked list.
+use super::{align_up, HeapAllocator};
+use core::alloc::Layout;
+use core::{mem, ptr};
+
+/// A free memory region, stored at the start of the region itself.
+struct ListNode {
+    /// Size of the free region in bytes, including the node.
+    size: usize,
+    /// The next free region, at a higher address.
+    next: Option<&'static mut ListNode>,
+}
+
+impl ListNode {
+    const fn new(size: usize) -> Self {
+        ListNode { size, next: None }
+    }
+
+    fn start_address(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn end_address(&self) -> usize {
+        self.start_address() + self.size
+    }
+}
+
+/// A first-fit allocator merging adjacent free regions on deallocation.
+pub struct LinkedListAllocator {
+    /// Dummy node whose `next` field is the first free region.
+    head: ListNode,
+}
+
+impl LinkedListAllocator {
+    /// Create a new empty [`LinkedListAllocator`].
+    pub const fn new() -> Self {
+        LinkedListAllocator {
+            head: ListNode::new(0),
+        }
+    }
+
+    /// Smallest region the allocator is able to keep track of.
+    const fn min_size() -> usize {
+        mem::size_of::<ListNode>()
+    }
+
+    /// Adjust a layout so the allocated region is able to hold a [`ListNode`]
+    /// once freed.
+    fn size_align(layout: Layout) -> (usize, usize) {
+        let layout = layout
+            .align_to(mem::align_of::<ListNode>())
+            .expect("Adjusting alignment failed")
+            .pad_to_align();
+        (layout.size().max(Self::min_size()), layout.align())
+    }
+
+    /// Insert a free region in the list, merging it with its neighbours when
+    /// they are contiguous.
+    ///
+    /// # Safety
+    ///
+    /// The region must be unused and aligned for a [`ListNode`].
+    pub unsafe fn add_free_region(&mut self, address: usize, size: usize) {
+        assert_eq!(align_up(address, mem::align_of::<ListNode>()), address);
+        assert!(size >= Self::min_size());
+
+        let mut current = &mut self.head;
+        while let Some(ref next) = current.next {
+            if next.start_address() > address {
+                break;
+            }
+            current = current.next.as_mut().unwrap();
+        }
+
+        let mut node = ListNode::new(size);
+        node.next = current.next.take();
+        if let Some(next) = node.next.as_mut() {
+            if address + size == next.start_address() {
+                node.size += next.size;
+                node.next = next.next.take();
+            }
+        }
+
+        if current.size != 0 && current.end_address() == address {
+            current.size += node.size;
+            current.next = node.next;
+        } else {
+            let node_ptr = address as *mut ListNode;
+            node_ptr.write(node);
+            current.next = Some(&mut *node_ptr);
+        }
+    }
+
+    /// Check whether an allocation fits in a region, returning the
+    /// allocation's start address on success.
+    fn alloc_from_region(region: &ListNode, size: usize, align: usize) -> Result<usize, ()> {
+        let mut start = align_up(region.start_address(), align);
+        if start != region.start_address() && start - region.start_address() < Self::min_size() {
+            // The padding before the allocation must be able to hold a node.
+            start = align_up(region.start_address() + Self::min_size(), align);
+        }
+        let end = start.checked_add(size).ok_or(())?;
+        if end > region.end_address() {
+            return Err(());
+        }
+        let excess = region.end_address() - end;
+        if excess > 0 && excess < Self::min_size() {
+            // The rest of the region cannot hold a node.
+            return Err(());
+        }
+        Ok(start)
+    }
+
+    /// Remove the first free region fitting the allocation from the list.
+    fn find_region(&mut self, size: usize, align: usize) -> Option<(&'static mut ListNode, usize)> {
+        let mut current = &mut self.head;
+        while let Some(ref mut region) = current.next {
+            if let Ok(start) = Self::alloc_from_region(region, size, align) {
+                let next = region.next.take();
+                let found = current.next.take().unwrap();
+                current.next = next;
+                return Some((found, start));
+            }
+            current = current.next.as_mut().unwrap();
+        }
+        None
+    }
+}
+
+impl Default for LinkedListAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeapAllocator for LinkedListAllocator {
+    unsafe fn init(&mut self, start: usize, size: usize) {
+        self.add_free_region(start, size);
+    }
+
+    fn allocate(&mut self, layout: Layout) -> *mut u8 {
+        let (size, align) = Self::size_align(layout);
+        let Some((region, start)) = self.find_region(size, align) else {
+            return ptr::null_mut();
+        };
+
+        let (region_start, region_end) = (region.start_address(), region.end_address());
+        unsafe {
+            if start > region_start {
+                self.add_free_region(region_start, start - region_start);
+            }
+            if region_end > start + size {
+                self.add_free_region(start + size, region_end - (start + size));
+            }
+        }
+        start as *mut u8
+    }
+
+    unsafe fn deallocate(&mut self, ptr: *mut u8, layout: Layout) {
+        let (size, _) = Self::size_align(layout);
+        self.add_free_region(ptr as usize, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn allocate_and_merge() {
+        let mut arena = [0_u64; 128];
+        let mut allocator = LinkedListAllocator::new();
+        let start = arena.as_mut_ptr() as usize;
+        unsafe { allocator.init(start, 1024) };
+
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let first = allocator.allocate(layout);
+        let second = allocator.allocate(layout);
+        assert_eq!(first as usize, start);
+        assert_eq!(second as usize, start + 256);
+
+        unsafe {
+            allocator.deallocate(first, layout);
+            allocator.deallocate(second, layout);
+        }
+        let whole = allocator.allocate(Layout::from_size_align(1024, 8).unwrap());
+        assert_eq!(whole as usize, start);
+    }
+
+    #[test_case]
+    fn aligned_allocation() {
+        let mut arena = [0_u64; 128];
+        let mut allocator = LinkedListAllocator::new();
+        let start = arena.as_mut_ptr() as usize;
+        unsafe { allocator.init(start, 1024) };
+
+        allocator.allocate(Layout::from_size_align(16, 8).unwrap());
+        let aligned = allocator.allocate(Layout::from_size_align(64, 256).unwrap());
+        assert_eq!(aligned as usize % 256, 0);
+    }
+
+    #[test_case]
+    fn out_of_memory() {
+        let mut arena = [0_u64; 128];
+        let mut allocator = LinkedListAllocator::new();
+        let start = arena.as_mut_ptr() as usize;
+        unsafe { allocator.init(start, 1024) };
+
+        let layout = Layout::from_size_align(2048, 8).unwrap();
+        assert!(allocator.allocate(layout).is_null());
+    }
+}
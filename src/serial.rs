@@ -1,24 +1,42 @@
 use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 
+use crate::arch::ia32::interrupts::pic::{COM1_IRQ, COM2_IRQ};
+use crate::arch::ia32::{halt, pause};
+use crate::arch::interrupts::{are_enabled, without_interrupts};
 use crate::arch::io::register::{ReadRegister, WriteRegister};
+use crate::utils::ring_buffer::RingBuffer;
 use registers::{
     dlh::DivisorLatchHighByte,
     dll::DivisorLatchLowByte,
-    fcr::{self, FifoControl, FifoControlRegister},
+    fcr::{FifoControl, FifoControlRegister},
     ier::{self, InterruptEnable, InterruptEnableRegister},
-    iir::InterruptIdentificationRegister,
-    lcr::{self, LineControl, LineControlRegister},
+    iir::{
+        flags::{FifoStatus, InterruptEventType},
+        InterruptIdentificationRegister,
+    },
+    lcr::{LineControl, LineControlRegister},
     lsr::LineStatusRegister,
-    mcr::ModemControlRegister,
+    mcr::{self, ModemControl, ModemControlRegister},
     msr::ModemStatusRegister,
     rbr::ReceiverBuffer,
     sr::ScratchRegister,
     thr::TransmitterHoldingBuffer,
 };
 
+pub use config::{BaudRateError, SerialConfig};
+pub use line::{LineError, Reception, SerialStatistics};
+pub use modem::{ModemEvent, ModemLine};
+pub use probe::{enumerate, UartInfo, UartModel};
+
+pub mod config;
+pub mod line;
+pub mod modem;
+pub mod probe;
 pub mod registers;
 
-type ComPort = usize;
+/// UART IO port base address
+pub type ComPort = usize;
 
 /// UART IO port 1 address
 pub const COM1: ComPort = 0x3F8;
@@ -29,6 +47,81 @@ pub const COM3: ComPort = 0x3E8;
 /// UART IO port 4 address
 pub const COM4: ComPort = 0x2E8;
 
+/// Number of standard COM ports, from [`COM1`] to [`COM4`].
+pub const PORT_COUNT: usize = 4;
+
+/// Capacity of the receive buffer of every port.
+pub const RECEIVE_BUFFER_SIZE: usize = 256;
+
+/// Receptions classified by the interrupt handler of every port, indexed as
+/// [`COM1`] to [`COM4`].
+static RECEIVE_BUFFERS: [RingBuffer<Reception, RECEIVE_BUFFER_SIZE>; PORT_COUNT] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+
+/// Capacity of the transmit queue of every port.
+pub const TRANSMIT_BUFFER_SIZE: usize = 1024;
+
+/// Bytes waiting to be sent by the interrupt handler of every port, indexed
+/// as [`COM1`] to [`COM4`].
+static TRANSMIT_BUFFERS: [RingBuffer<u8, TRANSMIT_BUFFER_SIZE>; PORT_COUNT] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+
+/// Whether writes are queued for the interrupt handler instead of polling
+/// the UART.
+static BUFFERED_TRANSMIT: AtomicBool = AtomicBool::new(false);
+
+/// Switch between the interrupt-driven and the polling transmit paths.
+///
+/// # Arguments
+///
+/// * `enabled` - `true` once the serial IRQs are handled, `false` to go back
+///   to polling, during a panic for instance. Queued bytes are flushed when
+///   disabling.
+pub fn set_buffered_transmit(enabled: bool) {
+    BUFFERED_TRANSMIT.store(enabled, Ordering::Release);
+    if !enabled {
+        flush_all();
+    }
+}
+
+/// Synchronously send the bytes queued on every port.
+pub fn flush_all() {
+    for port in [COM1, COM2, COM3, COM4] {
+        let serial = Serial::new(port);
+        if serial
+            .transmit_buffer()
+            .is_some_and(|buffer| !buffer.is_empty())
+        {
+            serial.flush();
+        }
+    }
+}
+
+/// Service the pending interrupts of every port wired to an IRQ line.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA IRQ being serviced, [`COM1`] and [`COM3`] share IRQ4
+///   while [`COM2`] and [`COM4`] share IRQ3.
+pub fn handle_interrupt(irq: usize) {
+    let ports: &[ComPort] = match irq {
+        COM1_IRQ => &[COM1, COM3],
+        COM2_IRQ => &[COM2, COM4],
+        _ => &[],
+    };
+    for &port in ports {
+        Serial::new(port).handle_interrupt();
+    }
+}
+
 /// A structure representing an UART device accessible through a given IO port.
 pub struct Serial {
     /// The UART port address
@@ -36,35 +129,11 @@ pub struct Serial {
 }
 
 impl Default for Serial {
-    /// Return the default UART configuration which is:
-    /// - Port [`COM1`]
-    /// - Baud rate of 38400
-    /// - 8 bits word length
-    /// - No parity
-    /// - Interrupt trigger level of 14
+    /// Return the UART configured by [`SerialConfig::default`].
     fn default() -> Self {
-        let result = Serial::new(COM1);
-        result.set_baud_rate(38400);
-        unsafe {
-            // 8 bit length, no parity
-            result.line_control_register().write(LineControl(
-                lcr::flags::WordLengthBits::Eight as u8
-                    | lcr::flags::StopBit::OneStop as u8
-                    | lcr::flags::Parity::NoParity as u8,
-            ));
-            // Enable FIFO, clear, 14 bits
-            result.fifo_control_register().write(FifoControl(
-                fcr::flags::TriggerLevel16::Itl14 as u8
-                    | fcr::flags::ENABLE_FIFOS
-                    | fcr::flags::CLEAR_TRANSMIT_FIFO
-                    | fcr::flags::CLEAR_RECEIVE_FIFO,
-            ));
-            // Enable interrupts
-            result.interrupt_enable_register().write(InterruptEnable(
-                ier::flags::RECEIVED_DATA_AVAILABLE_INTERRUPT,
-            ));
-            result
-        }
+        SerialConfig::default()
+            .build()
+            .expect("Default serial configuration is valid")
     }
 }
 
@@ -79,11 +148,45 @@ impl Serial {
     }
 
     /// Set the transfer speed of an UART by setting it's DLL and DLH registers.
-    pub fn set_baud_rate(&self, baud_rate: usize) {
-        let dlv = 115200 / baud_rate;
+    ///
+    /// # Arguments
+    ///
+    /// * `baud_rate` - The desired baud rate, it must divide
+    ///   [`config::MAX_BAUD_RATE`].
+    pub fn set_baud_rate(&self, baud_rate: u32) -> Result<(), BaudRateError> {
+        self.set_divisor(config::divisor(baud_rate)?);
+        Ok(())
+    }
+
+    /// Write the divisor latch registers.
+    fn set_divisor(&self, divisor: u16) {
         unsafe {
-            self.divisor_latch_low_byte().write((dlv & 0xff) as u8);
-            self.divisor_latch_high_byte().write((dlv >> 8) as u8);
+            self.divisor_latch_low_byte().write((divisor & 0xff) as u8);
+            self.divisor_latch_high_byte().write((divisor >> 8) as u8);
+        }
+    }
+
+    /// Apply a validated configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `divisor` - The divisor latch value of the configured baud rate.
+    /// * `config` - The line settings.
+    fn configure(&self, divisor: u16, config: &SerialConfig) {
+        self.set_divisor(divisor);
+        unsafe {
+            self.line_control_register()
+                .write(LineControl(config.line_control()));
+            self.fifo_control_register()
+                .write(FifoControl(config.fifo_control()));
+            // Assert DTR and RTS, OUT2 connects the interrupt line to the PIC
+            self.model_control_register().write(ModemControl::from(
+                mcr::flags::DATA_TERMINAL_READY
+                    | mcr::flags::REQUEST_TO_SEND
+                    | mcr::flags::AUX_OUTPUT_2,
+            ));
+            self.interrupt_enable_register()
+                .write(InterruptEnable(config.get_interrupts()));
         }
     }
 
@@ -96,21 +199,110 @@ impl Serial {
         }
     }
 
-    /// Write a byte to the serial bus of a given Serial structure. Waits for
-    /// the UART to be ready before sending it.
+    /// Write a byte to the serial bus, waiting for the UART to be ready.
+    fn write_byte_polling(&self, byte: u8) {
+        crate::arch::spin_loop(|| !self.can_write() || !self.may_transmit());
+        unsafe {
+            self.transmitter_holding_buffer().write(byte);
+        }
+        self.count_transmitted();
+    }
+
+    /// Write a byte to the serial bus of a given Serial structure.
     ///
     /// # Arguments
     ///
     /// * `byte` - The value to write on the serial bus.
+    ///
+    /// # Note
+    ///
+    /// Once buffered transmit is enabled the byte is queued and sent by the
+    /// interrupt handler, the queue is drained synchronously when full.
+    /// Otherwise the function waits for the UART to be ready before sending
+    /// it.
     pub fn write_byte(&self, byte: u8) {
-        crate::arch::spin_loop(|| !self.can_write());
+        match self.transmit_buffer() {
+            Some(buffer) if BUFFERED_TRANSMIT.load(Ordering::Acquire) => {
+                without_interrupts(|| {
+                    while buffer.push(byte).is_err() {
+                        // Make room, the oldest byte is sent first.
+                        if let Some(oldest) = buffer.pop() {
+                            self.write_byte_polling(oldest);
+                        }
+                    }
+                    self.start_transmit();
+                });
+            }
+            _ => self.write_byte_polling(byte),
+        }
+    }
+
+    /// Synchronously send every queued byte and wait for the transmitter to
+    /// be empty.
+    pub fn flush(&self) {
+        without_interrupts(|| {
+            if let Some(buffer) = self.transmit_buffer() {
+                while let Some(byte) = buffer.pop() {
+                    self.write_byte_polling(byte);
+                }
+            }
+            crate::arch::spin_loop(|| !self.can_write());
+        });
+    }
+
+    /// Enable the transmitter holding register empty interrupt, it is raised
+    /// right away if the register is already empty.
+    fn start_transmit(&self) {
+        use ier::flags::TRANSMITTER_HOLDING_REGISTER_EMPTY_INTERRUPT;
         unsafe {
-            self.transmitter_holding_buffer().write(byte);
+            let enable = self.interrupt_enable_register().read();
+            if !enable.transmitter_holding_register_empty_interrupt() {
+                self.interrupt_enable_register().write(InterruptEnable(
+                    enable.0 | TRANSMITTER_HOLDING_REGISTER_EMPTY_INTERRUPT,
+                ));
+            }
         }
     }
 
-    /// Write a string to the data buffer of the [`Serial`]. This function will
-    /// wait for the UART to be ready before sending any byte.
+    /// Fill the transmitter FIFO from the queue, the interrupt is disabled
+    /// once the queue is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of bytes the empty FIFO can hold.
+    fn transmit(&self, depth: usize) {
+        // Without CTS, transmission resumes on the next modem status change.
+        if !self.may_transmit() {
+            self.stop_transmit();
+            return;
+        }
+        for _ in 0..depth {
+            match self.transmit_buffer().and_then(RingBuffer::pop) {
+                Some(byte) => {
+                    unsafe { self.transmitter_holding_buffer().write(byte) };
+                    self.count_transmitted();
+                }
+                None => {
+                    self.stop_transmit();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Disable the transmitter holding register empty interrupt.
+    fn stop_transmit(&self) {
+        use ier::flags::TRANSMITTER_HOLDING_REGISTER_EMPTY_INTERRUPT;
+        unsafe {
+            let enable = self.interrupt_enable_register().read();
+            self.interrupt_enable_register().write(InterruptEnable(
+                enable.0 & !TRANSMITTER_HOLDING_REGISTER_EMPTY_INTERRUPT,
+            ));
+        }
+    }
+
+    /// Write a string to the data buffer of the [`Serial`], see
+    /// [`Serial::write_byte`].
     ///
     /// # Arguments
     ///
@@ -127,6 +319,160 @@ impl Serial {
         }
     }
 
+    /// Get the buffer filled by the interrupt handler, if the port is one of
+    /// [`COM1`] to [`COM4`].
+    fn receive_buffer(&self) -> Option<&'static RingBuffer<Reception, RECEIVE_BUFFER_SIZE>> {
+        self.port_index().map(|index| &RECEIVE_BUFFERS[index])
+    }
+
+    /// Get the queue drained by the interrupt handler, if the port is one of
+    /// [`COM1`] to [`COM4`].
+    fn transmit_buffer(&self) -> Option<&'static RingBuffer<u8, TRANSMIT_BUFFER_SIZE>> {
+        self.port_index().map(|index| &TRANSMIT_BUFFERS[index])
+    }
+
+    /// Get the index of the port's per port state, if the port is one of
+    /// [`COM1`] to [`COM4`].
+    fn port_index(&self) -> Option<usize> {
+        match self.com_port {
+            COM1 => Some(0),
+            COM2 => Some(1),
+            COM3 => Some(2),
+            COM4 => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Read and classify the byte at the front of the receiver, if any.
+    fn receive_one(&self) -> Option<Reception> {
+        // Reading the line status clears its error bits, they apply to the
+        // byte read right after.
+        let status = unsafe { self.line_status_register().read() };
+        if !status.data_ready() {
+            return None;
+        }
+        let byte = unsafe { self.receiver_buffer().read() };
+        let reception = Reception::classify(&status, byte);
+        self.count_reception(&reception);
+        Some(reception)
+    }
+
+    /// Move every byte waiting in the UART to the receive buffer,
+    /// receptions are dropped when it is full.
+    fn receive(&self) {
+        while let Some(reception) = self.receive_one() {
+            if let Some(buffer) = self.receive_buffer() {
+                if buffer.push(reception).is_err() {
+                    self.count_dropped();
+                }
+                self.throttle_receive(buffer.len());
+            }
+        }
+    }
+
+    /// Service every pending interrupt of the UART, called from the IRQ
+    /// handler.
+    pub fn handle_interrupt(&self) {
+        loop {
+            let identification = unsafe { self.interrupt_identification_register().read() };
+            if !identification.interrupt_pending() {
+                break;
+            }
+            match identification.interrupt_event_type() {
+                // Line errors are reported with the byte they apply to.
+                InterruptEventType::ReceivedDataAvailable
+                | InterruptEventType::TimeoutInterruptPending
+                | InterruptEventType::ReceiverLineStatus => self.receive(),
+                // Reading the status register acknowledges the interrupt.
+                InterruptEventType::ModemStatus => {
+                    let status = unsafe { self.modem_status_register().read() };
+                    self.modem_status_changed(status.0);
+                }
+                // Reading the IIR acknowledged it.
+                InterruptEventType::TransmitterHoldingRegisterEmpty => {
+                    self.transmit(match identification.fifo_status() {
+                        FifoStatus::Enabled if identification.fifo_enabled() => 64,
+                        FifoStatus::Enabled => 16,
+                        _ => 1,
+                    })
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Get the next [`Reception`] without waiting.
+    ///
+    /// # Note
+    ///
+    /// Receptions buffered by the interrupt handler come first, then the
+    /// UART is polled so reading also works with interrupts disabled.
+    pub fn try_receive(&self) -> Option<Reception> {
+        if let Some(buffer) = self.receive_buffer() {
+            if let Some(reception) = buffer.pop() {
+                self.throttle_receive(buffer.len());
+                return Some(reception);
+            }
+        }
+        without_interrupts(|| self.receive_one())
+    }
+
+    /// Read a valid received byte, without waiting.
+    ///
+    /// # Note
+    ///
+    /// A byte following an overrun is returned, only the bytes before it
+    /// were lost. Bytes with a parity or framing error and breaks are
+    /// skipped, they are only visible through [`Serial::try_receive`] and
+    /// [`Serial::statistics`].
+    pub fn try_read_byte(&self) -> Option<u8> {
+        loop {
+            if let Some(byte) = self.try_receive()?.valid_byte() {
+                return Some(byte);
+            }
+        }
+    }
+
+    /// Read a received byte, waiting for one to arrive.
+    pub fn read_byte(&self) -> u8 {
+        loop {
+            if let Some(byte) = self.try_read_byte() {
+                return byte;
+            }
+            if are_enabled() {
+                halt();
+            } else {
+                pause();
+            }
+        }
+    }
+
+    /// Read a line, waiting for a carriage return or a line feed. Returns the
+    /// number of bytes written to the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer receiving the line.
+    ///
+    /// # Note
+    ///
+    /// The terminator is not stored, bytes past the buffer capacity are
+    /// discarded and backspace removes the previous byte. Nothing is echoed.
+    pub fn read_line(&self, buffer: &mut [u8]) -> usize {
+        let mut length = 0;
+        loop {
+            match self.read_byte() {
+                b'\r' | b'\n' => return length,
+                0x08 | 0x7F => length = length.saturating_sub(1),
+                byte if length < buffer.len() => {
+                    buffer[length] = byte;
+                    length += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Get a [`LineStatusRegister`] handle from the serial port.
     pub fn line_status_register(&self) -> LineStatusRegister {
         LineStatusRegister::from(self.com_port)
@@ -0,0 +1,261 @@
+//! A module containing the [`SerialConfig`] builder used to configure an
+//! UART's line settings.
+use super::registers::{
+    fcr::{self, flags::TriggerLevel16},
+    ier,
+    lcr::flags::{Parity, StopBit, WordLengthBits},
+};
+use super::{ComPort, Serial, COM1};
+use core::fmt;
+
+/// Frequency of the UART's baud rate generator input, divided by 16. It is
+/// the highest supported baud rate.
+pub const MAX_BAUD_RATE: u32 = 115200;
+
+/// Set of errors that may occur while computing a baud rate divisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaudRateError {
+    /// The baud rate is zero.
+    Zero,
+    /// The baud rate is higher than [`MAX_BAUD_RATE`].
+    TooHigh(u32),
+    /// The divisor of the baud rate does not fit in the divisor latch.
+    TooLow(u32),
+    /// The baud rate does not divide [`MAX_BAUD_RATE`], the UART would use a
+    /// different rate.
+    Inexact(u32),
+}
+
+impl fmt::Display for BaudRateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BaudRateError::Zero => write!(f, "Baud rate cannot be zero"),
+            BaudRateError::TooHigh(rate) => {
+                write!(f, "Baud rate {} is above {}", rate, MAX_BAUD_RATE)
+            }
+            BaudRateError::TooLow(rate) => write!(f, "Baud rate {} is too low", rate),
+            BaudRateError::Inexact(rate) => {
+                write!(f, "Baud rate {} does not divide {}", rate, MAX_BAUD_RATE)
+            }
+        }
+    }
+}
+
+/// Compute the divisor latch value of a baud rate.
+///
+/// # Arguments
+///
+/// * `baud_rate` - The desired baud rate, it must divide [`MAX_BAUD_RATE`].
+pub fn divisor(baud_rate: u32) -> Result<u16, BaudRateError> {
+    match baud_rate {
+        0 => Err(BaudRateError::Zero),
+        rate if rate > MAX_BAUD_RATE => Err(BaudRateError::TooHigh(rate)),
+        rate if (MAX_BAUD_RATE / rate) * rate != MAX_BAUD_RATE => Err(BaudRateError::Inexact(rate)),
+        rate => (MAX_BAUD_RATE / rate)
+            .try_into()
+            .map_err(|_| BaudRateError::TooLow(rate)),
+    }
+}
+
+/// A builder describing the line settings of an UART, applied by
+/// [`SerialConfig::build`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// The UART port address.
+    port: ComPort,
+    /// The transfer speed.
+    baud_rate: u32,
+    /// The number of data bits per character.
+    word_length: WordLengthBits,
+    /// The parity bit mode.
+    parity: Parity,
+    /// The number of stop bits.
+    stop_bits: StopBit,
+    /// The receive FIFO interrupt trigger level.
+    trigger_level: TriggerLevel16,
+    /// The enabled interrupts, a combination of [`ier::flags`] values.
+    interrupts: u8,
+    /// Whether RTS/CTS hardware flow control is used.
+    flow_control: bool,
+}
+
+impl SerialConfig {
+    /// Create the default configuration which is:
+    /// - Port [`COM1`]
+    /// - Baud rate of 38400
+    /// - 8 bits word length
+    /// - No parity
+    /// - One stop bit
+    /// - Interrupt trigger level of 14
+    /// - Received data available and line status interrupts enabled
+    /// - No flow control
+    pub const fn const_default() -> Self {
+        SerialConfig {
+            port: COM1,
+            baud_rate: 38400,
+            word_length: WordLengthBits::Eight,
+            parity: Parity::NoParity,
+            stop_bits: StopBit::OneStop,
+            trigger_level: TriggerLevel16::Itl14,
+            interrupts: ier::flags::RECEIVED_DATA_AVAILABLE_INTERRUPT
+                | ier::flags::RECEIVER_LINE_STATUS_INTERRUPT,
+            flow_control: false,
+        }
+    }
+
+    /// Change the configured port.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The UART port address, such as [`COM1`].
+    pub fn port(self, port: ComPort) -> Self {
+        Self { port, ..self }
+    }
+
+    /// Change the configured baud rate, it is validated by
+    /// [`SerialConfig::build`].
+    ///
+    /// # Arguments
+    ///
+    /// * `baud_rate` - The desired baud rate.
+    pub fn baud_rate(self, baud_rate: u32) -> Self {
+        Self { baud_rate, ..self }
+    }
+
+    /// Change the configured word length.
+    ///
+    /// # Arguments
+    ///
+    /// * `word_length` - The number of data bits per character.
+    pub fn word_length(self, word_length: WordLengthBits) -> Self {
+        Self {
+            word_length,
+            ..self
+        }
+    }
+
+    /// Change the configured parity.
+    ///
+    /// # Arguments
+    ///
+    /// * `parity` - The parity bit mode.
+    pub fn parity(self, parity: Parity) -> Self {
+        Self { parity, ..self }
+    }
+
+    /// Change the configured stop bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `stop_bits` - The number of stop bits.
+    pub fn stop_bits(self, stop_bits: StopBit) -> Self {
+        Self { stop_bits, ..self }
+    }
+
+    /// Change the configured receive FIFO trigger level.
+    ///
+    /// # Arguments
+    ///
+    /// * `trigger_level` - The number of bytes raising the received data
+    ///   available interrupt.
+    pub fn trigger_level(self, trigger_level: TriggerLevel16) -> Self {
+        Self {
+            trigger_level,
+            ..self
+        }
+    }
+
+    /// Change the enabled interrupts.
+    ///
+    /// # Arguments
+    ///
+    /// * `interrupts` - A combination of [`ier::flags`] values.
+    pub fn interrupts(self, interrupts: u8) -> Self {
+        Self { interrupts, ..self }
+    }
+
+    /// Enable or disable RTS/CTS hardware flow control, see
+    /// [`Serial::set_flow_control`].
+    ///
+    /// # Arguments
+    ///
+    /// * `flow_control` - Whether flow control is used.
+    pub fn flow_control(self, flow_control: bool) -> Self {
+        Self {
+            flow_control,
+            ..self
+        }
+    }
+
+    /// Get the configured port.
+    pub fn get_port(&self) -> ComPort {
+        self.port
+    }
+
+    /// Get the configured baud rate.
+    pub fn get_baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Get the line control register value of the configuration.
+    pub fn line_control(&self) -> u8 {
+        self.word_length as u8 | self.stop_bits as u8 | self.parity as u8
+    }
+
+    /// Get the FIFO control register value of the configuration, enabling
+    /// and clearing both FIFOs.
+    pub fn fifo_control(&self) -> u8 {
+        self.trigger_level as u8
+            | fcr::flags::ENABLE_FIFOS
+            | fcr::flags::CLEAR_TRANSMIT_FIFO
+            | fcr::flags::CLEAR_RECEIVE_FIFO
+    }
+
+    /// Get the enabled interrupts.
+    pub fn get_interrupts(&self) -> u8 {
+        self.interrupts
+    }
+
+    /// Validate the configuration and apply it to the UART.
+    pub fn build(self) -> Result<Serial, BaudRateError> {
+        let divisor = divisor(self.baud_rate)?;
+        let serial = Serial::new(self.port);
+        serial.configure(divisor, &self);
+        serial.set_flow_control(self.flow_control);
+        Ok(serial)
+    }
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self::const_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn divisor_validation() {
+        assert_eq!(divisor(115200), Ok(1));
+        assert_eq!(divisor(38400), Ok(3));
+        assert_eq!(divisor(0), Err(BaudRateError::Zero));
+        assert_eq!(divisor(230400), Err(BaudRateError::TooHigh(230400)));
+        assert_eq!(divisor(1000), Err(BaudRateError::Inexact(1000)));
+        assert_eq!(divisor(1), Err(BaudRateError::TooLow(1)));
+    }
+
+    #[test_case]
+    fn default_line_settings() {
+        let config = SerialConfig::default();
+        assert_eq!(config.get_port(), COM1);
+        assert_eq!(config.line_control(), 0b0000_0011);
+        assert_eq!(config.fifo_control(), 0b1100_0111);
+        let config = config
+            .parity(Parity::EvenParity)
+            .stop_bits(StopBit::TwoStop)
+            .word_length(WordLengthBits::Seven);
+        assert_eq!(config.line_control(), 0b0001_1110);
+    }
+}
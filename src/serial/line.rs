@@ -0,0 +1,286 @@
+//! A module classifying received bytes according to the UART line status and
+//! keeping per port error counters.
+use super::registers::lsr::LineStatus;
+use super::{Serial, PORT_COUNT};
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// An error reported by the UART along with a received byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineError {
+    /// Bytes were lost before this one because the receiver was not read in
+    /// time, the byte itself is valid.
+    Overrun,
+    /// The parity bit does not match the configured parity.
+    Parity,
+    /// No valid stop bit was found.
+    Framing,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LineError::Overrun => "Overrun error",
+                LineError::Parity => "Parity error",
+                LineError::Framing => "Framing error",
+            }
+        )
+    }
+}
+
+/// Something received on the serial line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reception {
+    /// A byte received without error.
+    Byte(u8),
+    /// A byte received along with a line error.
+    Corrupted {
+        /// The received byte.
+        byte: u8,
+        /// The reported error.
+        error: LineError,
+    },
+    /// The line was held low for longer than a character, a console may use
+    /// it as an attention signal.
+    Break,
+}
+
+impl Reception {
+    /// Classify a byte from the line status read before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The line status register value.
+    /// * `byte` - The byte read from the receiver buffer.
+    pub fn classify(status: &LineStatus, byte: u8) -> Self {
+        let error = if status.break_interrupt() {
+            return Reception::Break;
+        } else if status.framing_error() {
+            LineError::Framing
+        } else if status.parity_error() {
+            LineError::Parity
+        } else if status.overrun_error() {
+            LineError::Overrun
+        } else {
+            return Reception::Byte(byte);
+        };
+        Reception::Corrupted { byte, error }
+    }
+
+    /// Get the received byte, `None` for a break.
+    pub fn byte(&self) -> Option<u8> {
+        match *self {
+            Reception::Byte(byte) | Reception::Corrupted { byte, .. } => Some(byte),
+            Reception::Break => None,
+        }
+    }
+
+    /// Get the received byte if it is valid, `None` for a break or a byte
+    /// with a parity or framing error. An overrun only lost the bytes before
+    /// this one, which is valid.
+    pub fn valid_byte(&self) -> Option<u8> {
+        match *self {
+            Reception::Byte(byte)
+            | Reception::Corrupted {
+                byte,
+                error: LineError::Overrun,
+            } => Some(byte),
+            Reception::Corrupted { .. } | Reception::Break => None,
+        }
+    }
+}
+
+/// A snapshot of a port's counters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SerialStatistics {
+    /// Bytes received, with or without error.
+    pub received: u32,
+    /// Bytes sent.
+    pub transmitted: u32,
+    /// Overrun errors reported by the UART.
+    pub overrun_errors: u32,
+    /// Parity errors.
+    pub parity_errors: u32,
+    /// Framing errors.
+    pub framing_errors: u32,
+    /// Break conditions.
+    pub breaks: u32,
+    /// Receptions dropped because the receive buffer was full.
+    pub dropped: u32,
+}
+
+impl fmt::Display for SerialStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RX: {}\nTX: {}\nOverrun: {}\nParity: {}\nFraming: {}\nBreak: {}\nDropped: {}",
+            self.received,
+            self.transmitted,
+            self.overrun_errors,
+            self.parity_errors,
+            self.framing_errors,
+            self.breaks,
+            self.dropped
+        )
+    }
+}
+
+/// The counters of a port, updated from the interrupt handler.
+struct Counters {
+    received: AtomicU32,
+    transmitted: AtomicU32,
+    overrun_errors: AtomicU32,
+    parity_errors: AtomicU32,
+    framing_errors: AtomicU32,
+    breaks: AtomicU32,
+    dropped: AtomicU32,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Counters {
+            received: AtomicU32::new(0),
+            transmitted: AtomicU32::new(0),
+            overrun_errors: AtomicU32::new(0),
+            parity_errors: AtomicU32::new(0),
+            framing_errors: AtomicU32::new(0),
+            breaks: AtomicU32::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    fn all(&self) -> [&AtomicU32; 7] {
+        [
+            &self.received,
+            &self.transmitted,
+            &self.overrun_errors,
+            &self.parity_errors,
+            &self.framing_errors,
+            &self.breaks,
+            &self.dropped,
+        ]
+    }
+}
+
+/// Counters of every port, indexed as [`super::COM1`] to [`super::COM4`].
+static COUNTERS: [Counters; PORT_COUNT] = [
+    Counters::new(),
+    Counters::new(),
+    Counters::new(),
+    Counters::new(),
+];
+
+fn increment(counter: &AtomicU32) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+impl Serial {
+    fn counters(&self) -> Option<&'static Counters> {
+        self.port_index().map(|index| &COUNTERS[index])
+    }
+
+    /// Count a reception.
+    pub(super) fn count_reception(&self, reception: &Reception) {
+        let Some(counters) = self.counters() else {
+            return;
+        };
+        match reception {
+            Reception::Byte(_) => increment(&counters.received),
+            Reception::Corrupted { error, .. } => {
+                increment(&counters.received);
+                increment(match error {
+                    LineError::Overrun => &counters.overrun_errors,
+                    LineError::Parity => &counters.parity_errors,
+                    LineError::Framing => &counters.framing_errors,
+                });
+            }
+            Reception::Break => increment(&counters.breaks),
+        }
+    }
+
+    /// Count a reception dropped because the receive buffer was full.
+    pub(super) fn count_dropped(&self) {
+        if let Some(counters) = self.counters() {
+            increment(&counters.dropped);
+        }
+    }
+
+    /// Count a sent byte.
+    pub(super) fn count_transmitted(&self) {
+        if let Some(counters) = self.counters() {
+            increment(&counters.transmitted);
+        }
+    }
+
+    /// Get a snapshot of the port's counters, all zero for ports other than
+    /// [`super::COM1`] to [`super::COM4`].
+    pub fn statistics(&self) -> SerialStatistics {
+        self.counters()
+            .map(|counters| {
+                let load = |counter: &AtomicU32| counter.load(Ordering::Relaxed);
+                SerialStatistics {
+                    received: load(&counters.received),
+                    transmitted: load(&counters.transmitted),
+                    overrun_errors: load(&counters.overrun_errors),
+                    parity_errors: load(&counters.parity_errors),
+                    framing_errors: load(&counters.framing_errors),
+                    breaks: load(&counters.breaks),
+                    dropped: load(&counters.dropped),
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Reset the port's counters.
+    pub fn reset_statistics(&self) {
+        if let Some(counters) = self.counters() {
+            for counter in counters.all() {
+                counter.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::registers::lsr::flags;
+
+    #[test_case]
+    fn classify_priority() {
+        let status = LineStatus::from(flags::DATA_READY);
+        assert_eq!(Reception::classify(&status, b'a'), Reception::Byte(b'a'));
+        let status =
+            LineStatus::from(flags::DATA_READY | flags::PARITY_ERROR | flags::OVERRUN_ERROR);
+        assert_eq!(
+            Reception::classify(&status, b'a'),
+            Reception::Corrupted {
+                byte: b'a',
+                error: LineError::Parity
+            }
+        );
+        let status =
+            LineStatus::from(flags::DATA_READY | flags::BREAK_INTERRUPT | flags::FRAMING_ERROR);
+        assert_eq!(Reception::classify(&status, 0), Reception::Break);
+        assert_eq!(Reception::Break.byte(), None);
+    }
+
+    #[test_case]
+    fn overrun_byte_is_valid() {
+        let overrun = Reception::Corrupted {
+            byte: b'a',
+            error: LineError::Overrun,
+        };
+        assert_eq!(overrun.valid_byte(), Some(b'a'));
+        let framing = Reception::Corrupted {
+            byte: b'a',
+            error: LineError::Framing,
+        };
+        assert_eq!(framing.valid_byte(), None);
+        assert_eq!(Reception::Byte(b'b').valid_byte(), Some(b'b'));
+        assert_eq!(Reception::Break.valid_byte(), None);
+    }
+}
@@ -0,0 +1,277 @@
+//! A module giving typed access to the modem control lines of a [`Serial`]
+//! port, along with RTS/CTS hardware flow control and modem status events.
+use super::registers::{ier, mcr, msr};
+use super::{Serial, PORT_COUNT};
+use crate::arch::interrupts::without_interrupts;
+use crate::arch::io::register::{ReadRegister, WriteRegister};
+use crate::utils::ring_buffer::RingBuffer;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Capacity of the modem event queue of every port.
+pub const MODEM_EVENT_BUFFER_SIZE: usize = 32;
+
+/// Receive buffer length above which RTS is deasserted when flow control is
+/// enabled.
+const RECEIVE_HIGH_WATER: usize = super::RECEIVE_BUFFER_SIZE * 3 / 4;
+/// Receive buffer length below which RTS is asserted again.
+const RECEIVE_LOW_WATER: usize = super::RECEIVE_BUFFER_SIZE / 4;
+
+/// Whether RTS/CTS flow control is enabled on every port, indexed as
+/// [`super::COM1`] to [`super::COM4`].
+static FLOW_CONTROL: [AtomicBool; PORT_COUNT] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Modem status changes reported by the interrupt handler of every port.
+static MODEM_EVENTS: [RingBuffer<ModemEvent, MODEM_EVENT_BUFFER_SIZE>; PORT_COUNT] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+
+/// The modem status inputs of an UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModemLine {
+    /// Clear to send (CTS), the remote end accepts data.
+    ClearToSend,
+    /// Data set ready (DSR), the remote end is ready.
+    DataSetReady,
+    /// Ring indicator (RI).
+    RingIndicator,
+    /// Data carrier detect (DCD).
+    CarrierDetect,
+}
+
+/// A change of a modem status input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModemEvent {
+    /// The line that changed.
+    pub line: ModemLine,
+    /// The new state of the line.
+    pub asserted: bool,
+}
+
+/// Decode the events reported by a modem status register value.
+///
+/// # Arguments
+///
+/// * `status` - The raw modem status register value.
+fn events(status: u8) -> impl Iterator<Item = ModemEvent> {
+    use msr::flags::*;
+    [
+        (DELTA_CLEAR_TO_SEND, CLEAR_TO_SEND, ModemLine::ClearToSend),
+        (
+            DELTA_DATA_SET_READY,
+            DATA_SET_READY,
+            ModemLine::DataSetReady,
+        ),
+        (
+            TRAILING_EDGE_RING_INDICATOR,
+            RING_INDICATOR,
+            ModemLine::RingIndicator,
+        ),
+        (
+            DELTA_DATA_CARRIER_DETECT,
+            CARRIER_DETECT,
+            ModemLine::CarrierDetect,
+        ),
+    ]
+    .into_iter()
+    .filter(move |&(delta, _, _)| status & delta != 0)
+    .map(move |(_, state, line)| ModemEvent {
+        line,
+        asserted: status & state != 0,
+    })
+}
+
+impl Serial {
+    /// Set or clear modem control register bits.
+    fn update_modem_control(&self, mask: u8, value: bool) {
+        without_interrupts(|| unsafe {
+            let register = self.model_control_register();
+            let current = register.read().0;
+            register.write(mcr::ModemControl(if value {
+                current | mask
+            } else {
+                current & !mask
+            }));
+        });
+    }
+
+    /// Assert or deassert the data terminal ready (DTR) output.
+    ///
+    /// # Arguments
+    ///
+    /// * `asserted` - The desired line state.
+    pub fn set_data_terminal_ready(&self, asserted: bool) {
+        self.update_modem_control(mcr::flags::DATA_TERMINAL_READY, asserted);
+    }
+
+    /// Assert or deassert the request to send (RTS) output.
+    ///
+    /// # Arguments
+    ///
+    /// * `asserted` - The desired line state.
+    ///
+    /// # Note
+    ///
+    /// With flow control enabled, RTS is also driven by the receive buffer
+    /// fill level.
+    pub fn set_request_to_send(&self, asserted: bool) {
+        self.update_modem_control(mcr::flags::REQUEST_TO_SEND, asserted);
+    }
+
+    /// Whether the data terminal ready (DTR) output is asserted.
+    pub fn data_terminal_ready(&self) -> bool {
+        unsafe { self.model_control_register().read() }.data_terminal_ready()
+    }
+
+    /// Whether the request to send (RTS) output is asserted.
+    pub fn request_to_send(&self) -> bool {
+        unsafe { self.model_control_register().read() }.request_to_send()
+    }
+
+    /// Whether the clear to send (CTS) input is asserted.
+    pub fn clear_to_send(&self) -> bool {
+        unsafe { self.modem_status_register().read() }.clear_to_send()
+    }
+
+    /// Whether the data set ready (DSR) input is asserted.
+    pub fn data_set_ready(&self) -> bool {
+        unsafe { self.modem_status_register().read() }.data_set_ready()
+    }
+
+    /// Whether the ring indicator (RI) input is asserted.
+    pub fn ring_indicator(&self) -> bool {
+        unsafe { self.modem_status_register().read() }.ring_indicator()
+    }
+
+    /// Whether the data carrier detect (DCD) input is asserted.
+    pub fn carrier_detect(&self) -> bool {
+        unsafe { self.modem_status_register().read() }.carrier_detect()
+    }
+
+    /// Get the state of a modem status input.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The input to read.
+    pub fn modem_line(&self, line: ModemLine) -> bool {
+        match line {
+            ModemLine::ClearToSend => self.clear_to_send(),
+            ModemLine::DataSetReady => self.data_set_ready(),
+            ModemLine::RingIndicator => self.ring_indicator(),
+            ModemLine::CarrierDetect => self.carrier_detect(),
+        }
+    }
+
+    /// Enable or disable RTS/CTS hardware flow control. Transmission pauses
+    /// while CTS is deasserted and RTS is deasserted while the receive
+    /// buffer is nearly full.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether flow control is used.
+    ///
+    /// # Note
+    ///
+    /// The modem status interrupt is enabled so transmission resumes as
+    /// soon as CTS is asserted, and RTS is asserted.
+    pub fn set_flow_control(&self, enabled: bool) {
+        let Some(index) = self.port_index() else {
+            return;
+        };
+        FLOW_CONTROL[index].store(enabled, Ordering::Release);
+        if enabled {
+            without_interrupts(|| unsafe {
+                let register = self.interrupt_enable_register();
+                let enable = register.read().0 | ier::flags::MODEM_STATUS_INTERRUPT;
+                register.write(ier::InterruptEnable(enable));
+            });
+            self.set_request_to_send(true);
+        }
+    }
+
+    /// Whether RTS/CTS hardware flow control is enabled.
+    pub fn flow_control(&self) -> bool {
+        self.port_index()
+            .is_some_and(|index| FLOW_CONTROL[index].load(Ordering::Acquire))
+    }
+
+    /// Whether the remote end accepts data, always `true` without flow
+    /// control.
+    pub(super) fn may_transmit(&self) -> bool {
+        !self.flow_control() || self.clear_to_send()
+    }
+
+    /// Update RTS according to the receive buffer fill level when flow
+    /// control is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The number of bytes in the receive buffer.
+    pub(super) fn throttle_receive(&self, length: usize) {
+        if !self.flow_control() {
+            return;
+        }
+        if length >= RECEIVE_HIGH_WATER && self.request_to_send() {
+            self.set_request_to_send(false);
+        } else if length <= RECEIVE_LOW_WATER && !self.request_to_send() {
+            self.set_request_to_send(true);
+        }
+    }
+
+    /// Queue the events of a modem status change and resume transmission if
+    /// CTS got asserted, called from the interrupt handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The raw modem status register value.
+    pub(super) fn modem_status_changed(&self, status: u8) {
+        let queue = self.port_index().map(|index| &MODEM_EVENTS[index]);
+        for event in events(status) {
+            if let Some(queue) = queue {
+                queue.push(event).ok();
+            }
+            if event.line == ModemLine::ClearToSend && event.asserted && self.flow_control() {
+                self.start_transmit();
+            }
+        }
+    }
+
+    /// Get the oldest modem status change, if any.
+    pub fn try_read_modem_event(&self) -> Option<ModemEvent> {
+        self.port_index()
+            .and_then(|index| MODEM_EVENTS[index].pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn decode_events() {
+        use msr::flags::*;
+        let mut decoded = events(DELTA_CLEAR_TO_SEND | CLEAR_TO_SEND | DELTA_DATA_CARRIER_DETECT);
+        assert_eq!(
+            decoded.next(),
+            Some(ModemEvent {
+                line: ModemLine::ClearToSend,
+                asserted: true
+            })
+        );
+        assert_eq!(
+            decoded.next(),
+            Some(ModemEvent {
+                line: ModemLine::CarrierDetect,
+                asserted: false
+            })
+        );
+        assert_eq!(decoded.next(), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! A module detecting which UART, if any, backs a COM port.
+use super::registers::{fcr, iir::flags::FifoStatus, mcr, msr};
+use super::{ComPort, Serial, COM1, COM2, COM3, COM4};
+use crate::arch::interrupts::without_interrupts;
+use crate::arch::io::register::{ReadRegister, WriteRegister};
+use core::fmt;
+
+/// Known UART chip models, from the oldest to the most recent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartModel {
+    /// Original UART without scratch register nor FIFO.
+    Uart8250,
+    /// UART with a scratch register and no FIFO.
+    Uart16450,
+    /// UART with a FIFO too unreliable to be used.
+    Uart16550,
+    /// UART with a working 16 bytes FIFO.
+    Uart16550A,
+    /// UART with a 64 bytes FIFO.
+    Uart16750,
+}
+
+impl UartModel {
+    /// Get the number of bytes the transmitter FIFO holds, 1 when the FIFO
+    /// is missing or unusable.
+    pub fn fifo_depth(&self) -> usize {
+        match self {
+            UartModel::Uart8250 | UartModel::Uart16450 | UartModel::Uart16550 => 1,
+            UartModel::Uart16550A => 16,
+            UartModel::Uart16750 => 64,
+        }
+    }
+}
+
+impl fmt::Display for UartModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                UartModel::Uart8250 => "8250",
+                UartModel::Uart16450 => "16450",
+                UartModel::Uart16550 => "16550",
+                UartModel::Uart16550A => "16550A",
+                UartModel::Uart16750 => "16750",
+            }
+        )
+    }
+}
+
+/// The result of a successful [`Serial::probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UartInfo {
+    /// The UART port address.
+    pub port: ComPort,
+    /// The detected chip model.
+    pub model: UartModel,
+    /// The number of bytes the transmitter FIFO holds.
+    pub fifo_depth: usize,
+}
+
+impl fmt::Display for UartInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UART {} at {:#X}, {} bytes FIFO",
+            self.model, self.port, self.fifo_depth
+        )
+    }
+}
+
+/// The modem status inputs, which mirror the modem control outputs in
+/// loopback mode.
+const MODEM_INPUTS: u8 = msr::flags::CARRIER_DETECT
+    | msr::flags::RING_INDICATOR
+    | msr::flags::DATA_SET_READY
+    | msr::flags::CLEAR_TO_SEND;
+
+/// Identify a UART model from its interrupt identification register once
+/// the FIFOs have been enabled.
+///
+/// # Arguments
+///
+/// * `identification` - The raw interrupt identification register value.
+/// * `scratch` - Whether the scratch register holds written values.
+fn identify(identification: u8, scratch: bool) -> UartModel {
+    use super::registers::iir::flags::{FIFO_ENABLED, FIFO_STATUS, FIFO_STATUS_OFFSET};
+    match FifoStatus::from((identification & FIFO_STATUS) >> FIFO_STATUS_OFFSET) {
+        FifoStatus::Enabled if identification & FIFO_ENABLED != 0 => UartModel::Uart16750,
+        FifoStatus::Enabled => UartModel::Uart16550A,
+        FifoStatus::EnabledNotFunctioning => UartModel::Uart16550,
+        _ if scratch => UartModel::Uart16450,
+        _ => UartModel::Uart8250,
+    }
+}
+
+impl Serial {
+    /// Whether the modem status inputs follow the modem control outputs in
+    /// loopback mode, which only happens when a UART answers on the port.
+    fn loopback_test(&self) -> bool {
+        use mcr::flags::*;
+        unsafe {
+            let control = self.model_control_register();
+            let status = self.modem_status_register();
+            control.write(mcr::ModemControl(
+                LOOPBACK_MODE | AUX_OUTPUT_2 | AUX_OUTPUT_1 | REQUEST_TO_SEND | DATA_TERMINAL_READY,
+            ));
+            let asserted = status.read().0 & MODEM_INPUTS == MODEM_INPUTS;
+            control.write(mcr::ModemControl(LOOPBACK_MODE));
+            let cleared = status.read().0 & MODEM_INPUTS == 0;
+            asserted && cleared
+        }
+    }
+
+    /// Whether the scratch register holds the written values.
+    fn scratch_test(&self) -> bool {
+        unsafe {
+            let scratch = self.scratch_register();
+            let saved = scratch.read();
+            let result = [0x55, 0xAA].iter().all(|&value| {
+                scratch.write(value);
+                scratch.read() == value
+            });
+            scratch.write(saved);
+            result
+        }
+    }
+
+    /// Detect the UART behind a port.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The UART port address, such as [`COM1`].
+    ///
+    /// # Note
+    ///
+    /// Pending output is flushed first. The modem control register is
+    /// restored afterwards while the FIFOs are left enabled with a trigger
+    /// level of 14 when they work, and disabled otherwise.
+    pub fn probe(port: ComPort) -> Option<UartInfo> {
+        let serial = Serial::new(port);
+        serial.flush();
+        without_interrupts(|| unsafe {
+            let saved = serial.model_control_register().read();
+            let present = serial.loopback_test();
+            serial.model_control_register().write(saved);
+            if !present {
+                return None;
+            }
+
+            let scratch = serial.scratch_test();
+            serial.fifo_control_register().write(fcr::FifoControl(
+                fcr::flags::ENABLE_FIFOS | fcr::flags::ENABLE_64B_FIFO,
+            ));
+            let model = identify(serial.interrupt_identification_register().read().0, scratch);
+            serial
+                .fifo_control_register()
+                .write(fcr::FifoControl(if model.fifo_depth() > 1 {
+                    fcr::flags::ENABLE_FIFOS | fcr::flags::TriggerLevel16::Itl14 as u8
+                } else {
+                    0
+                }));
+
+            Some(UartInfo {
+                port,
+                model,
+                fifo_depth: model.fifo_depth(),
+            })
+        })
+    }
+}
+
+/// List the UARTs answering on [`COM1`] to [`COM4`].
+pub fn enumerate() -> impl Iterator<Item = UartInfo> {
+    [COM1, COM2, COM3, COM4]
+        .into_iter()
+        .filter_map(Serial::probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn identify_models() {
+        assert_eq!(identify(0b1110_0001, true), UartModel::Uart16750);
+        assert_eq!(identify(0b1100_0001, true), UartModel::Uart16550A);
+        assert_eq!(identify(0b1000_0001, true), UartModel::Uart16550);
+        assert_eq!(identify(0b0000_0001, true), UartModel::Uart16450);
+        assert_eq!(identify(0b0000_0001, false), UartModel::Uart8250);
+    }
+}
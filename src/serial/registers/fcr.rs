@@ -74,6 +74,7 @@ pub mod flags {
     ///
     /// The `u8` representation of this enum has already been shifted to the
     /// field's position.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum TriggerLevel16 {
         /// Interrupt trigger level of 1 byte.
@@ -93,6 +94,7 @@ pub mod flags {
     ///
     /// The `u8` representation of this enum has already been shifted to the
     /// field's position.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum TriggerLevel64 {
         /// Interrupt trigger level of 1 byte.
@@ -41,7 +41,7 @@ impl From<ComPort> for InterruptIdentificationRegister {
 }
 
 /// Internal value of the [`InterruptIdentificationRegister`].
-pub struct InterruptIdentification(u8);
+pub struct InterruptIdentification(pub u8);
 
 impl From<u8> for InterruptIdentification {
     fn from(value: u8) -> Self {
@@ -99,6 +99,7 @@ pub mod flags {
     ///
     /// The `u8` representation of this enum has already been shifted to the
     /// field's position.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum InterruptEventType {
         ModemStatus = 0 << INTERUPT_EVENT_TYPE_OFFSET,
@@ -145,7 +146,12 @@ impl InterruptIdentification {
     }
 
     /// Whether an interrupt is pending.
+    ///
+    /// # Note
+    ///
+    /// The hardware bit is active low, it is cleared while an interrupt is
+    /// pending.
     pub fn interrupt_pending(&self) -> bool {
-        self.0 & flags::INTERRUPT_PENDING != 0
+        self.0 & flags::INTERRUPT_PENDING == 0
     }
 }
@@ -76,6 +76,7 @@ pub mod flags {
     pub const PARITY_OFFSET: u8 = 3;
     pub const STOP_BIT_OFFSET: u8 = 2;
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum Parity {
         NoParity = 0 << PARITY_OFFSET,
@@ -100,6 +101,7 @@ pub mod flags {
         }
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum StopBit {
         OneStop = 0 << STOP_BIT_OFFSET,
@@ -116,6 +118,7 @@ pub mod flags {
         }
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[repr(u8)]
     pub enum WordLengthBits {
         Five = 0,
@@ -40,7 +40,7 @@ impl From<ComPort> for ModemControlRegister {
     }
 }
 
-pub struct ModemControl(u8);
+pub struct ModemControl(pub u8);
 
 impl From<u8> for ModemControl {
     fn from(value: u8) -> Self {
@@ -34,7 +34,7 @@ impl From<ComPort> for ModemStatusRegister {
     }
 }
 
-pub struct ModemStatus(u8);
+pub struct ModemStatus(pub u8);
 
 impl From<u8> for ModemStatus {
     fn from(value: u8) -> Self {
@@ -0,0 +1,474 @@
+//! An interactive command shell, reading from the serial port and the
+//! keyboard and echoing to every `klog` output.
+//!
+//! Subsystems add their commands with [`register`]. The line editor supports
+//! backspace, `Ctrl-U` to clear the line, `Ctrl-C` to cancel it and the up
+//! and down arrows to browse the history.
+use crate::arch::ia32::halt;
+use crate::drivers::ps2::keyboard::{self, KeyCode, KeyState};
+use crate::klog;
+use crate::serial::{Serial, COM1};
+use crate::sync::RwLock;
+use core::fmt::{self, Write};
+
+pub mod commands;
+
+/// Maximum length of a line.
+pub const LINE_SIZE: usize = 128;
+/// Number of lines kept in the history.
+pub const HISTORY_SIZE: usize = 16;
+/// Maximum number of registered commands.
+pub const MAX_COMMANDS: usize = 32;
+/// Maximum number of arguments of a command, extra ones are ignored.
+pub const MAX_ARGUMENTS: usize = 16;
+
+/// The prompt printed before every line.
+const PROMPT: &str = "flint> ";
+
+/// A function running a command with its arguments, the command name
+/// excluded.
+pub type CommandHandler = fn(&[&str]);
+
+/// A shell command.
+#[derive(Clone, Copy)]
+pub struct Command {
+    /// The name typed to run the command.
+    pub name: &'static str,
+    /// A one line description, listed by `help`.
+    pub help: &'static str,
+    pub handler: CommandHandler,
+}
+
+/// An error registering a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellError {
+    /// [`MAX_COMMANDS`] commands are already registered.
+    Full,
+    /// A command with the same name is already registered.
+    Duplicate,
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShellError::Full => write!(f, "too many shell commands"),
+            ShellError::Duplicate => write!(f, "shell command already registered"),
+        }
+    }
+}
+
+static COMMANDS: RwLock<[Option<Command>; MAX_COMMANDS]> = RwLock::new([None; MAX_COMMANDS]);
+
+/// Register a command.
+///
+/// # Arguments
+///
+/// * `command` - The command, its name must not contain whitespace.
+pub fn register(command: Command) -> Result<(), ShellError> {
+    let mut commands = COMMANDS.write();
+    if find_in(&commands[..], command.name).is_some() {
+        return Err(ShellError::Duplicate);
+    }
+    let slot = commands
+        .iter_mut()
+        .find(|slot| slot.is_none())
+        .ok_or(ShellError::Full)?;
+    *slot = Some(command);
+    Ok(())
+}
+
+fn find_in(commands: &[Option<Command>], name: &str) -> Option<Command> {
+    commands
+        .iter()
+        .flatten()
+        .find(|command| command.name == name)
+        .copied()
+}
+
+/// Find a registered command by its name.
+pub fn find(name: &str) -> Option<Command> {
+    find_in(&COMMANDS.read()[..], name)
+}
+
+/// Call a function on every registered command, in registration order.
+pub fn for_each_command(f: impl FnMut(&Command)) {
+    // The commands are copied out of the lock, the function may register
+    // new ones.
+    let commands = *COMMANDS.read();
+    commands.iter().flatten().for_each(f);
+}
+
+/// Run a command line.
+///
+/// # Arguments
+///
+/// * `line` - The command name followed by its arguments, separated by
+///   whitespace.
+pub fn execute(line: &str) {
+    let mut arguments = [""; MAX_ARGUMENTS];
+    let mut count = 0;
+    for (slot, word) in arguments.iter_mut().zip(line.split_whitespace()) {
+        *slot = word;
+        count += 1;
+    }
+    let Some((&name, arguments)) = arguments[..count].split_first() else {
+        return;
+    };
+    match find(name) {
+        Some(command) => (command.handler)(arguments),
+        None => println!("Unknown command: {}, type help for a list", name),
+    }
+}
+
+/// An editing action, decoded from the serial port or the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A printable ASCII character.
+    Char(u8),
+    Backspace,
+    Enter,
+    /// Recall the previous history line.
+    Up,
+    /// Recall the next history line.
+    Down,
+    ClearLine,
+    Cancel,
+}
+
+impl Input {
+    /// Decode a character, as typed on the keyboard or received as a byte.
+    pub fn from_char(character: char) -> Option<Self> {
+        let input = match character {
+            '\r' | '\n' => Input::Enter,
+            '\x08' | '\x7F' => Input::Backspace,
+            '\x15' => Input::ClearLine,
+            '\x03' => Input::Cancel,
+            ' '..='~' => Input::Char(character as u8),
+            _ => return None,
+        };
+        Some(input)
+    }
+}
+
+/// A decoder of serial bytes, handling the VT100 sequences of the arrows.
+#[derive(Debug, Default)]
+pub struct SerialInput {
+    /// Number of bytes of an escape sequence received.
+    escape: usize,
+    /// Whether the previous byte was a carriage return, a line feed then
+    /// ends the same line.
+    carriage_return: bool,
+}
+
+impl SerialInput {
+    /// Feed a byte received from the serial port.
+    pub fn feed(&mut self, byte: u8) -> Option<Input> {
+        let carriage_return = core::mem::replace(&mut self.carriage_return, byte == b'\r');
+        if carriage_return && byte == b'\n' {
+            return None;
+        }
+        match (self.escape, byte) {
+            (0, 0x1B) => self.escape = 1,
+            (1, b'[') => self.escape = 2,
+            (2, b'A') => {
+                self.escape = 0;
+                return Some(Input::Up);
+            }
+            (2, b'B') => {
+                self.escape = 0;
+                return Some(Input::Down);
+            }
+            // Other sequences are dropped.
+            (1.., _) => self.escape = 0,
+            (0, byte) => return Input::from_char(byte.into()),
+        }
+        None
+    }
+}
+
+/// The previously entered lines.
+struct History {
+    lines: [[u8; LINE_SIZE]; HISTORY_SIZE],
+    lengths: [usize; HISTORY_SIZE],
+    /// Number of lines stored.
+    count: usize,
+    /// Index of the next line to write.
+    next: usize,
+}
+
+impl History {
+    const fn new() -> Self {
+        History {
+            lines: [[0; LINE_SIZE]; HISTORY_SIZE],
+            lengths: [0; HISTORY_SIZE],
+            count: 0,
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, line: &[u8]) {
+        // Repeated lines are stored once.
+        if line.is_empty() || self.get(1) == Some(line) {
+            return;
+        }
+        self.lines[self.next][..line.len()].copy_from_slice(line);
+        self.lengths[self.next] = line.len();
+        self.next = (self.next + 1) % HISTORY_SIZE;
+        self.count = (self.count + 1).min(HISTORY_SIZE);
+    }
+
+    /// Get a line, 1 being the most recent one.
+    fn get(&self, age: usize) -> Option<&[u8]> {
+        if age == 0 || age > self.count {
+            return None;
+        }
+        let index = (self.next + HISTORY_SIZE - age) % HISTORY_SIZE;
+        Some(&self.lines[index][..self.lengths[index]])
+    }
+}
+
+/// A line editor with history.
+pub struct LineEditor {
+    line: [u8; LINE_SIZE],
+    length: usize,
+    history: History,
+    /// Age of the recalled history line, 0 when editing a new line.
+    age: usize,
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        LineEditor {
+            line: [0; LINE_SIZE],
+            length: 0,
+            history: History::new(),
+            age: 0,
+        }
+    }
+
+    /// Get the line being edited.
+    pub fn line(&self) -> &str {
+        // Only printable ASCII characters are inserted.
+        core::str::from_utf8(&self.line[..self.length]).unwrap_or_default()
+    }
+
+    /// Erase the displayed line and replace it.
+    fn replace(&mut self, line: &[u8], echo: &mut impl Write) -> fmt::Result {
+        for _ in 0..self.length {
+            echo.write_str("\x08 \x08")?;
+        }
+        self.line[..line.len()].copy_from_slice(line);
+        self.length = line.len();
+        echo.write_str(self.line())
+    }
+
+    /// Apply an input.
+    ///
+    /// Returns whether the line is complete, it can then be read with
+    /// [`LineEditor::line`] until the next input.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The editing action.
+    /// * `echo` - The output showing the line.
+    pub fn feed(&mut self, input: Input, echo: &mut impl Write) -> Result<bool, fmt::Error> {
+        match input {
+            Input::Char(byte) if self.length < LINE_SIZE => {
+                self.line[self.length] = byte;
+                self.length += 1;
+                echo.write_char(byte.into())?;
+            }
+            Input::Char(_) => {}
+            Input::Backspace if self.length > 0 => {
+                self.length -= 1;
+                echo.write_str("\x08 \x08")?;
+            }
+            Input::Backspace => {}
+            Input::ClearLine => self.replace(&[], echo)?,
+            Input::Cancel => {
+                echo.write_str("^C\n")?;
+                self.length = 0;
+                self.age = 0;
+                return Ok(true);
+            }
+            Input::Up | Input::Down => {
+                let age = match input {
+                    Input::Up => self.age + 1,
+                    _ => self.age.saturating_sub(1),
+                };
+                if age == self.age {
+                    return Ok(false);
+                }
+                let mut recalled = [0; LINE_SIZE];
+                let length = match self.history.get(age) {
+                    Some(line) => {
+                        recalled[..line.len()].copy_from_slice(line);
+                        line.len()
+                    }
+                    // Going down past the most recent line gives an empty one.
+                    None if age == 0 => 0,
+                    None => return Ok(false),
+                };
+                self.age = age;
+                self.replace(&recalled[..length], echo)?;
+            }
+            Input::Enter => {
+                echo.write_char('\n')?;
+                self.history.push(&self.line[..self.length]);
+                self.age = 0;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Start a new line once a complete one was read.
+    pub fn clear(&mut self) {
+        self.length = 0;
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `klog` outputs, serial and VGA.
+struct Console;
+
+impl Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        klog::print_fmt(format_args!("{}", s));
+        Ok(())
+    }
+}
+
+/// Get the next input from the keyboard or the serial port, if any.
+fn poll_input(serial: &Serial, serial_input: &mut SerialInput) -> Option<Input> {
+    while let Some(event) = keyboard::read_event() {
+        if event.state != KeyState::Pressed {
+            continue;
+        }
+        let input = match event.code {
+            KeyCode::ArrowUp => Some(Input::Up),
+            KeyCode::ArrowDown => Some(Input::Down),
+            _ => event.character.and_then(Input::from_char),
+        };
+        if input.is_some() {
+            return input;
+        }
+    }
+    while let Some(byte) = serial.try_read_byte() {
+        if let Some(input) = serial_input.feed(byte) {
+            return Some(input);
+        }
+    }
+    None
+}
+
+/// Register the built-in commands and run the shell forever.
+pub fn run() -> ! {
+    commands::register_builtins();
+    let serial = Serial::new(COM1);
+    let mut serial_input = SerialInput::default();
+    let mut editor = LineEditor::new();
+    print!("{}", PROMPT);
+    loop {
+        let Some(input) = poll_input(&serial, &mut serial_input) else {
+            // Both inputs are interrupt driven.
+            halt();
+            continue;
+        };
+        if let Ok(true) = editor.feed(input, &mut Console) {
+            execute(editor.line());
+            editor.clear();
+            print!("{}", PROMPT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An output discarding the echo.
+    struct Sink;
+
+    impl Write for Sink {
+        fn write_str(&mut self, _: &str) -> fmt::Result {
+            Ok(())
+        }
+    }
+
+    fn type_line(editor: &mut LineEditor, line: &str) {
+        for byte in line.bytes() {
+            editor.feed(Input::Char(byte), &mut Sink).unwrap();
+        }
+    }
+
+    #[test_case]
+    fn line_editing() {
+        let mut editor = LineEditor::new();
+        type_line(&mut editor, "helpp");
+        assert_eq!(editor.feed(Input::Backspace, &mut Sink), Ok(false));
+        assert_eq!(editor.line(), "help");
+        assert_eq!(editor.feed(Input::Enter, &mut Sink), Ok(true));
+        assert_eq!(editor.line(), "help");
+        editor.clear();
+        type_line(&mut editor, "abc");
+        editor.feed(Input::ClearLine, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "");
+    }
+
+    #[test_case]
+    fn history_browsing() {
+        let mut editor = LineEditor::new();
+        for line in ["first", "second"] {
+            type_line(&mut editor, line);
+            editor.feed(Input::Enter, &mut Sink).unwrap();
+            editor.clear();
+        }
+        editor.feed(Input::Up, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "second");
+        editor.feed(Input::Up, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "first");
+        // There is no older line.
+        editor.feed(Input::Up, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "first");
+        editor.feed(Input::Down, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "second");
+        editor.feed(Input::Down, &mut Sink).unwrap();
+        assert_eq!(editor.line(), "");
+    }
+
+    #[test_case]
+    fn serial_decoding() {
+        let mut input = SerialInput::default();
+        assert_eq!(input.feed(b'a'), Some(Input::Char(b'a')));
+        assert_eq!(input.feed(b'\r'), Some(Input::Enter));
+        // Both line ending styles end a single line.
+        assert_eq!(input.feed(b'\n'), None);
+        assert_eq!(input.feed(b'\n'), Some(Input::Enter));
+        assert_eq!(input.feed(0x7F), Some(Input::Backspace));
+        assert_eq!(input.feed(0x1B), None);
+        assert_eq!(input.feed(b'['), None);
+        assert_eq!(input.feed(b'A'), Some(Input::Up));
+        assert_eq!(input.feed(0x1B), None);
+        assert_eq!(input.feed(b'x'), None);
+        assert_eq!(input.feed(b'x'), Some(Input::Char(b'x')));
+    }
+
+    #[test_case]
+    fn command_registry() {
+        fn handler(_: &[&str]) {}
+        let command = Command {
+            name: "test-registry",
+            help: "",
+            handler,
+        };
+        assert_eq!(register(command), Ok(()));
+        assert_eq!(register(command), Err(ShellError::Duplicate));
+        assert!(find("test-registry").is_some());
+    }
+}
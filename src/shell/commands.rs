@@ -0,0 +1,132 @@
+//! The commands registered by the shell itself.
+use super::{for_each_command, register, Command};
+use crate::arch::ia32::interrupts::pit::TICK_COUNTER;
+use crate::arch::ia32e::interrupts::idt;
+use crate::arch::ia32e::mm::gdt;
+use crate::drivers::ps2::CONTROLLER;
+use crate::mm::frame::{self, FRAME_SIZE};
+use crate::mm::heap::{HEAP_SIZE, HEAP_START};
+use crate::time;
+use core::arch::asm;
+use log::{warn, LevelFilter};
+
+const BUILTINS: [Command; 7] = [
+    Command {
+        name: "help",
+        help: "List the available commands",
+        handler: help,
+    },
+    Command {
+        name: "ticks",
+        help: "Show the timer ticks and uptime",
+        handler: ticks,
+    },
+    Command {
+        name: "gdt",
+        help: "Dump the global descriptor table",
+        handler: gdt,
+    },
+    Command {
+        name: "idt",
+        help: "Dump the interrupt descriptor table",
+        handler: idt,
+    },
+    Command {
+        name: "mem",
+        help: "Show the free physical memory and the heap region",
+        handler: mem,
+    },
+    Command {
+        name: "reboot",
+        help: "Reset the machine",
+        handler: reboot,
+    },
+    Command {
+        name: "log-level",
+        help: "Set the log level: off, error, warn, info, debug or trace",
+        handler: log_level,
+    },
+];
+
+/// Register the built-in commands, skipping those already registered.
+pub fn register_builtins() {
+    for command in BUILTINS {
+        if let Err(error) = register(command) {
+            warn!("{}: {}", command.name, error);
+        }
+    }
+}
+
+fn help(_: &[&str]) {
+    for_each_command(|command| println!("{:<12} {}", command.name, command.help));
+}
+
+fn ticks(_: &[&str]) {
+    let uptime = time::uptime();
+    println!(
+        "{} ticks at {} Hz, up for {}.{:03} s",
+        TICK_COUNTER.elasped_ticks(),
+        TICK_COUNTER.frequency(),
+        uptime.as_secs(),
+        uptime.subsec_millis()
+    );
+}
+
+fn gdt(_: &[&str]) {
+    match gdt::table() {
+        Some(table) => print!("{}", table),
+        None => println!("The GDT is not set up"),
+    }
+}
+
+fn idt(_: &[&str]) {
+    print!("{}", *idt::table());
+}
+
+fn mem(_: &[&str]) {
+    let free_frames = frame::allocator().free_frames();
+    println!(
+        "Free physical memory: {} KiB ({} frames)",
+        free_frames as u64 * FRAME_SIZE / 1024,
+        free_frames
+    );
+    println!(
+        "Heap: {:#X}..{:#X} ({} KiB)",
+        HEAP_START,
+        HEAP_START + HEAP_SIZE,
+        HEAP_SIZE / 1024
+    );
+}
+
+fn reboot(_: &[&str]) {
+    println!("Rebooting...");
+    if let Err(error) = CONTROLLER.pulse_reset() {
+        warn!("{}", error);
+    }
+    // Fall back to a triple fault: without a usable IDT, the breakpoint
+    // exception cannot be delivered, nor can the double fault.
+    let null_idt = [0_u64; 2];
+    unsafe {
+        asm!("lidt [{}]", "int3", in(reg) null_idt.as_ptr(), options(noreturn));
+    }
+}
+
+fn log_level(arguments: &[&str]) {
+    let level = match arguments {
+        [] => {
+            println!("{}", log::max_level());
+            return;
+        }
+        ["off"] => LevelFilter::Off,
+        ["error"] => LevelFilter::Error,
+        ["warn"] => LevelFilter::Warn,
+        ["info"] => LevelFilter::Info,
+        ["debug"] => LevelFilter::Debug,
+        ["trace"] => LevelFilter::Trace,
+        _ => {
+            println!("Usage: log-level [off|error|warn|info|debug|trace]");
+            return;
+        }
+    };
+    log::set_max_level(level);
+}
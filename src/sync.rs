@@ -0,0 +1,14 @@
+//! Synchronization primitives usable without any operating system support.
+//!
+//! Every primitive spins while waiting, [`IrqSafeSpinLock`] additionally
+//! masks maskable interrupts while held so data shared with interrupt
+//! handlers cannot deadlock on a single core.
+pub use irq::{IrqSafeSpinLock, IrqSafeSpinLockGuard};
+pub use once::{Lazy, Once};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use spin::{SpinLock, SpinLockGuard};
+
+pub mod irq;
+pub mod once;
+pub mod rwlock;
+pub mod spin;
@@ -0,0 +1,171 @@
+//! A module containing the [`IrqSafeSpinLock`] primitive, a spin lock also
+//! masking maskable interrupts while held.
+use super::spin::{SpinLock, SpinLockGuard};
+use crate::arch::interrupts;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+
+/// A [`SpinLock`] disabling maskable interrupts while held, the interrupt
+/// enable flag (IF) is restored to its previous state on release.
+///
+/// Data shared between normal code and interrupt handlers must use this lock,
+/// an interrupt handler spinning on a lock held by the code it interrupted
+/// would never return otherwise.
+pub struct IrqSafeSpinLock<T: ?Sized> {
+    inner: SpinLock<T>,
+}
+
+impl<T> IrqSafeSpinLock<T> {
+    /// Create a new unlocked [`IrqSafeSpinLock`] protecting a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The value to protect.
+    pub const fn new(data: T) -> Self {
+        IrqSafeSpinLock {
+            inner: SpinLock::new(data),
+        }
+    }
+
+    /// Consume the lock and return the protected value.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<T: ?Sized> IrqSafeSpinLock<T> {
+    /// Disable interrupts then acquire the lock, spinning until it is
+    /// available.
+    pub fn lock(&self) -> IrqSafeSpinLockGuard<'_, T> {
+        let interrupts_enabled = interrupts::are_enabled();
+        unsafe {
+            interrupts::disable();
+        }
+        IrqSafeSpinLockGuard {
+            guard: ManuallyDrop::new(self.inner.lock()),
+            interrupts_enabled,
+        }
+    }
+
+    /// Try to acquire the lock without waiting, interrupts are left untouched
+    /// on failure.
+    pub fn try_lock(&self) -> Option<IrqSafeSpinLockGuard<'_, T>> {
+        let interrupts_enabled = interrupts::are_enabled();
+        unsafe {
+            interrupts::disable();
+        }
+        match self.inner.try_lock() {
+            Some(guard) => Some(IrqSafeSpinLockGuard {
+                guard: ManuallyDrop::new(guard),
+                interrupts_enabled,
+            }),
+            None => {
+                if interrupts_enabled {
+                    unsafe {
+                        interrupts::enable();
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Whether the lock is currently held.
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Release the lock regardless of its current owner, the interrupt state
+    /// saved by the owner is lost.
+    ///
+    /// # Safety
+    ///
+    /// The current owner must never access the data again, this is meant for
+    /// paths which will never return to the owner such as a panic.
+    pub unsafe fn force_unlock(&self) {
+        self.inner.force_unlock();
+    }
+
+    /// Get a mutable reference to the protected value, no locking is needed
+    /// as the borrow checker guarantees exclusivity.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<T: Default> Default for IrqSafeSpinLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for IrqSafeSpinLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IrqSafeSpinLock {{ locked: {} }}", self.is_locked())
+    }
+}
+
+/// A scoped access to the data of an [`IrqSafeSpinLock`], the lock is
+/// released then interrupts are restored when the guard is dropped.
+pub struct IrqSafeSpinLockGuard<'a, T: ?Sized> {
+    guard: ManuallyDrop<SpinLockGuard<'a, T>>,
+    interrupts_enabled: bool,
+}
+
+impl<T: ?Sized> Deref for IrqSafeSpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: ?Sized> DerefMut for IrqSafeSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: ?Sized> Drop for IrqSafeSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // The lock must be released before an interrupt handler can run and
+        // try to take it.
+        unsafe {
+            ManuallyDrop::drop(&mut self.guard);
+        }
+        if self.interrupts_enabled {
+            unsafe {
+                interrupts::enable();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn interrupts_masked_while_held() {
+        let lock = IrqSafeSpinLock::new(0);
+        let enabled = interrupts::are_enabled();
+        {
+            let mut guard = lock.lock();
+            *guard += 1;
+            assert!(!interrupts::are_enabled());
+        }
+        assert_eq!(interrupts::are_enabled(), enabled);
+        assert_eq!(*lock.lock(), 1);
+    }
+
+    #[test_case]
+    fn try_lock_held_restores_interrupts() {
+        let lock = IrqSafeSpinLock::new(());
+        let enabled = interrupts::are_enabled();
+        let guard = lock.lock();
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+        assert_eq!(interrupts::are_enabled(), enabled);
+    }
+}
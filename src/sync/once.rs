@@ -0,0 +1,163 @@
+//! A module containing the [`Once`] cell and the [`Lazy`] value built on top
+//! of it.
+use crate::arch::spin_loop;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// The value has not been initialized yet.
+const INCOMPLETE: u8 = 0;
+/// The value is being initialized.
+const RUNNING: u8 = 1;
+/// The value is initialized and can be read.
+const COMPLETE: u8 = 2;
+
+/// A cell which can be written to only once, concurrent initializers spin
+/// until the first one completes.
+pub struct Once<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for Once<T> {}
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
+
+impl<T> Once<T> {
+    /// Create a new uninitialized [`Once`].
+    pub const fn new() -> Self {
+        Once {
+            state: AtomicU8::new(INCOMPLETE),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Initialize the cell with the result of a function if it has not been
+    /// initialized yet, then return a reference to its value.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The initialization function, called at most once.
+    ///
+    /// # Note
+    ///
+    /// Calling this method from within the initialization function will
+    /// spin forever.
+    pub fn call_once<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if self
+            .state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+            .is_ok()
+        {
+            unsafe {
+                (*self.value.get()).write(f());
+            }
+            self.state.store(COMPLETE, Ordering::Release);
+        } else {
+            spin_loop(|| !self.is_completed());
+        }
+        unsafe { self.get_unchecked() }
+    }
+
+    /// Get a reference to the value if the cell has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        self.is_completed().then(|| unsafe { self.get_unchecked() })
+    }
+
+    /// Whether the cell has been initialized.
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    unsafe fn get_unchecked(&self) -> &T {
+        (*self.value.get()).assume_init_ref()
+    }
+}
+
+impl<T> Default for Once<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Once<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == COMPLETE {
+            unsafe {
+                self.value.get_mut().assume_init_drop();
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Once<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Some(value) => write!(f, "Once({:?})", value),
+            None => write!(f, "Once(<uninit>)"),
+        }
+    }
+}
+
+/// A value initialized on first access.
+pub struct Lazy<T, F = fn() -> T> {
+    cell: Once<T>,
+    init: UnsafeCell<Option<F>>,
+}
+
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /// Create a new [`Lazy`] value.
+    ///
+    /// # Arguments
+    ///
+    /// * `init` - The function building the value on first access.
+    pub const fn new(init: F) -> Self {
+        Lazy {
+            cell: Once::new(),
+            init: UnsafeCell::new(Some(init)),
+        }
+    }
+
+    /// Force the initialization of the value and return a reference to it.
+    pub fn force(this: &Self) -> &T {
+        this.cell.call_once(|| {
+            // Only the initializing caller can get here, the function is
+            // taken exactly once.
+            let init = unsafe { (*this.init.get()).take() };
+            init.expect("Lazy value initialization function already used")()
+        })
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Self::force(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn call_once_runs_once() {
+        let once = Once::new();
+        assert!(once.get().is_none());
+        assert_eq!(*once.call_once(|| 1), 1);
+        assert_eq!(*once.call_once(|| 2), 1);
+        assert_eq!(once.get(), Some(&1));
+    }
+
+    #[test_case]
+    fn lazy_initialized_on_access() {
+        let lazy: Lazy<u32> = Lazy::new(|| 42);
+        assert!(!lazy.cell.is_completed());
+        assert_eq!(*lazy, 42);
+        assert!(lazy.cell.is_completed());
+    }
+}
@@ -0,0 +1,192 @@
+//! A module containing the [`RwLock`] reader-writer lock.
+use crate::arch::spin_loop;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bit of the state set while a writer holds the lock.
+const WRITER: usize = 1;
+/// State increment of every reader holding the lock.
+const READER: usize = 1 << 1;
+
+/// A reader-writer lock allowing either many readers or a single writer,
+/// busy waiting until the protected data is available.
+///
+/// # Note
+///
+/// Writers are not given priority, a constant flow of readers can starve
+/// them.
+pub struct RwLock<T: ?Sized> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Create a new unlocked [`RwLock`] protecting a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The value to protect.
+    pub const fn new(data: T) -> Self {
+        RwLock {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consume the lock and return the protected value.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Acquire a shared access, spinning while a writer holds the lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        spin_loop(|| !self.acquire_read());
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Try to acquire a shared access without waiting.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.acquire_read()
+            .then_some(RwLockReadGuard { lock: self })
+    }
+
+    /// Acquire an exclusive access, spinning while any reader or writer holds
+    /// the lock.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        spin_loop(|| !self.acquire_write());
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Try to acquire an exclusive access without waiting.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.acquire_write()
+            .then_some(RwLockWriteGuard { lock: self })
+    }
+
+    /// Number of readers currently holding the lock.
+    pub fn reader_count(&self) -> usize {
+        self.state.load(Ordering::Relaxed) / READER
+    }
+
+    /// Whether a writer currently holds the lock.
+    pub fn is_write_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & WRITER != 0
+    }
+
+    /// Get a mutable reference to the protected value, no locking is needed
+    /// as the borrow checker guarantees exclusivity.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    fn acquire_read(&self) -> bool {
+        let state = self.state.load(Ordering::Relaxed);
+        state & WRITER == 0
+            && self
+                .state
+                .compare_exchange_weak(state, state + READER, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    fn acquire_write(&self) -> bool {
+        self.state
+            .compare_exchange_weak(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RwLock {{ readers: {}, writer: {} }}",
+            self.reader_count(),
+            self.is_write_locked()
+        )
+    }
+}
+
+/// A scoped shared access to the data of a [`RwLock`].
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_sub(READER, Ordering::Release);
+    }
+}
+
+/// A scoped exclusive access to the data of a [`RwLock`].
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.state.fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn many_readers() {
+        let lock = RwLock::new(3);
+        let first = lock.read();
+        let second = lock.try_read().expect("Readers should share the lock");
+        assert_eq!(*first + *second, 6);
+        assert_eq!(lock.reader_count(), 2);
+        assert!(lock.try_write().is_none());
+    }
+
+    #[test_case]
+    fn single_writer() {
+        let lock = RwLock::new(3);
+        {
+            let mut guard = lock.write();
+            *guard = 4;
+            assert!(lock.try_read().is_none());
+            assert!(lock.try_write().is_none());
+        }
+        assert!(!lock.is_write_locked());
+        assert_eq!(*lock.read(), 4);
+    }
+}
@@ -0,0 +1,145 @@
+//! A module containing the [`SpinLock`] mutual exclusion primitive.
+use crate::arch::spin_loop;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A mutual exclusion primitive busy waiting until the protected data is
+/// available.
+///
+/// # Note
+///
+/// Interrupts are left untouched, data shared with an interrupt handler
+/// should use an [`IrqSafeSpinLock`](super::IrqSafeSpinLock) instead.
+pub struct SpinLock<T: ?Sized> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for SpinLock<T> {}
+unsafe impl<T: ?Sized + Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// Create a new unlocked [`SpinLock`] protecting a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The value to protect.
+    pub const fn new(data: T) -> Self {
+        SpinLock {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consume the lock and return the protected value.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> SpinLock<T> {
+    /// Acquire the lock, spinning until it is available.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        spin_loop(|| !self.acquire());
+        SpinLockGuard { lock: self }
+    }
+
+    /// Try to acquire the lock without waiting.
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        self.acquire().then_some(SpinLockGuard { lock: self })
+    }
+
+    /// Whether the lock is currently held.
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Release the lock regardless of its current owner.
+    ///
+    /// # Safety
+    ///
+    /// The current owner must never access the data again, this is meant for
+    /// paths which will never return to the owner such as a panic.
+    pub unsafe fn force_unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    /// Get a mutable reference to the protected value, no locking is needed
+    /// as the borrow checker guarantees exclusivity.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    fn acquire(&self) -> bool {
+        self.locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+}
+
+impl<T: Default> Default for SpinLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for SpinLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SpinLock {{ locked: {} }}", self.is_locked())
+    }
+}
+
+/// A scoped access to the data of a [`SpinLock`], the lock is released when
+/// the guard is dropped.
+pub struct SpinLockGuard<'a, T: ?Sized> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T: ?Sized> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn lock_and_release() {
+        let lock = SpinLock::new(5);
+        {
+            let mut guard = lock.lock();
+            *guard += 1;
+            assert!(lock.is_locked());
+        }
+        assert!(!lock.is_locked());
+        assert_eq!(*lock.lock(), 6);
+    }
+
+    #[test_case]
+    fn try_lock_held() {
+        let lock = SpinLock::new(());
+        let guard = lock.try_lock();
+        assert!(guard.is_some());
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+        assert!(lock.try_lock().is_some());
+    }
+}
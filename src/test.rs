@@ -6,6 +6,8 @@ use core::panic::PanicInfo;
 /// Print panic infos and tries to quit Qemu with the [`ExitCode::Failed`]
 /// error code.
 pub fn panic_handler(info: &PanicInfo) -> ! {
+    crate::serial::set_buffered_transmit(false);
+    unsafe { crate::klog::force_unlock() };
     println!("[failed]\n");
     println!("Error: {}\n", info);
     qemu::exit(ExitCode::Failed);
@@ -39,5 +41,6 @@ pub fn runner(tests: &[&dyn Testable]) {
         test.run();
     }
 
+    crate::serial::flush_all();
     qemu::exit(ExitCode::Success);
 }
@@ -0,0 +1,260 @@
+//! A module providing a monotonic clock with nanosecond resolution, driven
+//! by the PIT and interpolated with the time stamp counter.
+use crate::arch::ia32::interrupts::pit::{self, TICK_COUNTER};
+use crate::arch::ia32::{halt, out_byte, pause, read_timestamp_counter};
+use crate::arch::interrupts;
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+pub use core::time::Duration;
+
+/// Unused port used to wait about a microsecond per write, before the time
+/// stamp counter is calibrated.
+const DELAY_PORT: u16 = 0x80;
+
+/// A measurement of the monotonic clock, in nanoseconds since the PIT was
+/// set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Get the current [`Instant`].
+    pub fn now() -> Self {
+        Instant(TICK_COUNTER.elasped_nanoseconds())
+    }
+
+    /// Get the time elapsed since this instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().saturating_duration_since(*self)
+    }
+
+    /// Get the time elapsed from an earlier instant to this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - The earlier instant.
+    ///
+    /// # Panics
+    ///
+    /// `earlier` must not be later than `self`.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier)
+            .expect("Supplied instant is later than self")
+    }
+
+    /// Get the time elapsed from an earlier instant to this one, or `None`
+    /// if it is later.
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - The earlier instant.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(Duration::from_nanos)
+    }
+
+    /// Get the time elapsed from an earlier instant to this one, or zero if
+    /// it is later.
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - The earlier instant.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    /// Get the instant `duration` after this one, or `None` on overflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration to add.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        let nanoseconds: u64 = duration.as_nanos().try_into().ok()?;
+        self.0.checked_add(nanoseconds).map(Instant)
+    }
+
+    /// Get the instant `duration` after this one, or the latest instant on
+    /// overflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration to add.
+    pub fn saturating_add(&self, duration: Duration) -> Instant {
+        self.checked_add(duration).unwrap_or(Instant(u64::MAX))
+    }
+
+    /// Get the instant `duration` before this one, or `None` on underflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration to subtract.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        let nanoseconds: u64 = duration.as_nanos().try_into().ok()?;
+        self.0.checked_sub(nanoseconds).map(Instant)
+    }
+
+    /// Get the nanoseconds elapsed since the PIT was set up.
+    pub fn as_nanos(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("Overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("Overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl fmt::Display for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:09}s",
+            self.0 / 1_000_000_000,
+            self.0 % 1_000_000_000
+        )
+    }
+}
+
+/// Get the time elapsed since the PIT was set up.
+pub fn uptime() -> Duration {
+    Duration::from_nanos(TICK_COUNTER.elasped_nanoseconds())
+}
+
+/// Get the PIT tick frequency, in Hz.
+pub fn tick_frequency() -> u16 {
+    TICK_COUNTER.frequency()
+}
+
+/// Change the PIT tick frequency, the clock keeps counting from its current
+/// value.
+///
+/// # Arguments
+///
+/// * `frequency` - The desired frequency, in Hz.
+///
+/// # Panics
+///
+/// The frequency must be at least [`pit::MIN_FREQUENCY`].
+pub fn set_tick_frequency(frequency: u16) {
+    pit::set_frequency(frequency);
+}
+
+/// Halt the processor until a duration has elapsed.
+///
+/// # Arguments
+///
+/// * `duration` - The duration to sleep for.
+///
+/// # Note
+///
+/// Ticks are not counted while interrupts are disabled, the function busy
+/// waits with [`udelay`] in that case.
+pub fn sleep(duration: Duration) {
+    if !interrupts::are_enabled() {
+        udelay(duration.as_micros().try_into().unwrap_or(u64::MAX));
+        return;
+    }
+    // A deadline past the latest instant is never reached.
+    let deadline = Instant::now().saturating_add(duration);
+    while Instant::now() < deadline {
+        halt();
+    }
+}
+
+/// Busy wait for a number of microseconds, it does not rely on interrupts
+/// and can be used by drivers in any context.
+///
+/// # Arguments
+///
+/// * `microseconds` - The number of microseconds to wait for.
+///
+/// # Note
+///
+/// Until the time stamp counter is calibrated over a PIT tick, the delay is
+/// approximated by writes to an unused port, each taking about a
+/// microsecond.
+pub fn udelay(microseconds: u64) {
+    match TICK_COUNTER.timestamps_for(microseconds.saturating_mul(1000)) {
+        Some(timestamps) => {
+            let start = read_timestamp_counter();
+            while read_timestamp_counter().wrapping_sub(start) < timestamps {
+                pause();
+            }
+        }
+        None => {
+            for _ in 0..microseconds {
+                unsafe {
+                    out_byte(DELAY_PORT, 0);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn instant_arithmetic() {
+        let instant = Instant(1_000);
+        let later = instant + Duration::from_nanos(500);
+        assert_eq!(later.as_nanos(), 1_500);
+        assert_eq!(later - instant, Duration::from_nanos(500));
+        assert_eq!(instant.checked_duration_since(later), None);
+        assert_eq!(instant.checked_sub(Duration::from_micros(2)), None);
+    }
+
+    #[test_case]
+    fn huge_duration_saturates() {
+        assert_eq!(Instant(1).checked_add(Duration::MAX), None);
+        assert_eq!(Instant(1).saturating_add(Duration::MAX), Instant(u64::MAX));
+        assert_eq!(
+            Instant::now().saturating_add(Duration::from_secs(u64::MAX / 2)),
+            Instant(u64::MAX)
+        );
+        assert_eq!(
+            Instant(1).saturating_add(Duration::from_nanos(2)),
+            Instant(3)
+        );
+    }
+
+    #[test_case]
+    fn clock_is_monotonic() {
+        let first = Instant::now();
+        udelay(10);
+        assert!(Instant::now() >= first);
+    }
+}
@@ -1 +1,2 @@
 pub mod bitfield;
+pub mod ring_buffer;
@@ -0,0 +1,146 @@
+//! A module containing a lock-free [`RingBuffer`] used to hand data over
+//! from interrupt handlers to normal code.
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A fixed capacity lock-free FIFO queue.
+///
+/// A single producer, usually an interrupt handler, pushes values while any
+/// number of consumers pop them.
+///
+/// # Note
+///
+/// The capacity `N` must be a power of two so the indexes can wrap around
+/// freely.
+pub struct RingBuffer<T: Copy, const N: usize> {
+    /// Storage of the values, slots between `tail` and `head` are
+    /// initialized.
+    slots: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// Index of the next slot to write, only modified by the producer.
+    head: AtomicUsize,
+    /// Index of the next slot to read.
+    tail: AtomicUsize,
+}
+
+// Values are copied in and out of the slots, the indexes guarantee a slot
+// is never read and written at the same time.
+unsafe impl<T: Copy + Send, const N: usize> Sync for RingBuffer<T, N> {}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    /// Create an empty [`RingBuffer`].
+    ///
+    /// # Panics
+    ///
+    /// The capacity must be a non null power of two.
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "Capacity must be a power of two");
+        RingBuffer {
+            slots: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Push a value at the end of the queue, the value is given back if the
+    /// queue is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to push.
+    ///
+    /// # Note
+    ///
+    /// Only a single producer may push values at a time.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head.wrapping_sub(self.tail.load(Ordering::Acquire)) == N {
+            return Err(value);
+        }
+        unsafe {
+            (*self.slots.get())[head % N] = MaybeUninit::new(value);
+        }
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Pop the value at the front of the queue, if any.
+    pub fn pop(&self) -> Option<T> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            if tail == self.head.load(Ordering::Acquire) {
+                return None;
+            }
+            // The slot cannot be overwritten before the tail moves past it,
+            // the copy is only kept if this consumer is the one moving it.
+            let value = unsafe { (*self.slots.get())[tail % N].assume_init() };
+            match self.tail.compare_exchange_weak(
+                tail,
+                tail.wrapping_add(1),
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(value),
+                Err(current) => tail = current,
+            }
+        }
+    }
+
+    /// Get the number of values in the queue.
+    pub fn len(&self) -> usize {
+        self.head
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.tail.load(Ordering::Acquire))
+    }
+
+    /// Whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Get the maximum number of values in the queue.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<T: Copy, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn fifo_order() {
+        let buffer: RingBuffer<u8, 4> = RingBuffer::new();
+        assert!(buffer.is_empty());
+        buffer.push(1).unwrap();
+        buffer.push(2).unwrap();
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.pop(), Some(1));
+        assert_eq!(buffer.pop(), Some(2));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test_case]
+    fn full_and_wrap() {
+        let buffer: RingBuffer<u8, 2> = RingBuffer::new();
+        for round in 0..3 {
+            buffer.push(round).unwrap();
+            buffer.push(round + 10).unwrap();
+            assert!(buffer.is_full());
+            assert_eq!(buffer.push(0), Err(0));
+            assert_eq!(buffer.pop(), Some(round));
+            assert_eq!(buffer.pop(), Some(round + 10));
+        }
+    }
+}
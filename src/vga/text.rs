@@ -1,8 +1,28 @@
+use crate::arch::io::port::Port;
+use crate::arch::io::register::{ReadRegister, WriteRegister};
+use crate::utils::bitfield::*;
 use core::fmt;
 use volatile::Volatile;
 
-const BUFFER_HEIGHT: usize = 25;
-const BUFFER_WIDTH: usize = 80;
+/// Number of rows of the text mode buffer.
+pub const BUFFER_HEIGHT: usize = 25;
+/// Number of columns of the text mode buffer.
+pub const BUFFER_WIDTH: usize = 80;
+
+/// CRT controller index register, selecting the register accessed through
+/// the data port.
+const CRTC_INDEX: u16 = 0x3D4;
+const CRTC_DATA: u16 = 0x3D5;
+
+/// CRT controller registers.
+mod crtc {
+    pub const CURSOR_START: u8 = 0x0A;
+    pub const CURSOR_END: u8 = 0x0B;
+    pub const CURSOR_LOCATION_HIGH: u8 = 0x0E;
+    pub const CURSOR_LOCATION_LOW: u8 = 0x0F;
+    /// Hide the cursor, in the cursor start register.
+    pub const CURSOR_DISABLE: usize = 5;
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -47,6 +67,21 @@ impl ColorCode {
     fn new(foreground: Color, background: Color) -> ColorCode {
         ColorCode((background as u8) << 4 | (foreground as u8))
     }
+
+    /// Return the code with another foreground color.
+    fn foreground(self, color: Color) -> Self {
+        ColorCode(self.0.set_bits(0..4, color as u8))
+    }
+
+    /// Return the code with another background color.
+    ///
+    /// # Note
+    ///
+    /// The highest bit of the background selects blinking unless blinking is
+    /// disabled in the attribute controller, the last 8 colors may blink.
+    fn background(self, color: Color) -> Self {
+        ColorCode(self.0.set_bits(4..8, color as u8))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -61,22 +96,181 @@ struct Buffer {
     chars: [[Volatile<ScreenChar>; BUFFER_WIDTH]; BUFFER_HEIGHT],
 }
 
+/// The shape of the cursor, as the first and last scanlines it covers
+/// within a character cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorShape {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl CursorShape {
+    /// The last scanline of a character cell.
+    pub const MAX_SCANLINE: u8 = 15;
+    /// The usual two scanlines underline.
+    pub const UNDERLINE: Self = CursorShape { start: 14, end: 15 };
+    /// A cursor covering the whole cell.
+    pub const BLOCK: Self = CursorShape { start: 0, end: 15 };
+}
+
+/// The VGA CRT controller, driving the hardware cursor.
+struct Crtc {
+    index: Port<u8>,
+    data: Port<u8>,
+}
+
+impl Crtc {
+    const fn new() -> Self {
+        Crtc {
+            index: Port::new(CRTC_INDEX),
+            data: Port::new(CRTC_DATA),
+        }
+    }
+
+    fn read(&self, register: u8) -> u8 {
+        unsafe {
+            self.index.write(register);
+            self.data.read()
+        }
+    }
+
+    fn write(&self, register: u8, value: u8) {
+        unsafe {
+            self.index.write(register);
+            self.data.write(value);
+        }
+    }
+}
+
+/// A writer printing text to the VGA text mode buffer.
+///
+/// Text goes at the cursor position, a new line on the bottom row scrolls
+/// the screen up. The hardware cursor follows the writer.
 pub struct Writer {
+    row: usize,
     column: usize,
     color_code: ColorCode,
     buffer: &'static mut Buffer,
+    crtc: Crtc,
 }
 
 impl Writer {
+    /// Set the color of the characters written next.
+    pub fn set_foreground(&mut self, color: Color) {
+        self.color_code = self.color_code.foreground(color);
+    }
+
+    /// Set the background color of the characters written next, and of the
+    /// cleared cells.
+    pub fn set_background(&mut self, color: Color) {
+        self.color_code = self.color_code.background(color);
+    }
+
+    /// Get the cursor position, as a `(row, column)` pair.
+    pub fn position(&self) -> (usize, usize) {
+        (self.row, self.column)
+    }
+
+    /// Move the cursor, text is then written from this position.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The row, 0 being the top one.
+    /// * `column` - The column, 0 being the leftmost one.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the position is outside of the screen.
+    pub fn set_position(&mut self, row: usize, column: usize) {
+        assert!(
+            row < BUFFER_HEIGHT && column < BUFFER_WIDTH,
+            "VGA position ({}, {}) is outside of the screen",
+            row,
+            column
+        );
+        self.row = row;
+        self.column = column;
+        self.update_cursor();
+    }
+
+    /// Show or hide the hardware cursor.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        let start = self.crtc.read(crtc::CURSOR_START);
+        self.crtc.write(
+            crtc::CURSOR_START,
+            start.set_bit(crtc::CURSOR_DISABLE, !visible),
+        );
+    }
+
+    /// Set the scanlines covered by the hardware cursor.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if a scanline is greater than
+    /// [`CursorShape::MAX_SCANLINE`].
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) {
+        assert!(
+            shape.start <= CursorShape::MAX_SCANLINE && shape.end <= CursorShape::MAX_SCANLINE,
+            "Invalid cursor shape {:?}",
+            shape
+        );
+        // The upper bits of both registers hold the visibility and skew.
+        let start = self.crtc.read(crtc::CURSOR_START);
+        self.crtc
+            .write(crtc::CURSOR_START, start.set_bits(0..5, shape.start));
+        let end = self.crtc.read(crtc::CURSOR_END);
+        self.crtc
+            .write(crtc::CURSOR_END, end.set_bits(0..5, shape.end));
+    }
+
+    /// Blank the whole screen with the current background color and move the
+    /// cursor to the top left corner.
+    pub fn clear(&mut self) {
+        for row in 0..BUFFER_HEIGHT {
+            self.clear_row(row);
+        }
+        self.set_position(0, 0);
+    }
+
+    /// Write a string at a position without moving the cursor, e.g. for a
+    /// status line. The string is cut at the end of the row.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The row, 0 being the top one.
+    /// * `column` - The column of the first character.
+    /// * `s` - The string, new lines are shown as unprintable characters.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the position is outside of the screen.
+    pub fn write_at(&mut self, row: usize, column: usize, s: &str) {
+        assert!(
+            row < BUFFER_HEIGHT && column < BUFFER_WIDTH,
+            "VGA position ({}, {}) is outside of the screen",
+            row,
+            column
+        );
+        let cells = self.buffer.chars[row][column..].iter_mut();
+        for (cell, byte) in cells.zip(s.bytes()) {
+            cell.write(ScreenChar {
+                ascii_character: printable(byte),
+                color_code: self.color_code,
+            });
+        }
+    }
+
     pub fn write_byte(&mut self, byte: u8) {
         match byte {
             b'\n' => self.new_line(),
+            // Move back, the erased character is overwritten by the next one.
+            0x08 => self.column = self.column.saturating_sub(1),
             byte => {
                 if self.column >= BUFFER_WIDTH {
                     self.new_line();
                 }
 
-                let row = BUFFER_HEIGHT - 1;
+                let row = self.row;
                 let col = self.column;
 
                 let color_code = self.color_code;
@@ -92,14 +286,22 @@ impl Writer {
     pub fn write_string(&mut self, s: &str) {
         for byte in s.bytes() {
             match byte {
-                0x20..=0x7e | b'\n' => self.write_byte(byte),
-                _ => self.write_byte(0xfe),
+                b'\n' | 0x08 => self.write_byte(byte),
+                _ => self.write_byte(printable(byte)),
             }
         }
+        // The cursor is moved once, port accesses are slow.
+        self.update_cursor();
     }
 
-    /// Shift all lines up in the vga text mode buffer and clear the last line.
+    /// Go to the start of the next row, shifting all lines up in the vga
+    /// text mode buffer and clearing the last line when on the bottom row.
     fn new_line(&mut self) {
+        self.column = 0;
+        if self.row < BUFFER_HEIGHT - 1 {
+            self.row += 1;
+            return;
+        }
         for row in 1..BUFFER_HEIGHT {
             for col in 0..BUFFER_WIDTH {
                 let c = self.buffer.chars[row][col].read();
@@ -107,7 +309,6 @@ impl Writer {
             }
         }
         self.clear_row(BUFFER_HEIGHT - 1);
-        self.column = 0;
     }
 
     fn clear_row(&mut self, row: usize) {
@@ -119,14 +320,36 @@ impl Writer {
             self.buffer.chars[row][col].write(blank);
         }
     }
+
+    /// Move the hardware cursor to the writer position.
+    fn update_cursor(&self) {
+        // After the last column, the cursor stays on it until the next
+        // character wraps.
+        let column = self.column.min(BUFFER_WIDTH - 1);
+        let location = (self.row * BUFFER_WIDTH + column) as u16;
+        self.crtc
+            .write(crtc::CURSOR_LOCATION_HIGH, location.get_bits(8..16) as u8);
+        self.crtc
+            .write(crtc::CURSOR_LOCATION_LOW, location.get_bits(0..8) as u8);
+    }
+}
+
+/// Replace the bytes outside of printable ASCII with a square.
+fn printable(byte: u8) -> u8 {
+    match byte {
+        0x20..=0x7e => byte,
+        _ => 0xfe,
+    }
 }
 
 impl Default for Writer {
     fn default() -> Self {
         Writer {
+            row: BUFFER_HEIGHT - 1,
             column: 0,
             color_code: ColorCode::new(Color::White, Color::Black),
             buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
+            crtc: Crtc::new(),
         }
     }
 }
@@ -137,3 +360,27 @@ impl fmt::Write for Writer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn color_code_setters() {
+        let code = ColorCode::new(Color::White, Color::Black);
+        assert_eq!(code.0, 0x0F);
+        assert_eq!(code.foreground(Color::Yellow).0, 0x0E);
+        assert_eq!(code.background(Color::Blue).0, 0x1F);
+        assert_eq!(
+            code.foreground(Color::Red).background(Color::LightGray),
+            ColorCode::new(Color::Red, Color::LightGray)
+        );
+    }
+
+    #[test_case]
+    fn unprintable_bytes() {
+        assert_eq!(printable(b'a'), b'a');
+        assert_eq!(printable(b'\t'), 0xfe);
+        assert_eq!(printable(0x80), 0xfe);
+    }
+}
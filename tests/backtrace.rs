@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(flint::test::runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+#[cfg(target_arch = "x86_64")]
+bootloader::entry_point!(main);
+
+#[cfg(target_arch = "x86_64")]
+fn main(boot_info: &'static bootloader::BootInfo) -> ! {
+    flint::klog::init().ok();
+    // Frame records are checked against the page tables.
+    flint::mm::setup(boot_info);
+    test_main();
+
+    flint::arch::endless();
+}
+
+// Backtraces are only available on the x86_64 kernel, the test kernel then
+// runs no test.
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+#[cfg(target_arch = "x86")]
+fn main() -> ! {
+    flint::klog::init().ok();
+    test_main();
+
+    flint::arch::endless();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    flint::test::panic_handler(info)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod backtrace {
+    use flint::debug::backtrace::{Backtrace, Frame};
+
+    /// Capture a backtrace, its first frame returns to the caller.
+    #[inline(never)]
+    fn first_frame() -> Option<Frame> {
+        Backtrace::capture().next()
+    }
+
+    /// Get the first frame captured by a callee, along with this function's
+    /// address.
+    #[inline(never)]
+    fn caller() -> (u64, Option<Frame>) {
+        (caller as *const () as u64, first_frame())
+    }
+
+    #[test_case]
+    fn capture_returns_to_caller() {
+        let (caller, frame) = caller();
+        let frame = frame.expect("No frame captured");
+        assert!(
+            frame.address > caller && frame.address - caller < 0x1000,
+            "{:#X} is not a return address in the caller at {:#X}",
+            frame.address,
+            caller
+        );
+    }
+
+    #[test_case]
+    fn capture_walks_the_callers() {
+        // The test runner and the test kernel's entry are on the stack.
+        assert!(Backtrace::capture().count() >= 2);
+    }
+}
@@ -12,8 +12,16 @@ fn dummy_integration_test() {
     assert_eq!(1, 1);
 }
 
+#[cfg(target_arch = "x86_64")]
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
+    main()
+}
+
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+fn main() -> ! {
     test_main();
 
     flint::arch::endless();
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(flint::test::runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::arch::asm;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_arch = "x86")]
+use flint::arch::ia32::{
+    interrupts::{frame::InterruptStackFrame, idt},
+    mm::gdt,
+};
+#[cfg(target_arch = "x86_64")]
+use flint::arch::ia32e::{
+    interrupts::{frame::InterruptStackFrame, idt},
+    mm::gdt,
+};
+
+#[cfg(target_arch = "x86_64")]
+bootloader::entry_point!(bootloader_main);
+
+#[cfg(target_arch = "x86_64")]
+fn bootloader_main(_boot_info: &'static bootloader::BootInfo) -> ! {
+    main()
+}
+
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+fn main() -> ! {
+    flint::klog::init().ok();
+    gdt::setup_gdt();
+    idt::setup_idt();
+    test_main();
+
+    flint::arch::endless();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    flint::test::panic_handler(info)
+}
+
+static BREAKPOINTS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_breakpoint(_frame: &InterruptStackFrame) {
+    BREAKPOINTS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test_case]
+fn execution_resumes() {
+    unsafe { asm!("int3") };
+}
+
+#[test_case]
+fn callback_is_called() {
+    idt::set_breakpoint_callback(Some(count_breakpoint));
+    let before = BREAKPOINTS.load(Ordering::SeqCst);
+    unsafe { asm!("int3") };
+    unsafe { asm!("int3") };
+    idt::set_breakpoint_callback(None);
+    unsafe { asm!("int3") };
+    assert_eq!(BREAKPOINTS.load(Ordering::SeqCst), before + 2);
+}
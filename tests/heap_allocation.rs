@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(flint::test::runner)]
+#![reexport_test_harness_main = "test_main"]
+
+// The 32 bits kernel has no heap, the test kernel then runs no test.
+#[cfg(target_arch = "x86_64")]
+extern crate alloc;
+
+use core::panic::PanicInfo;
+
+#[cfg(target_arch = "x86_64")]
+bootloader::entry_point!(main);
+
+#[cfg(target_arch = "x86_64")]
+fn main(boot_info: &'static bootloader::BootInfo) -> ! {
+    flint::klog::init().ok();
+    flint::mm::setup(boot_info);
+    test_main();
+
+    flint::arch::endless();
+}
+
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+#[cfg(target_arch = "x86")]
+fn main() -> ! {
+    flint::klog::init().ok();
+    test_main();
+
+    flint::arch::endless();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    flint::test::panic_handler(info)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod heap {
+    use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+    use flint::mm::heap::HEAP_SIZE;
+
+    #[test_case]
+    fn simple_allocation() {
+        let first = Box::new(41);
+        let second = Box::new(13);
+        assert_eq!(*first, 41);
+        assert_eq!(*second, 13);
+    }
+
+    #[test_case]
+    fn large_vec() {
+        let n = 1000;
+        let vec: Vec<u64> = (0..n).collect();
+        assert_eq!(vec.iter().sum::<u64>(), (n - 1) * n / 2);
+    }
+
+    #[test_case]
+    fn collections() {
+        let mut map = BTreeMap::new();
+        map.insert(String::from("flint"), 1);
+        map.insert(String::from("kernel"), 2);
+        assert_eq!(map.get("kernel"), Some(&2));
+    }
+
+    #[test_case]
+    fn many_boxes() {
+        for i in 0..HEAP_SIZE {
+            let x = Box::new(i);
+            assert_eq!(*x, i);
+        }
+    }
+}
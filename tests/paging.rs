@@ -0,0 +1,147 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(flint::test::runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+#[cfg(target_arch = "x86_64")]
+bootloader::entry_point!(main);
+
+#[cfg(target_arch = "x86_64")]
+fn main(boot_info: &'static bootloader::BootInfo) -> ! {
+    flint::klog::init().ok();
+    flint::mm::setup(boot_info);
+    test_main();
+
+    flint::arch::endless();
+}
+
+// The 32 bits kernel does not enable paging, the test kernel then runs no
+// test.
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+#[cfg(target_arch = "x86")]
+fn main() -> ! {
+    flint::klog::init().ok();
+    test_main();
+
+    flint::arch::endless();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    flint::test::panic_handler(info)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod paging {
+    use core::ptr;
+    use flint::arch::ia32::address::{PhysicalAddress, VirtualAddress};
+    use flint::arch::ia32e::mm::paging::{
+        flags, physical_memory_offset, AddressSpace, PageFlags, PageSize, PagingError,
+    };
+    use flint::mm::frame::{self, FrameAllocator};
+
+    /// A page no kernel mapping uses.
+    const TEST_PAGE: u64 = flint::mm::heap::TEST_PAGE_ADDRESS;
+
+    /// Map the test page on a new frame, returning the frame.
+    fn map_test_page(space: &mut AddressSpace, page_flags: PageFlags) -> PhysicalAddress {
+        let mut allocator = frame::allocator();
+        let frame = allocator.allocate().expect("No frame available");
+        unsafe {
+            space
+                .map(
+                    VirtualAddress::new(TEST_PAGE),
+                    frame,
+                    PageSize::Size4KiB,
+                    page_flags,
+                    &mut *allocator,
+                )
+                .expect("Cannot map the test page");
+        }
+        frame
+    }
+
+    /// Unmap the test page and release its frame.
+    fn unmap_test_page(space: &mut AddressSpace) -> PhysicalAddress {
+        let frame = unsafe { space.unmap(VirtualAddress::new(TEST_PAGE)) }
+            .expect("Cannot unmap the test page");
+        frame::allocator().free(frame);
+        frame
+    }
+
+    #[test_case]
+    fn map_translate_update_unmap() {
+        let mut space = AddressSpace::active();
+        let page = VirtualAddress::new(TEST_PAGE);
+        let frame = map_test_page(&mut space, PageFlags(flags::WRITABLE | flags::NO_EXECUTE));
+
+        assert_eq!(
+            space.translate(VirtualAddress::new(TEST_PAGE + 0x123)),
+            Some(PhysicalAddress::new(u64::from(frame) + 0x123))
+        );
+        // Both mappings of the frame see the same memory.
+        let physical = (physical_memory_offset() + u64::from(frame)) as *const u64;
+        unsafe {
+            ptr::write_volatile(TEST_PAGE as *mut u64, 0xF117_F117);
+            assert_eq!(ptr::read_volatile(physical), 0xF117_F117);
+        }
+
+        unsafe { space.update_flags(page, PageFlags(flags::NO_EXECUTE)) }.unwrap();
+        let page_flags = space.page_flags(page).unwrap();
+        assert!(page_flags.contains(PageFlags(flags::PRESENT | flags::NO_EXECUTE)));
+        assert!(!page_flags.contains(PageFlags(flags::WRITABLE)));
+        // The frame is kept.
+        assert_eq!(
+            space.translate(page),
+            Some(PhysicalAddress::new(u64::from(frame)))
+        );
+
+        assert_eq!(unmap_test_page(&mut space), frame);
+        assert_eq!(space.translate(page), None);
+        assert_eq!(space.page_flags(page), None);
+    }
+
+    #[test_case]
+    fn mapping_errors() {
+        let mut space = AddressSpace::active();
+        let page = VirtualAddress::new(TEST_PAGE);
+        let frame = map_test_page(&mut space, PageFlags(flags::NO_EXECUTE));
+
+        let mut allocator = frame::allocator();
+        unsafe {
+            assert_eq!(
+                space.map(
+                    page,
+                    frame,
+                    PageSize::Size4KiB,
+                    PageFlags(0),
+                    &mut *allocator
+                ),
+                Err(PagingError::AlreadyMapped)
+            );
+            assert_eq!(
+                space.map(
+                    VirtualAddress::new(TEST_PAGE + 8),
+                    frame,
+                    PageSize::Size4KiB,
+                    PageFlags(0),
+                    &mut *allocator
+                ),
+                Err(PagingError::Unaligned)
+            );
+        }
+        drop(allocator);
+
+        unmap_test_page(&mut space);
+        assert_eq!(unsafe { space.unmap(page) }, Err(PagingError::NotMapped));
+        assert_eq!(
+            unsafe { space.update_flags(page, PageFlags(0)) },
+            Err(PagingError::NotMapped)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+#![cfg_attr(target_arch = "x86_64", feature(abi_x86_interrupt))]
+
+use core::panic::PanicInfo;
+use flint::print;
+use flint::qemu::{self, ExitCode};
+
+#[cfg(target_arch = "x86_64")]
+bootloader::entry_point!(main);
+
+#[cfg(target_arch = "x86_64")]
+fn main(_boot_info: &'static bootloader::BootInfo) -> ! {
+    use flint::arch::ia32e::interrupts::idt::{self, vector};
+    use flint::arch::ia32e::mm::gdt;
+
+    flint::klog::init().ok();
+    print!("stack_overflow::stack_overflow...\t");
+
+    gdt::setup_gdt();
+    idt::setup_idt();
+    // The predefined gate already uses a dedicated stack, only the handler
+    // is replaced.
+    idt::register(vector::DOUBLE_FAULT, double_fault);
+
+    stack_overflow();
+
+    panic!("Execution continued after stack overflow");
+}
+
+#[cfg(target_arch = "x86")]
+flint::multiboot_entry!(main);
+
+/// The 32 bits kernel has no dedicated double fault stack, a stack overflow
+/// triple faults.
+#[cfg(target_arch = "x86")]
+fn main() -> ! {
+    flint::klog::init().ok();
+    print!("stack_overflow::stack_overflow...\t[ignored]\n");
+    qemu::exit(ExitCode::Success);
+    flint::arch::endless();
+}
+
+#[cfg(target_arch = "x86_64")]
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+    stack_overflow();
+    // Prevent tail call optimization.
+    volatile::Volatile::new(0).read();
+}
+
+#[cfg(target_arch = "x86_64")]
+extern "x86-interrupt" fn double_fault(
+    _frame: flint::arch::ia32e::interrupts::frame::InterruptStackFrame,
+    _err: u64,
+) -> ! {
+    print!("[ok]\n");
+    qemu::exit(ExitCode::Success);
+    flint::arch::endless();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    flint::test::panic_handler(info)
+}
@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+"""Fill the symbol table reserved in the kernel's `.flint_symbols` section.
+
+The table is read by `debug::backtrace` to resolve return addresses, its
+layout is described there. The kernel ELF file is patched in place.
+
+Usage: embed_symbols.py <kernel>
+"""
+import os
+import re
+import struct
+import subprocess
+import sys
+
+SECTION = b".flint_symbols"
+MAGIC = b"FSYM"
+ENTRY = struct.Struct("<QIIII")
+HASH = re.compile(r"::h[0-9a-f]{16}$")
+
+
+def find_section(image):
+    """Get the file offset and size of the symbol table section."""
+    shoff, = struct.unpack_from("<Q", image, 0x28)
+    shentsize, shnum, shstrndx = struct.unpack_from("<HHH", image, 0x3A)
+    headers = [
+        struct.unpack_from("<IIQQQQ", image, shoff + index * shentsize)
+        for index in range(shnum)
+    ]
+    strings = headers[shstrndx][4]
+    for name, _, _, _, offset, size in headers:
+        end = image.index(b"\0", strings + name)
+        if image[strings + name:end] == SECTION:
+            return offset, size
+    sys.exit(f"{SECTION.decode()} section not found")
+
+
+def read_symbols(kernel):
+    """Get the function symbols sorted by address, along their size."""
+    nm = os.environ.get("NM", "nm")
+    output = subprocess.run(
+        [nm, "--defined-only", "--print-size", "--demangle", kernel],
+        check=True,
+        capture_output=True,
+        text=True,
+    ).stdout
+    symbols = {}
+    for line in output.splitlines():
+        fields = line.split(maxsplit=3)
+        if len(fields) != 4 or fields[2] not in "tTwW":
+            continue
+        address, size, _, name = fields
+        symbols.setdefault(int(address, 16), (int(size, 16), HASH.sub("", name)))
+    return sorted((address, size, name) for address, (size, name) in symbols.items())
+
+
+def encode(symbols):
+    """Encode the symbol table."""
+    header = MAGIC + struct.pack("<I", len(symbols))
+    names_offset = len(header) + len(symbols) * ENTRY.size
+    entries, names = b"", b""
+    for address, size, name in symbols:
+        name = name.encode()
+        entries += ENTRY.pack(address, size, names_offset + len(names), len(name), 0)
+        names += name
+    return header + entries + names
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit(__doc__)
+    kernel = sys.argv[1]
+    with open(kernel, "rb") as file:
+        image = bytearray(file.read())
+    offset, size = find_section(image)
+    table = encode(read_symbols(kernel))
+    if len(table) > size:
+        sys.exit(
+            f"Symbol table too large ({len(table)} bytes), "
+            f"increase SYMBOL_TABLE_SIZE above {size}"
+        )
+    image[offset:offset + size] = table.ljust(size, b"\0")
+    with open(kernel, "wb") as file:
+        file.write(image)
+
+
+if __name__ == "__main__":
+    main()
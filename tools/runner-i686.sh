@@ -0,0 +1,24 @@
+#!/bin/sh
+# Boot the 32 bits kernel with QEMU through its multiboot header.
+#
+# Test kernels, built in the deps directory, run without display under a
+# timeout. They exit through the isa-debug-exit device, whose status 33 is
+# mapped to success as bootimage does for the x86_64 kernel.
+kernel="$1"
+shift
+qemu="qemu-system-i386 -device isa-debug-exit,iobase=0xf4,iosize=0x04 -serial stdio"
+case "$kernel" in
+*/deps/*)
+    timeout 300 $qemu -display none -kernel "$kernel" "$@"
+    status=$?
+    case $status in
+    33) exit 0 ;;
+    # A kernel stopping without the debug device did not finish its tests.
+    0) exit 1 ;;
+    *) exit $status ;;
+    esac
+    ;;
+*)
+    exec $qemu -kernel "$kernel" "$@"
+    ;;
+esac
@@ -0,0 +1,5 @@
+#!/bin/sh
+# Embed the symbol table in the kernel, then boot it with bootimage.
+set -e
+python3 "$(dirname "$0")/embed_symbols.py" "$1"
+exec bootimage runner "$@"